@@ -0,0 +1,160 @@
+//! Build, traversal and lookup timings, plus memory estimates, for the trie
+//! variants on 200k random words. Run with `cargo bench`; with
+//! `--features rayon` it also compares sequential and parallel builds on 2M
+//! words and on 2M path-like keys that all start with '/'.
+
+use bustub::{ArenaTrie, AsciiTrie, SlabTrie, Trie, TstTrie};
+
+// Small deterministic PRNG, so every run measures the same words
+struct XorShift64(u64);
+
+impl XorShift64 {
+    fn next_u64(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.0 = x;
+        x
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+// Random lowercase words of 3 to 10 letters
+fn random_words(rng: &mut XorShift64, count: usize) -> Vec<String> {
+    (0..count)
+        .map(|_| {
+            let len = 3 + rng.below(8);
+            (0..len)
+                .map(|_| (b'a' + rng.below(26) as u8) as char)
+                .collect()
+        })
+        .collect()
+}
+
+fn time<R>(label: &str, f: impl FnOnce() -> R) -> R {
+    let start = std::time::Instant::now();
+    let result = f();
+    println!("{:<40} {:>10.2?}", label, start.elapsed());
+    result
+}
+
+// Sequential and parallel builds of larger lists
+#[cfg(feature = "rayon")]
+fn parallel_builds(rng: &mut XorShift64) {
+    use rayon::prelude::*;
+
+    let many_words = random_words(rng, 2_000_000);
+    let paths: Vec<String> = many_words
+        .iter()
+        .map(|word| format!("/srv/{}/{}", &word[..2], word))
+        .collect();
+    for (label, keys) in [("2M words", many_words), ("2M paths", paths)] {
+        let sequential: Trie<usize> = time(&format!("Trie build, {}", label), || {
+            keys.iter().map(String::as_str).zip(0..).collect()
+        });
+        let parallel = time(&format!("Trie::par_from_iter, {}", label), || {
+            Trie::par_from_iter(keys.par_iter().map(String::as_str).zip(0..keys.len()))
+        });
+        assert!(parallel == sequential);
+    }
+}
+
+fn main() {
+    let mut rng = XorShift64(0x9e37_79b9_7f4a_7c15);
+    let words = random_words(&mut rng, 200_000);
+
+    let trie = time("Trie build", || {
+        let mut trie = Trie::new();
+        for (i, word) in words.iter().enumerate() {
+            trie.insert(word, i);
+        }
+        trie
+    });
+    #[cfg(feature = "rayon")]
+    parallel_builds(&mut rng);
+
+    let ascii_trie = time("AsciiTrie build", || {
+        let mut trie = AsciiTrie::new();
+        for (i, word) in words.iter().enumerate() {
+            trie.insert(word, i).unwrap();
+        }
+        trie
+    });
+
+    let arena_trie = time("ArenaTrie build", || {
+        let mut trie = ArenaTrie::new();
+        for (i, word) in words.iter().enumerate() {
+            trie.insert(word, i);
+        }
+        trie
+    });
+
+    let slab_trie = time("SlabTrie build", || {
+        let mut trie = SlabTrie::new();
+        for (i, word) in words.iter().enumerate() {
+            trie.insert(word, i);
+        }
+        trie
+    });
+
+    let tst_trie = time("TstTrie build", || {
+        let mut trie = TstTrie::new();
+        for (i, word) in words.iter().enumerate() {
+            trie.insert(word, i);
+        }
+        trie
+    });
+
+    // Full iteration, building every key, on each layout
+    let count = time("Trie iteration with keys", || trie.iter().count());
+    assert_eq!(count, trie.len());
+    let arena_count = time("ArenaTrie iteration with keys", || {
+        arena_trie.iter().count()
+    });
+    assert_eq!(arena_count, count);
+    let slab_count = time("SlabTrie iteration with keys", || slab_trie.iter().count());
+    assert_eq!(slab_count, count);
+    let arena_count = time("ArenaTrie value scan", || arena_trie.values().count());
+    assert_eq!(arena_count, count);
+
+    let found = time("Trie lookup", || {
+        words.iter().filter(|word| trie.contains_key(word)).count()
+    });
+    assert_eq!(found, words.len());
+    let found = time("SlabTrie lookup", || {
+        words
+            .iter()
+            .filter(|word| slab_trie.contains_key(word))
+            .count()
+    });
+    assert_eq!(found, words.len());
+    let found = time("AsciiTrie lookup", || {
+        words
+            .iter()
+            .filter(|word| ascii_trie.contains_key(word).unwrap())
+            .count()
+    });
+    assert_eq!(found, words.len());
+    let found = time("TstTrie lookup", || {
+        words
+            .iter()
+            .filter(|word| tst_trie.contains_key(word))
+            .count()
+    });
+    assert_eq!(found, words.len());
+
+    println!(
+        "{:<40} {:>10} KiB",
+        "Trie memory estimate",
+        trie.memory_estimate() / 1024
+    );
+    println!(
+        "{:<40} {:>10} KiB",
+        "TstTrie memory estimate",
+        tst_trie.memory_estimate() / 1024
+    );
+}
@@ -0,0 +1,742 @@
+use crate::error::TrieError;
+
+const ARENA_ROOT: u32 = 0;
+
+// Node of an ArenaTrie. Children are arena indices, sorted by char.
+#[derive(Debug, PartialEq)]
+struct ArenaNode<T> {
+    key_char: char,
+    value: Option<T>,
+    children: Vec<(char, u32)>,
+}
+
+impl<T> ArenaNode<T> {
+    fn new(key_char: char) -> ArenaNode<T> {
+        ArenaNode {
+            key_char,
+            value: None,
+            children: Vec::new(),
+        }
+    }
+}
+
+/// Trie whose nodes all live in one `Vec` and refer to their children by
+/// index. The flat layout makes traversal cache friendly and dropping the
+/// trie never recurses.
+///
+/// It covers [`Trie`](crate::Trie)'s core map API: inserts and removals,
+/// lookups, `len`, mutable access and prefix iteration. Not carried over
+/// are key normalizers, lazy removal and `compact`, splitting and merging
+/// tries, rank and select, and the export, serialization and debug
+/// formats; those need a [`Trie`](crate::Trie).
+#[derive(Debug)]
+pub struct ArenaTrie<T> {
+    // Removed nodes go on a free list so their slots are reused without
+    // moving (and so invalidating) any other node
+    nodes: Vec<ArenaNode<T>>,
+    free: Vec<u32>,
+    count: usize,
+}
+
+impl<T> ArenaTrie<T> {
+    /// Creates an empty trie.
+    pub fn new() -> ArenaTrie<T> {
+        ArenaTrie {
+            nodes: vec![ArenaNode::new('\0')],
+            free: Vec::new(),
+            count: 0,
+        }
+    }
+
+    /// Creates an empty trie with room for `nodes` nodes, root included,
+    /// allocated up front in one block. Inserts that stay within it never
+    /// reallocate the arena, e.g. when loading a dictionary whose node
+    /// count is known from a previous build.
+    pub fn with_capacity(nodes: usize) -> ArenaTrie<T> {
+        let mut arena = Vec::with_capacity(nodes.max(1));
+        arena.push(ArenaNode::new('\0'));
+        ArenaTrie {
+            nodes: arena,
+            free: Vec::new(),
+            count: 0,
+        }
+    }
+
+    fn node(&self, index: u32) -> &ArenaNode<T> {
+        &self.nodes[index as usize]
+    }
+
+    fn child(&self, index: u32, key_char: char) -> Option<u32> {
+        let children = &self.node(index).children;
+        children
+            .binary_search_by_key(&key_char, |(c, _)| *c)
+            .ok()
+            .map(|i| children[i].1)
+    }
+
+    // Take a slot from the free list, or grow the arena if it is empty
+    fn alloc(&mut self, key_char: char) -> u32 {
+        match self.free.pop() {
+            Some(index) => {
+                self.nodes[index as usize] = ArenaNode::new(key_char);
+                index
+            }
+            None => {
+                let index =
+                    u32::try_from(self.nodes.len()).expect("ArenaTrie node indices exceed u32");
+                self.nodes.push(ArenaNode::new(key_char));
+                index
+            }
+        }
+    }
+
+    // Detach `index` from its parent's children and free its slot
+    fn unlink(&mut self, parent: u32, index: u32) {
+        let key_char = self.node(index).key_char;
+        let siblings = &mut self.nodes[parent as usize].children;
+        let position = siblings
+            .binary_search_by_key(&key_char, |(c, _)| *c)
+            .unwrap();
+        siblings.remove(position);
+        self.free.push(index);
+    }
+
+    fn find(&self, key: &str) -> Option<u32> {
+        let mut index = ARENA_ROOT;
+        for c in key.chars() {
+            index = self.child(index, c)?;
+        }
+        Some(index)
+    }
+
+    /// Inserts `value` under `key`. Returns false, leaving the trie
+    /// untouched, if `key` is already stored.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the trie would need more than `u32::MAX` nodes, the most
+    /// its indices can address.
+    pub fn insert(&mut self, key: &str, value: T) -> bool {
+        self.try_insert(key, value).is_ok()
+    }
+
+    /// Inserts `value` under `key`, leaving the trie untouched on error.
+    ///
+    /// # Errors
+    ///
+    /// [`TrieError::DuplicateKey`] if `key` already has a value.
+    ///
+    /// # Panics
+    ///
+    /// As [`ArenaTrie::insert`].
+    pub fn try_insert(&mut self, key: &str, value: T) -> Result<(), TrieError> {
+        // A duplicate allocates nothing, so the trie is untouched
+        if self.contains_key(key) {
+            return Err(TrieError::DuplicateKey);
+        }
+        let index = self.find_or_insert(key);
+        self.nodes[index as usize].value = Some(value);
+        self.count += 1;
+        Ok(())
+    }
+
+    /// Inserts `value` under `key`, or if `key` is already stored, replaces
+    /// its value with `merge(existing, value)`. Returns true if a merge
+    /// happened, false for a plain insert; see
+    /// [`Trie::insert_with`](crate::Trie::insert_with).
+    ///
+    /// # Panics
+    ///
+    /// As [`ArenaTrie::insert`].
+    pub fn insert_with<F: FnOnce(T, T) -> T>(&mut self, key: &str, value: T, merge: F) -> bool {
+        let index = self.find_or_insert(key);
+        let slot = &mut self.nodes[index as usize].value;
+        match slot.take() {
+            Some(existing) => {
+                *slot = Some(merge(existing, value));
+                true
+            }
+            None => {
+                *slot = Some(value);
+                self.count += 1;
+                false
+            }
+        }
+    }
+
+    // The node for `key`, creating the missing nodes along its path
+    fn find_or_insert(&mut self, key: &str) -> u32 {
+        let mut index = ARENA_ROOT;
+        for c in key.chars() {
+            index = match self.child(index, c) {
+                Some(child) => child,
+                None => {
+                    let child = self.alloc(c);
+                    let children = &mut self.nodes[index as usize].children;
+                    let position = children.binary_search_by_key(&c, |(c, _)| *c).unwrap_err();
+                    children.insert(position, (c, child));
+                    child
+                }
+            };
+        }
+        index
+    }
+
+    /// Removes `key`, freeing nodes left without values or children. Returns
+    /// whether it was removed.
+    pub fn remove(&mut self, key: &str) -> bool {
+        self.remove_if(key, |_| true).is_some()
+    }
+
+    /// Removes `key` and returns its value, but only if `pred` returns true
+    /// for it. Otherwise the trie is left untouched and `None` is returned.
+    /// Nodes are freed as with [`ArenaTrie::remove`].
+    pub fn remove_if<F: FnOnce(&T) -> bool>(&mut self, key: &str, pred: F) -> Option<T> {
+        let mut path = vec![ARENA_ROOT];
+        for c in key.chars() {
+            path.push(self.child(*path.last().unwrap(), c)?);
+        }
+        let slot = &mut self.nodes[*path.last().unwrap() as usize].value;
+        if !slot.as_ref().is_some_and(pred) {
+            return None;
+        }
+        let value = slot.take();
+        self.count -= 1;
+
+        while path.len() > 1 {
+            let index = path.pop().unwrap();
+            let node = self.node(index);
+            if node.value.is_some() || !node.children.is_empty() {
+                break;
+            }
+            self.unlink(*path.last().unwrap(), index);
+        }
+        value
+    }
+
+    /// Removes every entry for which `f` returns true, returning how many
+    /// were removed. `f` sees the entries in lexicographic key order. Nodes
+    /// left without values or children are freed, as with
+    /// [`ArenaTrie::remove`].
+    pub fn remove_where<F>(&mut self, mut f: F) -> usize
+    where
+        F: FnMut(&str, &T) -> bool,
+    {
+        // One pre-order walk takes the values out and records each node with
+        // its parent; visited in reverse, every node comes after its
+        // children, so it is freed once they are
+        let mut removed = 0;
+        let mut visited = Vec::new();
+        let mut stack = vec![(ARENA_ROOT, ARENA_ROOT, 0)];
+        let mut key = String::new();
+        while let Some((index, parent, parent_len)) = stack.pop() {
+            key.truncate(parent_len);
+            if index != ARENA_ROOT {
+                key.push(self.node(index).key_char);
+                visited.push((index, parent));
+            }
+            let node = &mut self.nodes[index as usize];
+            if node.value.as_ref().is_some_and(|value| f(&key, value)) {
+                node.value = None;
+                removed += 1;
+            }
+            for &(_, child) in node.children.iter().rev() {
+                stack.push((child, index, key.len()));
+            }
+        }
+
+        for (index, parent) in visited.into_iter().rev() {
+            let node = self.node(index);
+            if node.value.is_none() && node.children.is_empty() {
+                self.unlink(parent, index);
+            }
+        }
+        self.count -= removed;
+        removed
+    }
+
+    /// The value stored under `key`, if any.
+    pub fn get(&self, key: &str) -> Option<&T> {
+        self.node(self.find(key)?).value.as_ref()
+    }
+
+    /// A mutable reference to the value stored under `key`, if any.
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut T> {
+        let index = self.find(key)?;
+        self.nodes[index as usize].value.as_mut()
+    }
+
+    /// Returns true if `key` is stored in the trie.
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Number of stored keys, in O(1).
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    /// Returns true if no key is stored.
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Removes every key, keeping the arena's allocation for reuse.
+    pub fn clear(&mut self) {
+        self.nodes.truncate(1);
+        self.nodes[ARENA_ROOT as usize] = ArenaNode::new('\0');
+        self.free.clear();
+        self.count = 0;
+    }
+
+    /// The longest stored key that is a prefix of `key`, with its value. The
+    /// empty key, if stored, matches as a last resort.
+    pub fn longest_prefix_match(&self, key: &str) -> Option<(String, &T)> {
+        let mut best = self.node(ARENA_ROOT).value.as_ref().map(|value| (0, value));
+        let mut index = ARENA_ROOT;
+        for (i, c) in key.char_indices() {
+            index = match self.child(index, c) {
+                Some(child) => child,
+                None => break,
+            };
+            if let Some(value) = &self.node(index).value {
+                best = Some((i + c.len_utf8(), value));
+            }
+        }
+        best.map(|(len, value)| (String::from(&key[..len]), value))
+    }
+
+    /// Iterates over all entries in lexicographic key order, with mutable
+    /// access to the values. Splitting the arena into disjoint borrows
+    /// takes one slot per node up front.
+    pub fn iter_mut(&mut self) -> ArenaIterMut<'_, T> {
+        ArenaIterMut {
+            nodes: self
+                .nodes
+                .iter_mut()
+                .map(|node| ArenaNodeMut {
+                    key_char: node.key_char,
+                    children: &node.children,
+                    value: node.value.as_mut(),
+                })
+                .collect(),
+            stack: vec![(ARENA_ROOT, 0)],
+            key: String::new(),
+        }
+    }
+
+    /// Iterates over all entries in lexicographic key order.
+    pub fn iter(&self) -> ArenaIter<'_, T> {
+        ArenaIter {
+            nodes: &self.nodes,
+            start: ARENA_ROOT,
+            stack: vec![(ARENA_ROOT, 0)],
+            key: String::new(),
+        }
+    }
+
+    /// Iterates over the entries whose keys start with `prefix`, in
+    /// lexicographic order. Keys are yielded in full, prefix included.
+    pub fn iter_prefix(&self, prefix: &str) -> ArenaIter<'_, T> {
+        let start = self.find(prefix);
+        ArenaIter {
+            nodes: &self.nodes,
+            start: start.unwrap_or(ARENA_ROOT),
+            stack: start
+                .map(|start| (start, prefix.len()))
+                .into_iter()
+                .collect(),
+            key: String::from(prefix),
+        }
+    }
+
+    /// Iterates over the keys starting with `prefix`, in lexicographic order.
+    pub fn keys_with_prefix(&self, prefix: &str) -> impl Iterator<Item = String> + '_ {
+        self.iter_prefix(prefix).map(|(key, _)| key)
+    }
+
+    /// Iterates over all values in arena order, a linear scan that skips the
+    /// tree walk.
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.nodes.iter().filter_map(|node| node.value.as_ref())
+    }
+
+    /// Iterates over all values in arena order, mutably; see
+    /// [`ArenaTrie::values`].
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.nodes.iter_mut().filter_map(|node| node.value.as_mut())
+    }
+
+    /// Checks the arena's index integrity: every live node is reachable
+    /// from the root exactly once through a correctly keyed, sorted child
+    /// link, every other slot is on the free list, no empty node is left
+    /// unpruned, and `len` matches the values stored. A debugging aid, O(n): in release builds it does
+    /// nothing.
+    ///
+    /// # Panics
+    ///
+    /// Panics, in debug builds, if any of this doesn't hold.
+    pub fn validate(&self) {
+        if !cfg!(debug_assertions) {
+            return;
+        }
+
+        let len = self.nodes.len();
+        let values = self.values().count();
+        assert_eq!(
+            self.count, values,
+            "count is {}, not {}",
+            self.count, values
+        );
+        let mut free = vec![false; len];
+        for &index in &self.free {
+            let index = index as usize;
+            assert!(index < len && index != ARENA_ROOT as usize);
+            assert!(!free[index], "slot {} freed twice", index);
+            free[index] = true;
+        }
+
+        let mut reached = vec![false; len];
+        reached[ARENA_ROOT as usize] = true;
+        let mut stack = vec![ARENA_ROOT];
+        while let Some(index) = stack.pop() {
+            let node = self.node(index);
+            assert!(node.children.windows(2).all(|pair| pair[0].0 < pair[1].0));
+            if index != ARENA_ROOT {
+                assert!(node.value.is_some() || !node.children.is_empty());
+            }
+            for &(key_char, child) in &node.children {
+                let child = child as usize;
+                assert!(child < len, "child index {} out of bounds", child);
+                assert!(!free[child], "child index {} is on the free list", child);
+                assert!(!reached[child], "node {} reachable twice", child);
+                assert_eq!(self.nodes[child].key_char, key_char);
+                reached[child] = true;
+                stack.push(child as u32);
+            }
+        }
+
+        for index in 0..len {
+            assert!(reached[index] != free[index], "slot {} leaked", index);
+        }
+    }
+}
+
+/// Iterator over an [`ArenaTrie`]'s entries in lexicographic key order.
+pub struct ArenaIter<'a, T> {
+    // Pre-order walk, sharing one key buffer between steps
+    nodes: &'a [ArenaNode<T>],
+    // Node the walk starts at, whose key is in the buffer already
+    start: u32,
+    // Node index and the key length (in bytes) of its parent
+    stack: Vec<(u32, usize)>,
+    key: String,
+}
+
+/// Iterator over an [`ArenaTrie`]'s entries in lexicographic key order, with
+/// mutable access to the values.
+pub struct ArenaIterMut<'a, T> {
+    // Same walk as ArenaIter, over each node's fields borrowed separately:
+    // the values mutably, each taken out of its slot when yielded so it is
+    // handed out once
+    nodes: Vec<ArenaNodeMut<'a, T>>,
+    stack: Vec<(u32, usize)>,
+    key: String,
+}
+
+// An ArenaNode's fields, borrowed separately
+struct ArenaNodeMut<'a, T> {
+    key_char: char,
+    children: &'a [(char, u32)],
+    value: Option<&'a mut T>,
+}
+
+impl<T> Default for ArenaTrie<T> {
+    fn default() -> ArenaTrie<T> {
+        ArenaTrie::new()
+    }
+}
+
+impl<'a, T> Iterator for ArenaIter<'a, T> {
+    type Item = (String, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((index, parent_len)) = self.stack.pop() {
+            let node = &self.nodes[index as usize];
+            self.key.truncate(parent_len);
+            if index != self.start {
+                self.key.push(node.key_char);
+            }
+            for &(_, child) in node.children.iter().rev() {
+                self.stack.push((child, self.key.len()));
+            }
+            if let Some(value) = &node.value {
+                return Some((self.key.clone(), value));
+            }
+        }
+        None
+    }
+}
+
+impl<'a, T> Iterator for ArenaIterMut<'a, T> {
+    type Item = (String, &'a mut T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((index, parent_len)) = self.stack.pop() {
+            let node = &mut self.nodes[index as usize];
+            self.key.truncate(parent_len);
+            if index != ARENA_ROOT {
+                self.key.push(node.key_char);
+            }
+            for &(_, child) in node.children.iter().rev() {
+                self.stack.push((child, self.key.len()));
+            }
+            if let Some(value) = node.value.take() {
+                return Some((self.key.clone(), value));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::XorShift64;
+
+    fn sample() -> ArenaTrie<u32> {
+        let mut trie = ArenaTrie::<u32>::new();
+        assert!(trie.insert("to", 1));
+        assert!(trie.insert("tea", 2));
+        assert!(trie.insert("ten", 3));
+        assert!(trie.insert("i", 4));
+        assert!(trie.insert("in", 5));
+        trie
+    }
+
+    #[test]
+    fn insert_and_get() {
+        let mut trie = sample();
+        assert!(!trie.insert("tea", 6));
+        assert_eq!(trie.get("tea"), Some(&2));
+        assert_eq!(trie.get("te"), None);
+        assert!(trie.contains_key("in"));
+        assert_eq!(
+            trie.iter()
+                .map(|(key, value)| (key, *value))
+                .collect::<Vec<_>>(),
+            vec![
+                (String::from("i"), 4),
+                (String::from("in"), 5),
+                (String::from("tea"), 2),
+                (String::from("ten"), 3),
+                (String::from("to"), 1),
+            ]
+        );
+        assert_eq!(trie.values().sum::<u32>(), 15);
+        trie.validate();
+    }
+
+    #[test]
+    fn empty_key_and_prefixes() {
+        let mut trie = sample();
+        assert_eq!(trie.get(""), None);
+        assert!(trie.insert("", 0));
+        assert!(!trie.insert("", 6));
+        assert_eq!(trie.get(""), Some(&0));
+        assert_eq!(trie.iter().next(), Some((String::new(), &0)));
+
+        assert_eq!(
+            trie.keys_with_prefix("te").collect::<Vec<_>>(),
+            ["tea", "ten"]
+        );
+        assert_eq!(trie.keys_with_prefix("i").collect::<Vec<_>>(), ["i", "in"]);
+        assert_eq!(trie.keys_with_prefix("x").count(), 0);
+        assert_eq!(trie.iter_prefix("").count(), 6);
+        assert_eq!(
+            trie.iter_prefix("to").collect::<Vec<_>>(),
+            [(String::from("to"), &1)]
+        );
+        assert_eq!(
+            trie.longest_prefix_match("inner"),
+            Some((String::from("in"), &5))
+        );
+        assert_eq!(trie.longest_prefix_match("te"), Some((String::new(), &0)));
+
+        assert!(trie.remove(""));
+        assert!(!trie.remove(""));
+        assert_eq!(trie.longest_prefix_match("te"), None);
+        assert_eq!(trie.get("in"), Some(&5));
+        trie.validate();
+    }
+
+    #[test]
+    fn remove_and_reuse() {
+        let mut trie = sample();
+        let node_slots = trie.nodes.len();
+        assert!(trie.remove("tea"));
+        assert!(!trie.remove("tea"));
+        assert!(trie.remove("i"));
+        assert_eq!(trie.free.len(), 1);
+        assert_eq!(trie.get("ten"), Some(&3));
+        assert_eq!(trie.get("in"), Some(&5));
+        trie.validate();
+        assert!(trie.insert("tee", 7));
+        assert_eq!(trie.nodes.len(), node_slots);
+        assert!(trie.free.is_empty());
+        trie.validate();
+    }
+
+    #[test]
+    fn len_and_mutation() {
+        let mut trie = sample();
+        assert_eq!(trie.len(), 5);
+        assert_eq!(trie.try_insert("tea", 6), Err(TrieError::DuplicateKey));
+        assert_eq!(trie.try_insert("te", 6), Ok(()));
+        assert!(trie.insert_with("te", 1, |a, b| a + b));
+        assert!(!trie.insert_with("t", 8, |a, b| a + b));
+        assert_eq!(trie.get("te"), Some(&7));
+        assert_eq!(trie.len(), 7);
+
+        *trie.get_mut("to").unwrap() += 10;
+        assert_eq!(trie.get_mut("x"), None);
+        for (key, value) in trie.iter_mut() {
+            *value += key.len() as u32 * 100;
+        }
+        for value in trie.values_mut() {
+            *value *= 2;
+        }
+        assert_eq!(
+            trie.iter()
+                .map(|(key, value)| (key, *value))
+                .collect::<Vec<_>>(),
+            [
+                ("i", 208),
+                ("in", 410),
+                ("t", 216),
+                ("te", 414),
+                ("tea", 604),
+                ("ten", 606),
+                ("to", 422),
+            ]
+            .map(|(key, value)| (String::from(key), value))
+        );
+        trie.validate();
+
+        assert_eq!(trie.remove_if("ten", |value| *value > 1000), None);
+        assert_eq!(trie.remove_if("ten", |value| *value == 606), Some(606));
+        assert_eq!(trie.remove_if("ten", |_| true), None);
+        assert_eq!(trie.len(), 6);
+        trie.validate();
+
+        trie.clear();
+        assert!(trie.is_empty());
+        assert_eq!(trie.iter_mut().count(), 0);
+    }
+
+    #[test]
+    fn remove_where() {
+        let mut trie = sample();
+        trie.insert("", 0);
+        let mut seen = Vec::new();
+        let removed = trie.remove_where(|key, value| {
+            seen.push(String::from(key));
+            key.starts_with('t') || *value == 4
+        });
+        assert_eq!(removed, 4);
+        assert_eq!(seen, ["", "i", "in", "tea", "ten", "to"]);
+        assert_eq!(trie.len(), 2);
+        assert_eq!(trie.keys_with_prefix("").collect::<Vec<_>>(), ["", "in"]);
+        // Only the root and i, n are left live
+        assert_eq!(trie.nodes.len() - trie.free.len(), 3);
+        trie.validate();
+
+        assert_eq!(trie.remove_where(|_, _| true), 2);
+        assert!(trie.is_empty());
+        assert_eq!(trie.free.len(), trie.nodes.len() - 1);
+        trie.validate();
+    }
+
+    #[test]
+    fn clear() {
+        let mut trie = sample();
+        let capacity = trie.nodes.capacity();
+        trie.clear();
+        assert_eq!(trie.iter().count(), 0);
+        assert_eq!(trie.nodes.capacity(), capacity);
+        trie.validate();
+    }
+
+    #[test]
+    fn with_capacity() {
+        let mut trie = ArenaTrie::with_capacity(6);
+        let arena = trie.nodes.as_ptr();
+        for (i, key) in ["tea", "ten", "to"].iter().enumerate() {
+            trie.insert(key, i);
+        }
+        // Six nodes with the root, all in the initial block
+        assert_eq!(trie.nodes.len(), 6);
+        assert_eq!(trie.nodes.as_ptr(), arena);
+        assert_eq!(trie.get("ten"), Some(&1));
+        trie.validate();
+        assert_eq!(ArenaTrie::<u32>::with_capacity(0).iter().count(), 0);
+    }
+
+    #[test]
+    fn values_dropped_once() {
+        use std::rc::Rc;
+
+        let value = Rc::new(());
+        let mut trie = ArenaTrie::new();
+        for key in ["a", "ab", "abc", "b", "bcd"] {
+            trie.insert(key, Rc::clone(&value));
+        }
+        assert!(!trie.insert("a", Rc::clone(&value)));
+        assert_eq!(Rc::strong_count(&value), 6);
+        trie.remove("ab");
+        trie.remove("bcd");
+        assert_eq!(Rc::strong_count(&value), 4);
+        // Into a freed slot
+        trie.insert("c", Rc::clone(&value));
+        assert_eq!(trie.free.len(), 1);
+        assert_eq!(Rc::strong_count(&value), 5);
+        trie.clear();
+        assert_eq!(Rc::strong_count(&value), 1);
+        for key in ["x", "xy"] {
+            trie.insert(key, Rc::clone(&value));
+        }
+        drop(trie);
+        assert_eq!(Rc::strong_count(&value), 1);
+    }
+
+    #[test]
+    fn differential() {
+        let mut trie = ArenaTrie::new();
+        let mut rng = XorShift64(0x853c_49e6_748f_ea9b);
+        let mut model = std::collections::BTreeMap::new();
+        for _ in 0..5000 {
+            let len = rng.below(5);
+            let key: String = (0..len)
+                .map(|_| (b'a' + rng.below(4) as u8) as char)
+                .collect();
+            if rng.below(2) == 0 {
+                let expected = !model.contains_key(&key);
+                assert_eq!(trie.insert(&key, len as u32), expected);
+                model.entry(key).or_insert(len as u32);
+            } else {
+                assert_eq!(trie.remove(&key), model.remove(&key).is_some());
+            }
+        }
+        assert_eq!(trie.len(), model.len());
+        trie.validate();
+        for prefix in ["", "a", "bc", "dddd", "abcda"] {
+            assert!(trie
+                .keys_with_prefix(prefix)
+                .eq(model.keys().filter(|key| key.starts_with(prefix)).cloned()));
+        }
+        assert!(trie
+            .iter()
+            .map(|(key, value)| (key, *value))
+            .eq(model.into_iter()));
+    }
+}
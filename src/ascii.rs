@@ -0,0 +1,359 @@
+use std::fmt;
+
+/// Error for a key outside [`AsciiTrie`]'s `[a-z]` alphabet.
+#[derive(Debug, PartialEq)]
+pub enum AsciiKeyError {
+    /// `ch`, at char position `index` of the key, is not a lowercase ASCII
+    /// letter.
+    InvalidChar { ch: char, index: usize },
+}
+
+impl fmt::Display for AsciiKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AsciiKeyError::InvalidChar { ch, index } => {
+                write!(f, "{:?} at char {} is not in [a-z]", ch, index)
+            }
+        }
+    }
+}
+
+impl std::error::Error for AsciiKeyError {}
+
+// Node with one direct-indexed slot per letter of [a-z]
+struct AsciiNode<T> {
+    value: Option<T>,
+    children: [Option<Box<AsciiNode<T>>>; 26],
+}
+
+impl<T> AsciiNode<T> {
+    fn new() -> AsciiNode<T> {
+        AsciiNode {
+            value: None,
+            children: std::array::from_fn(|_| None),
+        }
+    }
+
+    fn child_count(&self) -> usize {
+        self.children.iter().flatten().count()
+    }
+}
+
+impl<T> Drop for AsciiNode<T> {
+    // The boxes' drop glue would recurse once per level and overflow the
+    // stack on long keys, so the children are moved onto a work list and
+    // each node drops without descendants
+    fn drop(&mut self) {
+        let mut pending: Vec<Box<AsciiNode<T>>> =
+            self.children.iter_mut().filter_map(Option::take).collect();
+        while let Some(mut node) = pending.pop() {
+            pending.extend(node.children.iter_mut().filter_map(Option::take));
+        }
+    }
+}
+
+/// Trie restricted to lowercase ASCII keys. Children live in a fixed array, so
+/// each step is a direct index rather than a hash or search; `cargo bench`
+/// compares its build and lookup times with the general
+/// [`Trie`](crate::Trie)'s.
+pub struct AsciiTrie<T> {
+    root: AsciiNode<T>,
+}
+
+impl<T> AsciiTrie<T> {
+    /// Creates an empty trie.
+    pub fn new() -> AsciiTrie<T> {
+        AsciiTrie {
+            root: AsciiNode::new(),
+        }
+    }
+
+    // Child slot index for the char at `index` of a key
+    fn slot(ch: char, index: usize) -> Result<usize, AsciiKeyError> {
+        match ch {
+            'a'..='z' => Ok((ch as u8 - b'a') as usize),
+            _ => Err(AsciiKeyError::InvalidChar { ch, index }),
+        }
+    }
+
+    fn slots(key: &str) -> Result<Vec<usize>, AsciiKeyError> {
+        key.chars()
+            .enumerate()
+            .map(|(index, ch)| Self::slot(ch, index))
+            .collect()
+    }
+
+    fn find_node(&self, key: &str) -> Result<Option<&AsciiNode<T>>, AsciiKeyError> {
+        let mut current_node = Some(&self.root);
+        for (index, ch) in key.chars().enumerate() {
+            // Keep validating past a missing child so every query reports
+            // alphabet violations, not just the ones on existing paths
+            let slot = Self::slot(ch, index)?;
+            current_node = current_node.and_then(|node| node.children[slot].as_deref());
+        }
+        Ok(current_node)
+    }
+
+    /// Inserts `value` under `key`. Like [`Trie::insert`](crate::Trie::insert),
+    /// returns false for an already stored key; the empty key is stored on
+    /// the root.
+    pub fn insert(&mut self, key: &str, value: T) -> Result<bool, AsciiKeyError> {
+        // Validate the whole key before creating any nodes
+        for (index, ch) in key.chars().enumerate() {
+            Self::slot(ch, index)?;
+        }
+
+        let mut current_node = &mut self.root;
+        for ch in key.bytes() {
+            current_node = current_node.children[(ch - b'a') as usize]
+                .get_or_insert_with(|| Box::new(AsciiNode::new()));
+        }
+
+        if current_node.value.is_some() {
+            return Ok(false);
+        }
+        current_node.value = Some(value);
+        Ok(true)
+    }
+
+    /// The value stored under `key`, if any.
+    pub fn get(&self, key: &str) -> Result<Option<&T>, AsciiKeyError> {
+        Ok(self.find_node(key)?.and_then(|node| node.value.as_ref()))
+    }
+
+    /// Returns true if `key` is stored in the trie.
+    pub fn contains_key(&self, key: &str) -> Result<bool, AsciiKeyError> {
+        Ok(self.get(key)?.is_some())
+    }
+
+    /// Removes `key`, pruning nodes left without values or children. Returns
+    /// whether it was removed.
+    pub fn remove(&mut self, key: &str) -> Result<bool, AsciiKeyError> {
+        let slots = Self::slots(key)?;
+
+        // Iterative, for any key length. The nodes to prune are a chain at
+        // the end of the path: each without a value or a child but the next
+        // one, and the last without children. A first walk finds where that
+        // chain starts, a second cuts it off, or just takes the value.
+        let mut current_node = &self.root;
+        let mut chain_start = None;
+        for (i, &slot) in slots.iter().enumerate() {
+            current_node = match current_node.children[slot].as_deref() {
+                Some(node) => node,
+                None => return Ok(false),
+            };
+            let prunable = if i + 1 == slots.len() {
+                current_node.child_count() == 0
+            } else {
+                current_node.value.is_none() && current_node.child_count() == 1
+            };
+            if prunable {
+                chain_start.get_or_insert(i);
+            } else {
+                chain_start = None;
+            }
+        }
+        if current_node.value.is_none() {
+            return Ok(false);
+        }
+
+        let depth = chain_start.unwrap_or(slots.len());
+        let mut current_node = &mut self.root;
+        for &slot in &slots[..depth] {
+            current_node = current_node.children[slot].as_mut().unwrap();
+        }
+        match chain_start {
+            Some(start) => current_node.children[slots[start]] = None,
+            None => current_node.value = None,
+        }
+        Ok(true)
+    }
+
+    /// All stored keys starting with `prefix`, in lexicographic order.
+    pub fn keys_with_prefix(&self, prefix: &str) -> Result<Vec<String>, AsciiKeyError> {
+        let node = match self.find_node(prefix)? {
+            Some(node) => node,
+            None => return Ok(Vec::new()),
+        };
+        Ok(AsciiIter::new(node, String::from(prefix))
+            .map(|(key, _)| key)
+            .collect())
+    }
+
+    /// Iterates over all entries in lexicographic key order.
+    pub fn iter(&self) -> AsciiIter<'_, T> {
+        AsciiIter::new(&self.root, String::new())
+    }
+}
+
+/// Iterator over an [`AsciiTrie`]'s entries in lexicographic key order.
+pub struct AsciiIter<'a, T> {
+    // Pre-order walk over a subtree, which is lexicographic order. One key
+    // buffer is shared between steps and cloned for each entry.
+    //
+    // Node, key length before it, and the letter on its edge (None for the
+    // node the walk starts from)
+    stack: Vec<(&'a AsciiNode<T>, usize, Option<char>)>,
+    key: String,
+}
+
+impl<'a, T> AsciiIter<'a, T> {
+    // Walk the subtree under `node`, whose own key is `key`
+    fn new(node: &'a AsciiNode<T>, key: String) -> AsciiIter<'a, T> {
+        AsciiIter {
+            stack: vec![(node, key.len(), None)],
+            key,
+        }
+    }
+}
+
+impl<T> Default for AsciiTrie<T> {
+    fn default() -> AsciiTrie<T> {
+        AsciiTrie::new()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for AsciiTrie<T> {
+    // The entries, read off an iterative walk; a derived impl would recurse
+    // once per level
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<T: PartialEq> PartialEq for AsciiTrie<T> {
+    // Removal prunes every node it empties, so tries holding the same
+    // entries have the same nodes, and comparing entries suffices
+    fn eq(&self, other: &AsciiTrie<T>) -> bool {
+        self.iter().eq(other.iter())
+    }
+}
+
+impl<'a, T> Iterator for AsciiIter<'a, T> {
+    type Item = (String, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((node, parent_len, letter)) = self.stack.pop() {
+            self.key.truncate(parent_len);
+            self.key.extend(letter);
+            for (slot, child) in node.children.iter().enumerate().rev() {
+                if let Some(child) = child {
+                    let letter = (b'a' + slot as u8) as char;
+                    self.stack.push((child, self.key.len(), Some(letter)));
+                }
+            }
+            if let Some(value) = &node.value {
+                return Some((self.key.clone(), value));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> AsciiTrie<u32> {
+        let mut trie = AsciiTrie::<u32>::new();
+        assert_eq!(trie.insert("cat", 1), Ok(true));
+        assert_eq!(trie.insert("car", 2), Ok(true));
+        assert_eq!(trie.insert("ca", 3), Ok(true));
+        assert_eq!(trie.insert("dog", 4), Ok(true));
+        trie
+    }
+
+    #[test]
+    fn insert_and_get() {
+        let mut trie = sample();
+        assert_eq!(trie.insert("cat", 5), Ok(false));
+        assert_eq!(trie.get("cat"), Ok(Some(&1)));
+        assert_eq!(trie.get("c"), Ok(None));
+        assert_eq!(trie.contains_key("dog"), Ok(true));
+        assert_eq!(
+            trie.keys_with_prefix("ca"),
+            Ok(vec![
+                String::from("ca"),
+                String::from("car"),
+                String::from("cat")
+            ])
+        );
+        assert_eq!(trie.keys_with_prefix("x"), Ok(Vec::new()));
+        assert_eq!(
+            trie.iter()
+                .map(|(key, value)| (key, *value))
+                .collect::<Vec<_>>(),
+            vec![
+                (String::from("ca"), 3),
+                (String::from("car"), 2),
+                (String::from("cat"), 1),
+                (String::from("dog"), 4),
+            ]
+        );
+    }
+
+    #[test]
+    fn remove() {
+        let mut trie = sample();
+        assert_eq!(trie.remove("ca"), Ok(true));
+        assert_eq!(trie.remove("ca"), Ok(false));
+        assert_eq!(trie.get("car"), Ok(Some(&2)));
+        assert_eq!(trie.remove("dog"), Ok(true));
+        assert!(trie.root.children[(b'd' - b'a') as usize].is_none());
+    }
+
+    #[test]
+    fn empty_key() {
+        let mut trie = sample();
+        assert_eq!(trie.get(""), Ok(None));
+        assert_eq!(trie.insert("", 0), Ok(true));
+        assert_eq!(trie.insert("", 5), Ok(false));
+        assert_eq!(trie.get(""), Ok(Some(&0)));
+        assert_eq!(trie.keys_with_prefix("").unwrap()[..2], ["", "ca"]);
+        assert_eq!(trie.keys_with_prefix("c").unwrap().len(), 3);
+        assert_eq!(trie.iter().next(), Some((String::new(), &0)));
+        assert_eq!(trie.remove(""), Ok(true));
+        assert_eq!(trie.remove(""), Ok(false));
+        assert_eq!(trie.contains_key(""), Ok(false));
+        assert_eq!(trie.get("cat"), Ok(Some(&1)));
+    }
+
+    #[test]
+    fn deep_key() {
+        std::thread::spawn(|| {
+            let key = "a".repeat(1_000_000);
+            let mut trie = AsciiTrie::new();
+            assert_eq!(trie.insert(&key, 1), Ok(true));
+            assert_eq!(trie.insert(&key[..10], 2), Ok(true));
+            assert_eq!(trie.get(&key), Ok(Some(&1)));
+            assert_eq!(trie.iter().count(), 2);
+            assert!(format!("{:?}", trie).ends_with(": 1}"));
+            let mut other = AsciiTrie::new();
+            other.insert(&key, 1).unwrap();
+            other.insert(&key[..10], 2).unwrap();
+            assert!(trie == other);
+
+            assert_eq!(trie.remove(&key), Ok(true));
+            assert_eq!(trie.get(&key[..10]), Ok(Some(&2)));
+            assert!(trie != other);
+            trie.insert(&key, 3).unwrap();
+        })
+        .join()
+        .unwrap();
+    }
+
+    #[test]
+    fn alphabet_violation() {
+        let mut trie = sample();
+        let invalid = |ch, index| AsciiKeyError::InvalidChar { ch, index };
+        assert_eq!(trie.insert("Cat", 6), Err(invalid('C', 0)));
+        assert_eq!(trie.insert("ca t", 6), Err(invalid(' ', 2)));
+        assert_eq!(trie.insert("café", 6), Err(invalid('é', 3)));
+        assert_eq!(trie.get("cat!"), Err(invalid('!', 3)));
+        assert_eq!(trie.contains_key("CAT"), Err(invalid('C', 0)));
+        assert_eq!(trie.keys_with_prefix("c4"), Err(invalid('4', 1)));
+        assert_eq!(trie.remove("cat_"), Err(invalid('_', 3)));
+        assert_eq!(trie.get("cat"), Ok(Some(&1)));
+        assert_eq!(invalid('é', 3).to_string(), "'é' at char 3 is not in [a-z]");
+    }
+}
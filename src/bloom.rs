@@ -0,0 +1,133 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use crate::trie::Trie;
+
+// About 1% false positives: 10 bits per key with 7 probes
+const BITS_PER_KEY: usize = 10;
+const PROBES: u64 = 7;
+
+/// Bloom filter over a [`Trie`]'s keys, built by [`Trie::build_bloom`], for
+/// cheap negative lookups in front of the trie.
+///
+/// [`PrefixBloom::might_contain`] never returns false for a key that was
+/// stored when the filter was built, but returns true for roughly 1% of
+/// the keys that weren't. The filter is a snapshot: keys inserted into the
+/// trie afterwards are missing from it, so rebuild it after changes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrefixBloom {
+    bits: Vec<u64>,
+}
+
+impl PrefixBloom {
+    // Room for `keys` keys, with at least one word so probing never
+    // divides by zero
+    fn with_capacity(keys: usize) -> PrefixBloom {
+        let words = (keys * BITS_PER_KEY).div_ceil(64).max(1);
+        PrefixBloom {
+            bits: vec![0; words],
+        }
+    }
+
+    // Bit positions for `key`, by double hashing one 64-bit hash
+    fn probes(&self, key: &str) -> impl Iterator<Item = usize> {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        let hash = hasher.finish();
+        // An odd step visits distinct bits as long as there are more than 7
+        let step = hash.rotate_left(32) | 1;
+        let len = (self.bits.len() * 64) as u64;
+        (0..PROBES).map(move |i| (hash.wrapping_add(i.wrapping_mul(step)) % len) as usize)
+    }
+
+    fn insert(&mut self, key: &str) {
+        for bit in self.probes(key) {
+            self.bits[bit / 64] |= 1 << (bit % 64);
+        }
+    }
+
+    /// Returns false if `key` certainly wasn't stored, true if it may have
+    /// been. Keys are compared as stored, i.e. after normalization; see
+    /// [`Trie::contains_key_fast`] to normalize on the way.
+    pub fn might_contain(&self, key: &str) -> bool {
+        self.probes(key)
+            .all(|bit| self.bits[bit / 64] & 1 << (bit % 64) != 0)
+    }
+
+    /// Size of the filter in bytes.
+    pub fn size_in_bytes(&self) -> usize {
+        self.bits.len() * 8
+    }
+}
+
+impl<T> Trie<T> {
+    /// Builds a [`PrefixBloom`] of the stored keys, sized at 10 bits per
+    /// key.
+    pub fn build_bloom(&self) -> PrefixBloom {
+        let mut bloom = PrefixBloom::with_capacity(self.len());
+        for (key, _) in self.iter() {
+            bloom.insert(&key);
+        }
+        bloom
+    }
+
+    /// [`Trie::contains_key`], asking `bloom` first and only walking the
+    /// trie if it says the key may be there. `bloom` must have been built
+    /// from this trie since it last changed, or keys inserted in between
+    /// are reported missing.
+    pub fn contains_key_fast(&self, bloom: &PrefixBloom, key: &str) -> bool {
+        bloom.might_contain(&self.normalize(key)) && self.contains_key(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::XorShift64;
+
+    fn random_key(rng: &mut XorShift64) -> String {
+        (0..1 + rng.below(12))
+            .map(|_| char::from(b'a' + rng.below(26) as u8))
+            .collect()
+    }
+
+    #[test]
+    fn no_false_negatives() {
+        let mut rng = XorShift64(0x5eed_b100_f11e_0001);
+        let mut trie = Trie::new();
+        for i in 0..5000 {
+            trie.insert(&random_key(&mut rng), i);
+        }
+        trie.insert("", -1);
+        trie.insert("héllo wörld", -2);
+        let bloom = trie.build_bloom();
+        assert_eq!(bloom.size_in_bytes(), (trie.len() * 10).div_ceil(64) * 8);
+
+        for (key, _) in trie.iter() {
+            assert!(bloom.might_contain(&key), "{:?}", key);
+            assert!(trie.contains_key_fast(&bloom, &key));
+        }
+
+        // Roughly 1% false positives, with plenty of slack
+        let absent: Vec<String> = (0..10_000).map(|_| random_key(&mut rng) + "#").collect();
+        let false_positives = absent.iter().filter(|key| bloom.might_contain(key)).count();
+        assert!(false_positives < 300, "{}", false_positives);
+        assert!(absent
+            .iter()
+            .all(|key| !trie.contains_key_fast(&bloom, key)));
+    }
+
+    #[test]
+    fn empty_and_normalized() {
+        let bloom = Trie::<()>::new().build_bloom();
+        assert!(!bloom.might_contain(""));
+        assert!(!bloom.might_contain("a"));
+
+        let mut trie = Trie::new_with_normalizer(|key: &str| key.to_lowercase());
+        trie.insert("Hello", ());
+        let bloom = trie.build_bloom();
+        assert!(bloom.might_contain("hello"));
+        assert!(trie.contains_key_fast(&bloom, "HELLO"));
+        assert!(!trie.contains_key_fast(&bloom, "help"));
+    }
+}
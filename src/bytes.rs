@@ -0,0 +1,319 @@
+// Child storage of a BytesNode: a 256-bit occupancy bitmap plus the children
+// packed in byte order, so a child's index is the number of set bits below
+// its byte. Unlike a HashMap<u8, _> this costs 32 bytes plus one Vec.
+#[derive(Debug, PartialEq)]
+struct ByteChildren<T> {
+    bitmap: [u64; 4],
+    nodes: Vec<BytesNode<T>>,
+}
+
+impl<T> ByteChildren<T> {
+    fn new() -> ByteChildren<T> {
+        ByteChildren {
+            bitmap: [0; 4],
+            nodes: Vec::new(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    fn contains(&self, byte: u8) -> bool {
+        self.bitmap[byte as usize / 64] & (1 << (byte % 64)) != 0
+    }
+
+    // Position of `byte`'s child in `nodes`, whether or not it is present
+    fn index(&self, byte: u8) -> usize {
+        let word = byte as usize / 64;
+        let below: u32 = self.bitmap[..word].iter().map(|w| w.count_ones()).sum();
+        let mask = (1u64 << (byte % 64)) - 1;
+        (below + (self.bitmap[word] & mask).count_ones()) as usize
+    }
+
+    fn get(&self, byte: u8) -> Option<&BytesNode<T>> {
+        if !self.contains(byte) {
+            return None;
+        }
+        Some(&self.nodes[self.index(byte)])
+    }
+
+    fn get_mut(&mut self, byte: u8) -> Option<&mut BytesNode<T>> {
+        if !self.contains(byte) {
+            return None;
+        }
+        let index = self.index(byte);
+        Some(&mut self.nodes[index])
+    }
+
+    // The child for `byte`, created empty if it doesn't exist yet
+    fn get_or_insert(&mut self, byte: u8) -> &mut BytesNode<T> {
+        let index = self.index(byte);
+        if !self.contains(byte) {
+            self.bitmap[byte as usize / 64] |= 1 << (byte % 64);
+            self.nodes.insert(index, BytesNode::new());
+        }
+        &mut self.nodes[index]
+    }
+
+    fn remove(&mut self, byte: u8) -> Option<BytesNode<T>> {
+        if !self.contains(byte) {
+            return None;
+        }
+        let index = self.index(byte);
+        self.bitmap[byte as usize / 64] &= !(1 << (byte % 64));
+        Some(self.nodes.remove(index))
+    }
+
+    // The bytes with a child, in ascending order
+    fn bytes(&self) -> impl Iterator<Item = u8> + '_ {
+        self.bitmap
+            .iter()
+            .enumerate()
+            .flat_map(|(word_index, &word)| {
+                let mut word = word;
+                std::iter::from_fn(move || {
+                    if word == 0 {
+                        return None;
+                    }
+                    let bit = word.trailing_zeros() as usize;
+                    word &= word - 1;
+                    Some((word_index * 64 + bit) as u8)
+                })
+            })
+    }
+
+    // (byte, child) pairs in ascending byte order
+    fn iter(&self) -> impl Iterator<Item = (u8, &BytesNode<T>)> + '_ {
+        self.bytes().zip(self.nodes.iter())
+    }
+}
+
+// Node of a BytesTrie; its byte is implied by the parent's bitmap
+#[derive(Debug, PartialEq)]
+struct BytesNode<T> {
+    value: Option<T>,
+    children: ByteChildren<T>,
+}
+
+impl<T> BytesNode<T> {
+    fn new() -> BytesNode<T> {
+        BytesNode {
+            value: None,
+            children: ByteChildren::new(),
+        }
+    }
+}
+
+/// Trie keyed by arbitrary byte strings, such as hashes, serialized tuples
+/// or text that isn't valid UTF-8. Like [`Trie`](crate::Trie), the empty key
+/// is stored on the root node.
+#[derive(Debug, PartialEq)]
+pub struct BytesTrie<T> {
+    root: BytesNode<T>,
+}
+
+impl<T> Default for BytesTrie<T> {
+    fn default() -> BytesTrie<T> {
+        BytesTrie::new()
+    }
+}
+
+impl<T> BytesTrie<T> {
+    /// Creates an empty trie.
+    pub fn new() -> BytesTrie<T> {
+        BytesTrie {
+            root: BytesNode::new(),
+        }
+    }
+
+    fn find_node(&self, key: &[u8]) -> Option<&BytesNode<T>> {
+        let mut current_node = &self.root;
+        for &byte in key {
+            current_node = current_node.children.get(byte)?;
+        }
+        Some(current_node)
+    }
+
+    /// Inserts `value` under `key`. Returns false, leaving the trie
+    /// untouched, if `key` is already stored.
+    pub fn insert(&mut self, key: &[u8], value: T) -> bool {
+        let mut current_node = &mut self.root;
+        for &byte in key {
+            current_node = current_node.children.get_or_insert(byte);
+        }
+
+        if current_node.value.is_some() {
+            return false;
+        }
+        current_node.value = Some(value);
+        true
+    }
+
+    /// The value stored under `key`, if any.
+    pub fn get(&self, key: &[u8]) -> Option<&T> {
+        self.find_node(key)?.value.as_ref()
+    }
+
+    /// Returns true if `key` is stored in the trie.
+    pub fn contains_key(&self, key: &[u8]) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Removes `key`, pruning nodes left without values or children. Returns
+    /// whether it was removed.
+    pub fn remove(&mut self, key: &[u8]) -> bool {
+        match key.split_first() {
+            Some((&byte, rest)) => Self::remove_helper(&mut self.root, byte, rest),
+            None => self.root.value.take().is_some(),
+        }
+    }
+
+    fn remove_helper(parent_node: &mut BytesNode<T>, byte: u8, rest: &[u8]) -> bool {
+        let node = match parent_node.children.get_mut(byte) {
+            Some(node) => node,
+            None => return false,
+        };
+
+        let removed = match rest.split_first() {
+            Some((&next, rest)) => Self::remove_helper(node, next, rest),
+            None => node.value.take().is_some(),
+        };
+
+        if removed && node.value.is_none() && node.children.is_empty() {
+            parent_node.children.remove(byte);
+        }
+        removed
+    }
+
+    /// Iterates over all entries in lexicographic (byte-wise) key order.
+    pub fn iter(&self) -> BytesIter<'_, T> {
+        BytesIter::new(&self.root, Vec::new())
+    }
+
+    /// Iterates over the entries whose keys start with `prefix`, in
+    /// lexicographic order. Keys are yielded in full, prefix included.
+    pub fn iter_prefix(&self, prefix: &[u8]) -> BytesIter<'_, T> {
+        match self.find_node(prefix) {
+            Some(node) => BytesIter::new(node, prefix.to_vec()),
+            None => BytesIter {
+                stack: Vec::new(),
+                key: Vec::new(),
+            },
+        }
+    }
+}
+
+/// Iterator over a [`BytesTrie`]'s entries in lexicographic key order.
+pub struct BytesIter<'a, T> {
+    // Pre-order walk sharing one key buffer between steps, like the string
+    // trie's Iter: node, key length before it, and its byte (None for the
+    // node the walk starts from)
+    stack: Vec<(&'a BytesNode<T>, usize, Option<u8>)>,
+    key: Vec<u8>,
+}
+
+impl<'a, T> BytesIter<'a, T> {
+    fn new(node: &'a BytesNode<T>, key: Vec<u8>) -> BytesIter<'a, T> {
+        BytesIter {
+            stack: vec![(node, key.len(), None)],
+            key,
+        }
+    }
+}
+
+impl<'a, T> Iterator for BytesIter<'a, T> {
+    type Item = (Vec<u8>, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((node, parent_len, byte)) = self.stack.pop() {
+            self.key.truncate(parent_len);
+            self.key.extend(byte);
+            let children: Vec<(u8, &BytesNode<T>)> = node.children.iter().collect();
+            for (byte, child) in children.into_iter().rev() {
+                self.stack.push((child, self.key.len(), Some(byte)));
+            }
+            if let Some(value) = &node.value {
+                return Some((self.key.clone(), value));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binary_keys() {
+        let mut trie = BytesTrie::new();
+        assert!(trie.insert(&[0x00], 1));
+        assert!(trie.insert(&[0x00, 0x00], 2));
+        assert!(trie.insert(&[0xFF, 0x00, 0xFF], 3));
+        // Not valid UTF-8
+        assert!(trie.insert(&[0xC3, 0x28], 4));
+        assert!(trie.insert(b"", 5));
+        assert!(!trie.insert(&[0x00], 6));
+
+        assert_eq!(trie.get(&[0x00]), Some(&1));
+        assert_eq!(trie.get(&[0xFF, 0x00, 0xFF]), Some(&3));
+        assert_eq!(trie.get(&[0xFF, 0x00]), None);
+        assert_eq!(trie.get(&[0xC3, 0x28]), Some(&4));
+        assert_eq!(trie.get(b""), Some(&5));
+        assert!(!trie.contains_key(&[0x01]));
+
+        assert_eq!(
+            trie.iter().collect::<Vec<_>>(),
+            vec![
+                (vec![], &5),
+                (vec![0x00], &1),
+                (vec![0x00, 0x00], &2),
+                (vec![0xC3, 0x28], &4),
+                (vec![0xFF, 0x00, 0xFF], &3),
+            ]
+        );
+        assert_eq!(
+            trie.iter_prefix(&[0x00]).collect::<Vec<_>>(),
+            vec![(vec![0x00], &1), (vec![0x00, 0x00], &2)]
+        );
+        assert_eq!(trie.iter_prefix(&[0xFF, 0x01]).next(), None);
+    }
+
+    #[test]
+    fn remove() {
+        let mut trie = BytesTrie::new();
+        trie.insert(&[0xFF, 0x00, 0xFF], 1);
+        trie.insert(&[0xFF], 2);
+        assert!(!trie.remove(&[0xFF, 0x00]));
+        assert!(trie.remove(&[0xFF, 0x00, 0xFF]));
+        assert!(!trie.remove(&[0xFF, 0x00, 0xFF]));
+        assert!(trie.root.children.get(0xFF).unwrap().children.is_empty());
+        assert!(trie.remove(&[0xFF]));
+        assert!(trie.root.children.is_empty());
+        assert_eq!(trie.root.children.bitmap, [0; 4]);
+    }
+
+    #[test]
+    fn full_fanout() {
+        let mut trie = BytesTrie::new();
+        for byte in (0..=255u8).rev() {
+            assert!(trie.insert(&[byte], byte as u32));
+        }
+        assert_eq!(trie.root.children.bitmap, [u64::MAX; 4]);
+        for byte in 0..=255u8 {
+            assert_eq!(trie.get(&[byte]), Some(&(byte as u32)));
+        }
+        assert!(trie.iter().map(|(key, _)| key[0]).eq(0..=255u8));
+
+        for byte in (0..=255u8).filter(|byte| byte % 3 == 0) {
+            assert!(trie.remove(&[byte]));
+        }
+        assert!(trie
+            .iter()
+            .map(|(key, value)| (key[0], *value))
+            .eq((0..=255u8)
+                .filter(|byte| byte % 3 != 0)
+                .map(|byte| (byte, byte as u32))));
+    }
+}
@@ -0,0 +1,250 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::node::TrieNode;
+use crate::trie::Trie;
+
+impl<T: Eq + Hash> Trie<T> {
+    /// Merges all equivalent suffix subtrees into a minimal, read-only
+    /// [`Dawg`]. Two subtrees are equivalent if their roots hold equal
+    /// values (or both none) and their children are equivalent char by
+    /// char, so a word list's many "…tion" or "…ing" endings end up stored
+    /// once. With distinct values per key little can be shared; the savings
+    /// come from sets and from values repeated across keys.
+    pub fn minimize(mut self) -> Dawg<T> {
+        // Nodes left by remove_lazy would be merged into a bare leaf
+        self.compact();
+        let mut registry = HashMap::new();
+        let mut trie_node_count = 0;
+        register(self.root_mut(), &mut registry, &mut trie_node_count);
+
+        // Ids were handed out in registration order, and the root came last
+        let mut nodes: Vec<(DawgNode<T>, u32)> = registry.into_iter().collect();
+        nodes.sort_unstable_by_key(|(_, id)| *id);
+        Dawg {
+            nodes: nodes.into_iter().map(|(node, _)| node).collect(),
+            trie_node_count,
+        }
+    }
+}
+
+// Registers the subtree under `node` bottom-up, returning its id. Each
+// node's signature (its value plus its children's chars and already merged
+// ids) is hashed, so equivalent subtrees meet in `registry` instead of being
+// compared pairwise. Values are moved out of the trie on the way.
+fn register<T: Eq + Hash>(
+    node: &mut TrieNode<T>,
+    registry: &mut HashMap<DawgNode<T>, u32>,
+    trie_node_count: &mut usize,
+) -> u32 {
+    *trie_node_count += 1;
+    let (value, children) = node.split_mut();
+    let children = children
+        .into_iter()
+        .map(|child| (child.key_char(), register(child, registry, trie_node_count)))
+        .collect();
+    let signature = DawgNode {
+        value: value.take(),
+        children,
+    };
+    let next_id = registry.len() as u32;
+    *registry.entry(signature).or_insert(next_id)
+}
+
+// Node of a Dawg; children are (char, index into the Dawg's node list),
+// sorted by char
+#[derive(Debug, PartialEq, Eq, Hash)]
+struct DawgNode<T> {
+    value: Option<T>,
+    children: Vec<(char, u32)>,
+}
+
+/// Minimal acyclic automaton built by [`Trie::minimize`]: a read-only trie
+/// in which keys with equivalent suffixes share nodes.
+///
+/// Keys are matched as stored, i.e. after the trie's normalization.
+#[derive(Debug)]
+pub struct Dawg<T = ()> {
+    // The last node is the root
+    nodes: Vec<DawgNode<T>>,
+    trie_node_count: usize,
+}
+
+/// The result of [`Trie::minimize`], under the name of what it is for.
+pub type MinimizedTrie<T> = Dawg<T>;
+
+impl<T> Dawg<T> {
+    fn root(&self) -> u32 {
+        (self.nodes.len() - 1) as u32
+    }
+
+    // Id of the node reached by following `key` from the root
+    fn find_node(&self, key: &str) -> Option<u32> {
+        let mut id = self.root();
+        for c in key.chars() {
+            let children = &self.nodes[id as usize].children;
+            let i = children.binary_search_by_key(&c, |(c, _)| *c).ok()?;
+            id = children[i].1;
+        }
+        Some(id)
+    }
+
+    /// The value stored under `key`, if any.
+    pub fn get(&self, key: &str) -> Option<&T> {
+        self.nodes[self.find_node(key)? as usize].value.as_ref()
+    }
+
+    /// Returns true if `key` is one of the keys the DAWG was built from.
+    pub fn contains(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Returns true if any key starts with `prefix`.
+    pub fn contains_prefix(&self, prefix: &str) -> bool {
+        // Every node but a bare root leads to a value
+        self.find_node(prefix).is_some_and(|id| {
+            let node = &self.nodes[id as usize];
+            node.value.is_some() || !node.children.is_empty()
+        })
+    }
+
+    /// Iterates over all entries in lexicographic key order.
+    pub fn iter(&self) -> DawgIter<'_, T> {
+        DawgIter {
+            nodes: &self.nodes,
+            stack: vec![(self.root(), 0, None)],
+            key: String::new(),
+        }
+    }
+
+    /// Iterates over the keys starting with `prefix`, in lexicographic order.
+    pub fn keys_with_prefix(&self, prefix: &str) -> impl Iterator<Item = String> + '_ {
+        let stack = self.find_node(prefix).map(|id| (id, prefix.len(), None));
+        let iter = DawgIter {
+            nodes: &self.nodes,
+            stack: stack.into_iter().collect(),
+            key: String::from(prefix),
+        };
+        iter.map(|(key, _)| key)
+    }
+
+    /// Number of nodes, root included.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Number of nodes, root included, of the trie the DAWG was built from,
+    /// to compare with [`Dawg::node_count`].
+    pub fn trie_node_count(&self) -> usize {
+        self.trie_node_count
+    }
+}
+
+/// Iterator over a [`Dawg`]'s entries in lexicographic key order.
+pub struct DawgIter<'a, T> {
+    // Pre-order walk, sharing one key buffer between steps. A shared node
+    // is visited once per path reaching it.
+    nodes: &'a [DawgNode<T>],
+    // Node index, the key length (in bytes) of its parent and the char on
+    // the edge from it, none for the node the walk starts from
+    stack: Vec<(u32, usize, Option<char>)>,
+    key: String,
+}
+
+impl<'a, T> Iterator for DawgIter<'a, T> {
+    type Item = (String, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((index, parent_len, edge)) = self.stack.pop() {
+            let node = &self.nodes[index as usize];
+            self.key.truncate(parent_len);
+            self.key.extend(edge);
+            for &(c, child) in node.children.iter().rev() {
+                self.stack.push((child, self.key.len(), Some(c)));
+            }
+            if let Some(value) = &node.value {
+                return Some((self.key.clone(), value));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn minimize() {
+        let words = [
+            "cities", "parties", "pities", "ties", "city", "party", "pity", "tie",
+        ];
+        let mut trie = Trie::<()>::new();
+        for word in words {
+            trie.insert(word, ());
+        }
+        let trie_nodes = trie.root().count_nodes();
+        let dawg = trie.minimize();
+        for word in words {
+            assert!(dawg.contains(word));
+        }
+        for word in ["", "cit", "citie", "parti", "ti", "tiess", "pities!", "x"] {
+            assert!(!dawg.contains(word));
+        }
+        assert_eq!(dawg.trie_node_count(), trie_nodes);
+        assert!(dawg.node_count() * 2 < trie_nodes);
+
+        let dawg = Trie::<()>::new().minimize();
+        assert_eq!(dawg.node_count(), 1);
+        assert!(!dawg.contains("a"));
+        assert!(!dawg.contains_prefix(""));
+        assert_eq!(dawg.iter().next(), None);
+
+        let mut trie = Trie::<()>::new();
+        trie.insert("", ());
+        trie.insert("a", ());
+        let dawg = trie.minimize();
+        assert!(dawg.contains(""));
+        assert!(dawg.contains("a"));
+    }
+
+    #[test]
+    fn shared_suffixes_with_values() {
+        let mut trie = Trie::new();
+        let stems = ["act", "connect", "direct", "elect", "inject", "protect"];
+        for stem in stems {
+            trie.insert(stem, "verb");
+            trie.insert(&format!("{}ion", stem), "noun");
+            trie.insert(&format!("{}ing", stem), "gerund");
+            trie.insert(&format!("{}ed", stem), "past");
+        }
+        trie.insert("ion", "particle");
+        let expected: Vec<(String, &str)> = trie.iter().map(|(key, value)| (key, *value)).collect();
+
+        let dawg = trie.minimize();
+        // Each stem's "t" node and everything below it is shared
+        assert!(dawg.node_count() * 3 < dawg.trie_node_count());
+        assert_eq!(
+            dawg.iter()
+                .map(|(key, value)| (key, *value))
+                .collect::<Vec<_>>(),
+            expected
+        );
+        for (key, value) in &expected {
+            assert_eq!(dawg.get(key), Some(value));
+        }
+        // Same shape as "ion" under each stem, different value
+        assert_eq!(dawg.get("ion"), Some(&"particle"));
+        assert_eq!(dawg.get("actio"), None);
+
+        assert!(dawg.contains_prefix("inje"));
+        assert!(dawg.contains_prefix("connecte"));
+        assert!(!dawg.contains_prefix("connecti0"));
+        assert_eq!(
+            dawg.keys_with_prefix("elect").collect::<Vec<_>>(),
+            ["elect", "elected", "electing", "election"]
+        );
+        assert_eq!(dawg.keys_with_prefix("x").next(), None);
+        assert_eq!(dawg.keys_with_prefix("").count(), expected.len());
+    }
+}
@@ -0,0 +1,26 @@
+use std::fmt;
+
+/// Error returned by fallible [`Trie`](crate::Trie) operations such as
+/// [`Trie::try_insert`](crate::Trie::try_insert).
+///
+/// There is no `EmptyKey` variant: the empty key is an ordinary key, stored
+/// on the root, so inserting it fails only as a duplicate, like any other.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TrieError {
+    /// The key already has a value.
+    DuplicateKey,
+    /// No key starts at byte `offset` of a text being segmented.
+    UnmatchedChar { offset: usize },
+}
+
+impl fmt::Display for TrieError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrieError::DuplicateKey => write!(f, "key already has a value"),
+            TrieError::UnmatchedChar { offset } => write!(f, "no key matches at byte {}", offset),
+        }
+    }
+}
+
+impl std::error::Error for TrieError {}
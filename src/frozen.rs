@@ -0,0 +1,105 @@
+use std::sync::Arc;
+
+use crate::iter::Iter;
+use crate::trie::Trie;
+
+/// A read-only trie, from [`Trie::freeze`], e.g. a dictionary built once
+/// and then shared by many threads.
+///
+/// Clones share the same nodes, so cloning is O(1) and a clone can be
+/// handed to each thread without a lock around it. A `FrozenTrie` is
+/// `Send` and `Sync` when `T` is.
+#[derive(Debug)]
+pub struct FrozenTrie<T> {
+    trie: Arc<Trie<T>>,
+}
+
+impl<T> Trie<T> {
+    /// Turns the trie into a read-only [`FrozenTrie`]. The normalizer is
+    /// kept.
+    pub fn freeze(self) -> FrozenTrie<T> {
+        FrozenTrie {
+            trie: Arc::new(self),
+        }
+    }
+}
+
+impl<T> Clone for FrozenTrie<T> {
+    fn clone(&self) -> FrozenTrie<T> {
+        FrozenTrie {
+            trie: Arc::clone(&self.trie),
+        }
+    }
+}
+
+impl<T> FrozenTrie<T> {
+    /// The value stored under `key`, if any.
+    pub fn get(&self, key: &str) -> Option<&T> {
+        self.trie.get(key)
+    }
+
+    /// Returns true if `key` is stored in the trie.
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.trie.contains_key(key)
+    }
+
+    /// Number of stored keys.
+    pub fn len(&self) -> usize {
+        self.trie.len()
+    }
+
+    /// Returns true if no key is stored.
+    pub fn is_empty(&self) -> bool {
+        self.trie.is_empty()
+    }
+
+    /// Iterates over all entries in lexicographic key order.
+    pub fn iter(&self) -> Iter<'_, T> {
+        self.trie.iter()
+    }
+
+    /// Iterates over the stored keys that start with `prefix`, in
+    /// lexicographic order.
+    pub fn keys_with_prefix(&self, prefix: &str) -> impl Iterator<Item = String> + '_ {
+        self.trie.keys_with_prefix(prefix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn shared_between_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<FrozenTrie<String>>();
+
+        let mut trie = Trie::new_with_normalizer(|key| key.to_lowercase());
+        for (i, key) in ["tea", "ten", "to", "inn"].iter().enumerate() {
+            trie.insert(key, i);
+        }
+        let frozen = trie.freeze();
+        assert_eq!(frozen.len(), 4);
+        assert_eq!(frozen.get("TEN"), Some(&1));
+        assert!(!frozen.contains_key("te"));
+        assert_eq!(
+            frozen.keys_with_prefix("t").collect::<Vec<_>>(),
+            ["tea", "ten", "to"]
+        );
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let frozen = frozen.clone();
+                thread::spawn(move || frozen.iter().map(|(_, value)| *value).sum::<usize>())
+            })
+            .collect();
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), 6);
+        }
+        // The clones all shared one trie
+        assert_eq!(Arc::strong_count(&frozen.trie), 1);
+        assert!(!frozen.is_empty());
+    }
+}
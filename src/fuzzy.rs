@@ -0,0 +1,280 @@
+use crate::node::TrieNode;
+use crate::trie::Trie;
+
+/// Distance used by [`Trie::edit_distance_search`]. Every edit costs 1 and
+/// distances count chars, not bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DistanceMetric {
+    /// Insertions, deletions and substitutions.
+    Levenshtein,
+    /// Levenshtein plus transpositions of two adjacent chars, in the
+    /// optimal string alignment variant: no substring is edited twice, so
+    /// "ca" to "abc" is 3 rather than 2.
+    DamerauLevenshtein,
+    /// Substitutions only, so only keys as long as the query are candidates.
+    Hamming,
+}
+
+// State of one search, threaded through the recursive walk
+struct Search<'q> {
+    query: &'q [char],
+    max_distance: usize,
+    metric: DistanceMetric,
+    // Key of the node being visited
+    key: String,
+    found: Vec<(String, usize)>,
+}
+
+impl<'q> Search<'q> {
+    // Levenshtein and Damerau–Levenshtein: `row[j]` is the distance from the
+    // current key to the first j query chars. `prev_row` and `last` are the
+    // parent's row and the current key's last char, for transpositions.
+    fn edit<T>(
+        &mut self,
+        node: &TrieNode<T>,
+        row: &[usize],
+        prev_row: &[usize],
+        last: Option<char>,
+    ) {
+        let distance = row[self.query.len()];
+        if node.value().is_some() && distance <= self.max_distance {
+            self.found.push((self.key.clone(), distance));
+        }
+
+        // Rows only grow from here on, except that a transposition reaches
+        // back to the parent's row
+        let exceeds = |row: &[usize]| row.iter().all(|&d| d > self.max_distance);
+        if exceeds(row)
+            && (self.metric == DistanceMetric::Levenshtein
+                || prev_row.is_empty()
+                || exceeds(prev_row))
+        {
+            return;
+        }
+
+        for child in node.child_nodes() {
+            let c = child.key_char();
+            let mut next_row = Vec::with_capacity(row.len());
+            next_row.push(row[0] + 1);
+            for (j, &q) in self.query.iter().enumerate() {
+                let mut d = (row[j + 1] + 1)
+                    .min(next_row[j] + 1)
+                    .min(row[j] + usize::from(q != c));
+                if self.metric == DistanceMetric::DamerauLevenshtein
+                    && j > 0
+                    && last == Some(q)
+                    && self.query[j - 1] == c
+                {
+                    d = d.min(prev_row[j - 1] + 1);
+                }
+                next_row.push(d);
+            }
+
+            self.key.push(c);
+            self.edit(child, &next_row, row, Some(c));
+            self.key.pop();
+        }
+    }
+
+    // Hamming: `mismatches` so far along a key `depth` chars long
+    fn hamming<T>(&mut self, node: &TrieNode<T>, depth: usize, mismatches: usize) {
+        if depth == self.query.len() {
+            if node.value().is_some() {
+                self.found.push((self.key.clone(), mismatches));
+            }
+            return;
+        }
+
+        for child in node.child_nodes() {
+            let c = child.key_char();
+            let mismatches = mismatches + usize::from(c != self.query[depth]);
+            if mismatches <= self.max_distance {
+                self.key.push(c);
+                self.hamming(child, depth + 1, mismatches);
+                self.key.pop();
+            }
+        }
+    }
+}
+
+impl<T> Trie<T> {
+    /// The keys within `max_distance` of `query` under `metric`, with their
+    /// distances, sorted by distance and then alphabetically. The query is
+    /// normalized like a key.
+    ///
+    /// This is the usual trie walk computing one row of the dynamic
+    /// programming table per node, so shared prefixes share work, and
+    /// abandoning subtrees once no row entry can get back under
+    /// `max_distance`.
+    pub fn edit_distance_search(
+        &self,
+        query: &str,
+        max_distance: usize,
+        metric: DistanceMetric,
+    ) -> Vec<(String, usize)> {
+        let query: Vec<char> = self.normalize(query).chars().collect();
+        let mut search = Search {
+            query: &query,
+            max_distance,
+            metric,
+            key: String::new(),
+            found: Vec::new(),
+        };
+        match metric {
+            DistanceMetric::Hamming => search.hamming(self.root(), 0, 0),
+            DistanceMetric::Levenshtein | DistanceMetric::DamerauLevenshtein => {
+                let row: Vec<usize> = (0..=query.len()).collect();
+                search.edit(self.root(), &row, &[], None);
+            }
+        }
+
+        let mut found = search.found;
+        found.sort_by(|(a, a_distance), (b, b_distance)| {
+            a_distance.cmp(b_distance).then_with(|| a.cmp(b))
+        });
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::XorShift64;
+
+    // Plain full-table distances to check the trie walk against
+    fn distance(a: &str, b: &str, metric: DistanceMetric) -> Option<usize> {
+        let a: Vec<char> = a.chars().collect();
+        let b: Vec<char> = b.chars().collect();
+        if metric == DistanceMetric::Hamming {
+            return (a.len() == b.len()).then(|| a.iter().zip(&b).filter(|(x, y)| x != y).count());
+        }
+
+        let mut table = vec![vec![0; b.len() + 1]; a.len() + 1];
+        for i in 0..=a.len() {
+            for j in 0..=b.len() {
+                table[i][j] = if i == 0 || j == 0 {
+                    i + j
+                } else {
+                    let mut d = (table[i - 1][j] + 1)
+                        .min(table[i][j - 1] + 1)
+                        .min(table[i - 1][j - 1] + usize::from(a[i - 1] != b[j - 1]));
+                    if metric == DistanceMetric::DamerauLevenshtein
+                        && i > 1
+                        && j > 1
+                        && a[i - 1] == b[j - 2]
+                        && a[i - 2] == b[j - 1]
+                    {
+                        d = d.min(table[i - 2][j - 2] + 1);
+                    }
+                    d
+                };
+            }
+        }
+        Some(table[a.len()][b.len()])
+    }
+
+    fn words() -> Trie<()> {
+        let mut trie = Trie::new();
+        for word in ["book", "books", "boot", "cook", "bokk", "obok", "bo", ""] {
+            trie.insert(word, ());
+        }
+        trie
+    }
+
+    #[test]
+    fn each_metric() {
+        let trie = words();
+        assert_eq!(
+            trie.edit_distance_search("book", 1, DistanceMetric::Levenshtein),
+            [
+                (String::from("book"), 0),
+                (String::from("bokk"), 1),
+                (String::from("books"), 1),
+                (String::from("boot"), 1),
+                (String::from("cook"), 1),
+            ]
+        );
+        // "boko" and "book", "obko" and "obok" are one transposition apart
+        assert_eq!(
+            trie.edit_distance_search("boko", 1, DistanceMetric::DamerauLevenshtein),
+            [(String::from("bokk"), 1), (String::from("book"), 1)]
+        );
+        assert_eq!(
+            trie.edit_distance_search("boko", 1, DistanceMetric::Levenshtein),
+            [(String::from("bokk"), 1)]
+        );
+        assert_eq!(
+            trie.edit_distance_search("obko", 1, DistanceMetric::DamerauLevenshtein),
+            [(String::from("obok"), 1)]
+        );
+        assert_eq!(
+            trie.edit_distance_search("obko", 1, DistanceMetric::Levenshtein),
+            []
+        );
+        // "bo" and "books" are in reach for Levenshtein, not for Hamming
+        assert_eq!(
+            trie.edit_distance_search("boo", 2, DistanceMetric::Hamming),
+            []
+        );
+        assert_eq!(
+            trie.edit_distance_search("boak", 2, DistanceMetric::Hamming),
+            [
+                (String::from("bokk"), 1),
+                (String::from("book"), 1),
+                (String::from("boot"), 2),
+                (String::from("cook"), 2),
+            ]
+        );
+        assert_eq!(
+            trie.edit_distance_search("", 0, DistanceMetric::Hamming),
+            [(String::new(), 0)]
+        );
+        assert_eq!(
+            trie.edit_distance_search("x", 1, DistanceMetric::Levenshtein),
+            [(String::new(), 1)]
+        );
+    }
+
+    #[test]
+    fn matches_full_table() {
+        let mut rng = XorShift64(0x0123_4567_89ab_cdef);
+        let alphabet = ['a', 'b', 'c', 'é'];
+        let random_word = |rng: &mut XorShift64| -> String {
+            (0..rng.below(6)).map(|_| alphabet[rng.below(4)]).collect()
+        };
+        for _ in 0..30 {
+            let mut trie = Trie::new();
+            let mut keys = Vec::new();
+            for _ in 0..rng.below(40) {
+                let key = random_word(&mut rng);
+                if trie.insert(&key, ()) {
+                    keys.push(key);
+                }
+            }
+
+            let query = random_word(&mut rng);
+            for metric in [
+                DistanceMetric::Levenshtein,
+                DistanceMetric::DamerauLevenshtein,
+                DistanceMetric::Hamming,
+            ] {
+                for max_distance in 0..4 {
+                    let mut expected: Vec<(String, usize)> = keys
+                        .iter()
+                        .filter_map(|key| Some((key.clone(), distance(&query, key, metric)?)))
+                        .filter(|(_, d)| *d <= max_distance)
+                        .collect();
+                    expected.sort_by(|(a, da), (b, db)| da.cmp(db).then_with(|| a.cmp(b)));
+                    assert_eq!(
+                        trie.edit_distance_search(&query, max_distance, metric),
+                        expected,
+                        "{:?} {} {:?}",
+                        query,
+                        max_distance,
+                        metric
+                    );
+                }
+            }
+        }
+    }
+}
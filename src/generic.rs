@@ -0,0 +1,432 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hash};
+
+use crate::error::TrieError;
+use crate::iter::GenericIter;
+use crate::node::GenericNode;
+use crate::ranked::{self, Ranked};
+
+/// A trie over sequences of any key symbol `K`, e.g. `u32` token ids or
+/// whole `String` words, mapping each sequence to a value of type `T`.
+/// [`Trie`](crate::Trie) is the `K = char` specialization with string
+/// conveniences on top.
+///
+/// Keys are passed as anything iterable over `K` and come back as `Vec<K>`.
+/// Although only `Eq + Hash + Clone` is needed to tell symbols apart, `K`
+/// must also be `Ord`: small nodes keep their children sorted, and iteration
+/// is in lexicographic order. The root node's key is `K::default()`, which
+/// isn't part of any key; the empty key is stored on the root.
+///
+/// Values inserted with [`GenericTrie::insert_with_priority`] also carry a
+/// priority of type `P`, `f64` unless chosen otherwise, and
+/// [`GenericTrie::top_k_by_prefix`] finds the highest-priority keys under a
+/// prefix without visiting the rest. Each node tracks the highest priority
+/// in its subtree, kept current by inserts and removals alike.
+#[derive(Debug)]
+pub struct GenericTrie<K, T, S = RandomState, P = f64> {
+    pub(crate) root: GenericNode<K, T, S, P>,
+}
+
+impl<K: Default, T> GenericTrie<K, T> {
+    /// Creates an empty trie. For a custom hasher, use
+    /// [`GenericTrie::default`].
+    pub fn new() -> GenericTrie<K, T> {
+        GenericTrie::default()
+    }
+}
+
+impl<K, T, S, P> GenericTrie<K, T, S, P>
+where
+    K: Ord + Hash + Clone,
+    S: BuildHasher + Default,
+    P: PartialOrd + Clone,
+{
+    /// The root node, for read-only traversal.
+    pub fn root(&self) -> &GenericNode<K, T, S, P> {
+        &self.root
+    }
+
+    /// Inserts `value` under `key`. Returns false, leaving the trie
+    /// untouched, if `key` is already stored.
+    pub fn insert<I: IntoIterator<Item = K>>(&mut self, key: I, value: T) -> bool {
+        self.try_insert(key, value).is_ok()
+    }
+
+    /// Inserts `value` under `key`, leaving the trie untouched on error.
+    ///
+    /// # Errors
+    ///
+    /// [`TrieError::DuplicateKey`] if `key` already has a value.
+    pub fn try_insert<I: IntoIterator<Item = K>>(
+        &mut self,
+        key: I,
+        value: T,
+    ) -> Result<(), TrieError> {
+        self.try_insert_entry(key, value, None)
+    }
+
+    /// Inserts `value` under `key` with `priority`, for
+    /// [`GenericTrie::top_k_by_prefix`] to rank it by. Returns false,
+    /// leaving the trie untouched, if `key` is already stored.
+    ///
+    /// # Panics
+    ///
+    /// If `priority` can't be compared with itself, like NaN, which has no
+    /// place in a ranking.
+    pub fn insert_with_priority<I>(&mut self, key: I, value: T, priority: P) -> bool
+    where
+        I: IntoIterator<Item = K>,
+    {
+        assert!(
+            priority.partial_cmp(&priority).is_some(),
+            "priority is incomparable, like NaN"
+        );
+        self.try_insert_entry(key, value, Some(priority)).is_ok()
+    }
+
+    // The one insert path, for values with or without a priority
+    pub(crate) fn try_insert_entry<I>(
+        &mut self,
+        key: I,
+        value: T,
+        priority: Option<P>,
+    ) -> Result<(), TrieError>
+    where
+        I: IntoIterator<Item = K>,
+    {
+        self.root
+            .update_at(key, true, |node| {
+                // A stored key's nodes all exist already, so no node was
+                // created
+                if node.value().is_some() {
+                    return Err(TrieError::DuplicateKey);
+                }
+                node.set_value(value);
+                if priority.is_some() {
+                    node.set_priority(priority);
+                }
+                Ok(())
+            })
+            .unwrap()
+    }
+
+    /// Inserts `value` under `key`, or if `key` is already stored, replaces
+    /// its value with `merge(existing, value)`, keeping its priority.
+    /// Returns true if a merge happened, false for a plain insert.
+    pub fn insert_with<I, F>(&mut self, key: I, value: T, merge: F) -> bool
+    where
+        I: IntoIterator<Item = K>,
+        F: FnOnce(T, T) -> T,
+    {
+        self.root
+            .update_at(key, true, |node| match node.take_entry() {
+                Some((existing, priority)) => {
+                    node.set_value(merge(existing, value));
+                    if priority.is_some() {
+                        node.set_priority(priority);
+                    }
+                    true
+                }
+                None => {
+                    node.set_value(value);
+                    false
+                }
+            })
+            .unwrap()
+    }
+
+    // Node reached by following `key` from the root
+    fn find_node<I: IntoIterator<Item = K>>(&self, key: I) -> Option<&GenericNode<K, T, S, P>> {
+        let mut current_node = &self.root;
+        for k in key {
+            current_node = current_node.child(k)?;
+        }
+        Some(current_node)
+    }
+
+    /// The value stored under `key`, if any.
+    pub fn get<I: IntoIterator<Item = K>>(&self, key: I) -> Option<&T> {
+        self.find_node(key)?.value()
+    }
+
+    /// A mutable reference to the value stored under `key`, if any.
+    pub fn get_mut<I: IntoIterator<Item = K>>(&mut self, key: I) -> Option<&mut T> {
+        let mut current_node = &mut self.root;
+        for k in key {
+            current_node = current_node.child_mut(k)?;
+        }
+        current_node.value_mut()
+    }
+
+    /// The priority `key`'s value was inserted with, if it is stored with
+    /// one.
+    pub fn priority<I: IntoIterator<Item = K>>(&self, key: I) -> Option<&P> {
+        self.find_node(key)?.priority()
+    }
+
+    /// The highest priority among the keys starting with `prefix`, read off
+    /// the prefix's node in one walk down.
+    pub fn max_priority_with_prefix<I: IntoIterator<Item = K>>(&self, prefix: I) -> Option<&P> {
+        self.find_node(prefix)?.max_priority()
+    }
+
+    /// The (at most) `k` keys starting with `prefix` whose values have the
+    /// highest priorities, highest first, with their values. Equal
+    /// priorities are in lexicographic key order, and values inserted
+    /// without a priority are left out.
+    pub fn top_k_by_prefix<I>(&self, prefix: I, k: usize) -> Vec<(Vec<K>, &T)>
+    where
+        I: IntoIterator<Item = K>,
+    {
+        self.top_k_nodes(prefix, k)
+            .into_iter()
+            .map(|(key, node)| (key, node.value().unwrap()))
+            .collect()
+    }
+
+    // top_k_by_prefix, handing out the nodes reached
+    pub(crate) fn top_k_nodes<I>(&self, prefix: I, k: usize) -> Vec<Ranked<'_, K, T, S, P>>
+    where
+        I: IntoIterator<Item = K>,
+    {
+        let prefix: Vec<K> = prefix.into_iter().collect();
+        match self.find_node(prefix.iter().cloned()) {
+            Some(node) => ranked::top_k(node, prefix, k),
+            None => Vec::new(),
+        }
+    }
+
+    /// Returns true if `key` is stored in the trie.
+    pub fn contains_key<I: IntoIterator<Item = K>>(&self, key: I) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Removes `key`, returning whether it was removed. Nodes left without
+    /// values or children are pruned.
+    pub fn remove<I: IntoIterator<Item = K>>(&mut self, key: I) -> bool {
+        self.remove_if(key, |_| true).is_some()
+    }
+
+    /// Removes `key` and returns its value, but only if `pred` returns true
+    /// for it; otherwise the trie is left untouched. Nodes are pruned as
+    /// with [`GenericTrie::remove`], and only if the value was removed.
+    pub fn remove_if<I, F>(&mut self, key: I, pred: F) -> Option<T>
+    where
+        I: IntoIterator<Item = K>,
+        F: FnOnce(&T) -> bool,
+    {
+        self.remove_node_if(key, |node| node.value().is_some_and(pred))
+            .map(|(value, _)| value)
+    }
+
+    /// Removes `key`, returning its value along with the priority it was
+    /// inserted with, if any. Nodes are pruned as with
+    /// [`GenericTrie::remove`].
+    pub fn remove_with_priority<I>(&mut self, key: I) -> Option<(T, Option<P>)>
+    where
+        I: IntoIterator<Item = K>,
+    {
+        self.remove_node_if(key, |node| node.value().is_some())
+    }
+
+    // Removes the value of `key`'s node, with its priority, if `pred`
+    // returns true for the node, pruning as remove_if does
+    pub(crate) fn remove_node_if<I, F>(&mut self, key: I, pred: F) -> Option<(T, Option<P>)>
+    where
+        I: IntoIterator<Item = K>,
+        F: FnOnce(&GenericNode<K, T, S, P>) -> bool,
+    {
+        let key: Vec<K> = key.into_iter().collect();
+        let removed = self
+            .root
+            .update_at(key.iter().cloned(), false, |node| {
+                if pred(node) {
+                    node.take_entry()
+                } else {
+                    None
+                }
+            })
+            .flatten();
+        if removed.is_some() {
+            self.prune(&key);
+        }
+        removed
+    }
+
+    // Drops the nodes at the end of `key`'s path left without values or
+    // children. Iterative, for any key length: a first walk finds the top
+    // of that chain, then it is detached from its parent in one go.
+    fn prune(&mut self, key: &[K]) {
+        // Every node of the chain has no value and no child but the next
+        // one on the path, and the last has no children at all
+        let mut chain_start = None;
+        let mut current_node = &self.root;
+        for (i, k) in key.iter().enumerate() {
+            current_node = current_node.child(k).unwrap();
+            if current_node.value().is_none() && current_node.child_count() <= 1 {
+                chain_start.get_or_insert(i);
+            } else {
+                chain_start = None;
+            }
+        }
+        let start = match chain_start {
+            Some(start) if !current_node.has_children() => start,
+            _ => return,
+        };
+        self.root
+            .update_at(key[..start].iter().cloned(), false, |parent| {
+                parent.remove_child(&key[start])
+            })
+            .unwrap();
+    }
+
+    /// Number of stored keys, in O(1): every node keeps count of the values
+    /// below it.
+    pub fn len(&self) -> usize {
+        self.root.count_values()
+    }
+
+    /// Returns true if no key is stored.
+    pub fn is_empty(&self) -> bool {
+        // Removal prunes emptied nodes, so any child leads to a value
+        self.root.value().is_none() && !self.root.has_children()
+    }
+
+    /// Clones the trie with every value replaced by `f` applied to it. The
+    /// keys, priorities and node layout are unchanged, and `T` needn't be
+    /// `Clone`.
+    pub fn clone_with_map<U, F: FnMut(&T) -> U>(&self, mut f: F) -> GenericTrie<K, U, S, P> {
+        GenericTrie {
+            root: self.root.clone_with_map(&mut f),
+        }
+    }
+
+    /// Iterates over all entries in lexicographic key order.
+    pub fn iter(&self) -> GenericIter<'_, K, T, S, P> {
+        GenericIter::new(&self.root, Vec::new())
+    }
+
+    /// Iterates over the entries whose keys start with `prefix`, in
+    /// lexicographic order. Keys are yielded in full, prefix included.
+    pub fn iter_prefix<I>(&self, prefix: I) -> GenericIter<'_, K, T, S, P>
+    where
+        I: IntoIterator<Item = K>,
+    {
+        let prefix: Vec<K> = prefix.into_iter().collect();
+        match self.find_node(prefix.iter().cloned()) {
+            Some(node) => GenericIter::new(node, prefix),
+            None => GenericIter::empty(),
+        }
+    }
+}
+
+impl<K: Default, T, S, P> Default for GenericTrie<K, T, S, P> {
+    fn default() -> GenericTrie<K, T, S, P> {
+        GenericTrie {
+            root: GenericNode::new(K::default(), None),
+        }
+    }
+}
+
+impl<K, T, S, P> PartialEq for GenericTrie<K, T, S, P>
+where
+    K: Ord + Hash,
+    T: PartialEq,
+    S: BuildHasher + Default,
+    P: PartialOrd + Clone,
+{
+    fn eq(&self, other: &GenericTrie<K, T, S, P>) -> bool {
+        self.root == other.root
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn token_keys() {
+        let mut trie = GenericTrie::<u32, &str>::new();
+        assert!(trie.insert([3, 1, 4], "pi"));
+        assert!(trie.insert([3, 1], "short"));
+        assert!(trie.insert(vec![2, 7, 1, 8], "e"));
+        assert!(trie.insert([], "empty"));
+        assert!(!trie.insert([3, 1, 4], "again"));
+        assert_eq!(
+            trie.try_insert([3, 1], "again"),
+            Err(TrieError::DuplicateKey)
+        );
+
+        assert_eq!(trie.get([3, 1, 4]), Some(&"pi"));
+        assert_eq!(trie.get([3]), None);
+        assert_eq!(trie.get([]), Some(&"empty"));
+        assert!(trie.contains_key(vec![2, 7, 1, 8]));
+        *trie.get_mut([3, 1]).unwrap() = "shorter";
+        assert_eq!(trie.len(), 4);
+
+        assert_eq!(
+            trie.iter().collect::<Vec<_>>(),
+            vec![
+                (vec![], &"empty"),
+                (vec![2, 7, 1, 8], &"e"),
+                (vec![3, 1], &"shorter"),
+                (vec![3, 1, 4], &"pi"),
+            ]
+        );
+        assert_eq!(
+            trie.iter_prefix([3]).collect::<Vec<_>>(),
+            vec![(vec![3, 1], &"shorter"), (vec![3, 1, 4], &"pi")]
+        );
+        assert_eq!(trie.iter_prefix([9]).next(), None);
+
+        assert!(!trie.remove([3]));
+        assert!(trie.remove([3, 1, 4]));
+        assert!(!trie
+            .root()
+            .child(3u32)
+            .unwrap()
+            .child(1u32)
+            .unwrap()
+            .has_children());
+        assert!(trie.remove([3, 1]));
+        assert!(!trie.root().has_child(3u32));
+        assert!(trie.remove([]));
+        assert!(trie.remove([2, 7, 1, 8]));
+        assert!(trie.is_empty());
+    }
+
+    #[test]
+    fn word_keys() {
+        fn words(sentence: &str) -> impl Iterator<Item = String> + '_ {
+            sentence.split(' ').map(String::from)
+        }
+
+        let mut trie = GenericTrie::<String, u32>::new();
+        assert!(trie.insert(words("the quick brown fox"), 1));
+        assert!(trie.insert(words("the quick red fox"), 2));
+        assert!(trie.insert(words("the lazy dog"), 3));
+
+        assert_eq!(trie.get(words("the quick red fox")), Some(&2));
+        assert_eq!(trie.get(words("the quick")), None);
+        assert_eq!(trie.root().child_count(), 1);
+        assert_eq!(
+            trie.iter_prefix(words("the quick"))
+                .map(|(key, value)| (key.join(" "), *value))
+                .collect::<Vec<_>>(),
+            vec![
+                (String::from("the quick brown fox"), 1),
+                (String::from("the quick red fox"), 2),
+            ]
+        );
+
+        assert!(trie.remove(words("the quick brown fox")));
+        let quick = trie
+            .root()
+            .child(String::from("the"))
+            .unwrap()
+            .child(String::from("quick"))
+            .unwrap();
+        assert!(!quick.has_child(String::from("brown")));
+        assert_eq!(trie.len(), 2);
+    }
+}
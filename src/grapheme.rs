@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+// Node keyed on an extended grapheme cluster rather than a single char, so a
+// user-perceived character like "é" (e + U+0301) occupies exactly one node
+#[derive(Debug, PartialEq)]
+struct GraphemeNode<T> {
+    key_cluster: String,
+    value: Option<T>,
+    children: HashMap<String, GraphemeNode<T>>,
+}
+
+impl<T> GraphemeNode<T> {
+    fn new(key_cluster: &str, value: Option<T>) -> GraphemeNode<T> {
+        GraphemeNode {
+            key_cluster: String::from(key_cluster),
+            value,
+            children: HashMap::new(),
+        }
+    }
+
+    #[cfg(test)]
+    fn key_cluster(&self) -> &str {
+        &self.key_cluster
+    }
+
+    fn child(&self, key_cluster: &str) -> Option<&GraphemeNode<T>> {
+        self.children.get(key_cluster)
+    }
+}
+
+/// Trie that keys on extended grapheme clusters rather than chars, so a
+/// user-perceived character like "é" (e + U+0301) occupies exactly one node.
+/// Enabled by the `graphemes` feature.
+#[derive(Debug, PartialEq)]
+pub struct GraphemeTrie<T> {
+    root: GraphemeNode<T>,
+}
+
+impl<T> Default for GraphemeTrie<T> {
+    fn default() -> GraphemeTrie<T> {
+        GraphemeTrie::new()
+    }
+}
+
+impl<T> GraphemeTrie<T> {
+    /// Creates an empty trie.
+    pub fn new() -> GraphemeTrie<T> {
+        GraphemeTrie {
+            root: GraphemeNode::new("", None),
+        }
+    }
+
+    /// Inserts `value` under `key`, one node per grapheme cluster. Returns
+    /// false, leaving the trie untouched, if `key` is empty or already stored.
+    pub fn insert(&mut self, key: &str, value: T) -> bool {
+        if key.is_empty() {
+            return false;
+        }
+
+        let mut current_node = &mut self.root;
+        for cluster in key.graphemes(true) {
+            if !current_node.children.contains_key(cluster) {
+                current_node
+                    .children
+                    .insert(String::from(cluster), GraphemeNode::new(cluster, None));
+            }
+            current_node = current_node.children.get_mut(cluster).unwrap();
+        }
+
+        if current_node.value.is_some() {
+            return false;
+        }
+        current_node.value = Some(value);
+        true
+    }
+
+    /// Removes `key`, pruning nodes left without values or children. Returns
+    /// whether it was removed.
+    pub fn remove(&mut self, key: &str) -> bool {
+        if key.is_empty() {
+            return false;
+        }
+
+        let clusters: Vec<&str> = key.graphemes(true).collect();
+        Self::remove_helper(&mut self.root, &clusters)
+    }
+
+    fn remove_helper(parent_node: &mut GraphemeNode<T>, clusters: &[&str]) -> bool {
+        let node = match parent_node.children.get_mut(clusters[0]) {
+            None => {
+                return false;
+            }
+            Some(v) => v,
+        };
+
+        let removed = if clusters.len() == 1 {
+            node.value.take().is_some()
+        } else {
+            Self::remove_helper(node, &clusters[1..])
+        };
+
+        if removed && node.value.is_none() && node.children.is_empty() {
+            parent_node.children.remove(clusters[0]);
+        }
+        removed
+    }
+
+    /// The value stored under `key`, if any.
+    pub fn get(&self, key: &str) -> Option<&T> {
+        if key.is_empty() {
+            return None;
+        }
+
+        let mut current_node = &self.root;
+        for cluster in key.graphemes(true) {
+            current_node = current_node.child(cluster)?;
+        }
+
+        current_node.value.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grapheme_clusters() {
+        let mut trie = GraphemeTrie::<u32>::new();
+
+        // "e" followed by a combining acute accent is one grapheme
+        let combining = "e\u{301}";
+        assert!(trie.insert(combining, 1));
+        assert_eq!(trie.root.children.len(), 1);
+        let node = trie.root.child(combining).unwrap();
+        assert_eq!(node.key_cluster(), combining);
+        assert!(node.children.is_empty());
+        assert_eq!(trie.get(combining), Some(&1));
+        assert_eq!(trie.get("e"), None);
+
+        // Thumbs up with a skin tone modifier is one grapheme
+        let thumbs_up = "\u{1F44D}\u{1F3FD}";
+        let key = format!("{}{}", thumbs_up, combining);
+        assert!(trie.insert(&key, 2));
+        let node = trie.root.child(thumbs_up).unwrap();
+        assert_eq!(node.children.len(), 1);
+        assert!(node.child(combining).unwrap().children.is_empty());
+        assert_eq!(trie.get(&key), Some(&2));
+        assert_eq!(trie.get("\u{1F44D}"), None);
+        assert!(!trie.insert(&key, 3));
+
+        assert!(trie.remove(&key));
+        assert_eq!(trie.get(&key), None);
+        assert_eq!(trie.root.child(thumbs_up), None);
+        assert!(!trie.remove(thumbs_up));
+        assert_eq!(trie.get(combining), Some(&1));
+    }
+}
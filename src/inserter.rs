@@ -0,0 +1,126 @@
+use crate::node::TrieNode;
+use crate::trie::Trie;
+
+/// Builds one key a char at a time, for parsers that receive keys
+/// incrementally, e.g. from a reader. Obtained from [`Trie::inserter`].
+///
+/// The finished key goes through the trie's normalizer, as with
+/// [`Trie::insert`]. Dropping an inserter without calling
+/// [`Inserter::finish`] leaves the trie unchanged.
+pub struct Inserter<'a, T> {
+    root: &'a mut TrieNode<T>,
+    // The trie's key count, bumped by finish
+    count: &'a mut usize,
+    normalizer: Option<fn(&str) -> String>,
+    // The chars pushed so far. Their nodes are only looked up or created by
+    // finish: a normalizer maps whole keys, so the path isn't known before
+    // then. finish walks down from the root anyway to update the value
+    // counts, and an abandoned key leaves no empty nodes behind.
+    key: String,
+}
+
+impl<T> Trie<T> {
+    /// An [`Inserter`] positioned at the empty key.
+    pub fn inserter(&mut self) -> Inserter<'_, T> {
+        let normalizer = self.normalizer();
+        let (root, count) = self.root_and_count_mut();
+        Inserter {
+            root,
+            count,
+            normalizer,
+            key: String::new(),
+        }
+    }
+}
+
+impl<'a, T> Inserter<'a, T> {
+    /// Appends `c` to the key.
+    pub fn push(&mut self, c: char) {
+        self.key.push(c);
+    }
+
+    /// Stores `value` under the key pushed so far, normalized, creating its
+    /// missing nodes. Like [`Trie::insert`], returns false, leaving the trie
+    /// untouched, if the key is already stored.
+    pub fn finish(self, value: T) -> bool {
+        let key = match self.normalizer {
+            Some(normalizer) => normalizer(&self.key),
+            None => self.key,
+        };
+        let inserted = self
+            .root
+            .update_at(key.chars(), true, |node| {
+                if node.value().is_some() {
+                    return false;
+                }
+                node.set_value(value);
+                true
+            })
+            .unwrap();
+        if inserted {
+            *self.count += 1;
+        }
+        inserted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Trie;
+
+    #[test]
+    fn push_and_finish() {
+        let mut trie = Trie::<u32>::new();
+        let mut inserter = trie.inserter();
+        for c in "aaaa".chars() {
+            inserter.push(c);
+        }
+        assert!(inserter.finish(4));
+        assert_eq!(trie.get("aaaa"), Some(&4));
+        assert_eq!(trie.get("aaa"), None);
+
+        let mut inserter = trie.inserter();
+        "aaaa".chars().for_each(|c| inserter.push(c));
+        assert!(!inserter.finish(5));
+        assert_eq!(trie.get("aaaa"), Some(&4));
+
+        let mut inserter = trie.inserter();
+        inserter.push('a');
+        inserter.push('é');
+        assert!(inserter.finish(2));
+        assert!(trie.inserter().finish(0));
+        assert!(!trie.inserter().finish(1));
+
+        // An abandoned key leaves no nodes behind
+        let nodes = trie.root().count_nodes();
+        let mut inserter = trie.inserter();
+        "abandoned".chars().for_each(|c| inserter.push(c));
+        drop(inserter);
+        assert_eq!(trie.root().count_nodes(), nodes);
+        assert_eq!(
+            trie.iter().collect::<Vec<_>>(),
+            vec![
+                (String::new(), &0),
+                (String::from("aaaa"), &4),
+                (String::from("aé"), &2)
+            ]
+        );
+    }
+
+    #[test]
+    fn finish_normalizes() {
+        let mut trie = Trie::<u32>::new_with_normalizer(|key: &str| key.to_lowercase());
+        let mut inserter = trie.inserter();
+        "Key".chars().for_each(|c| inserter.push(c));
+        assert!(inserter.finish(1));
+        assert_eq!(trie.iter().collect::<Vec<_>>(), [(String::from("key"), &1)]);
+        assert_eq!(trie.get("KEY"), Some(&1));
+        assert!(trie.contains_key("Key"));
+
+        let mut inserter = trie.inserter();
+        "kEY".chars().for_each(|c| inserter.push(c));
+        assert!(!inserter.finish(2));
+        assert!(trie.remove("KEY"));
+        assert!(trie.is_empty());
+    }
+}
@@ -0,0 +1,295 @@
+// Keys are walked from the most significant bit, one level per bit
+const KEY_BITS: u32 = u64::BITS;
+
+// Node of a binary trie. With at most two children, they live in two slots
+// indexed by the bit rather than in a map. IntTrie only stores values at
+// depth KEY_BITS; IpPrefixTrie stores them at any depth.
+#[derive(Debug, PartialEq)]
+pub(crate) struct IntNode<T> {
+    pub(crate) value: Option<T>,
+    pub(crate) children: [Option<Box<IntNode<T>>>; 2],
+}
+
+impl<T> IntNode<T> {
+    pub(crate) fn new() -> IntNode<T> {
+        IntNode {
+            value: None,
+            children: [None, None],
+        }
+    }
+
+    pub(crate) fn child(&self, bit: usize) -> Option<&IntNode<T>> {
+        self.children[bit].as_deref()
+    }
+
+    pub(crate) fn has_children(&self) -> bool {
+        self.children.iter().any(Option::is_some)
+    }
+}
+
+// Bit of `key` at `depth`, counting from the most significant bit
+fn bit(key: u64, depth: u32) -> usize {
+    ((key >> (KEY_BITS - 1 - depth)) & 1) as usize
+}
+
+/// Binary trie keyed by `u64`, branching on bits from the most significant
+/// down. Iteration is in ascending key order, and it answers XOR queries
+/// such as [`IntTrie::max_xor_with`] in 64 steps.
+#[derive(Debug, PartialEq)]
+pub struct IntTrie<T> {
+    root: IntNode<T>,
+}
+
+impl<T> Default for IntTrie<T> {
+    fn default() -> IntTrie<T> {
+        IntTrie::new()
+    }
+}
+
+impl<T> IntTrie<T> {
+    /// Creates an empty trie.
+    pub fn new() -> IntTrie<T> {
+        IntTrie {
+            root: IntNode::new(),
+        }
+    }
+
+    /// Inserts `value` under `key`. Returns false, leaving the trie
+    /// untouched, if `key` is already stored.
+    pub fn insert(&mut self, key: u64, value: T) -> bool {
+        let mut current_node = &mut self.root;
+        for depth in 0..KEY_BITS {
+            current_node = current_node.children[bit(key, depth)]
+                .get_or_insert_with(|| Box::new(IntNode::new()));
+        }
+
+        if current_node.value.is_some() {
+            return false;
+        }
+        current_node.value = Some(value);
+        true
+    }
+
+    // Node reached by the top `bits` bits of `key`
+    fn find_node(&self, key: u64, bits: u32) -> Option<&IntNode<T>> {
+        let mut current_node = &self.root;
+        for depth in 0..bits {
+            current_node = current_node.child(bit(key, depth))?;
+        }
+        Some(current_node)
+    }
+
+    /// The value stored under `key`, if any.
+    pub fn get(&self, key: u64) -> Option<&T> {
+        self.find_node(key, KEY_BITS)?.value.as_ref()
+    }
+
+    /// Returns true if `key` is stored in the trie.
+    pub fn contains_key(&self, key: u64) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Removes `key`, pruning nodes left without children. Returns whether
+    /// it was removed.
+    pub fn remove(&mut self, key: u64) -> bool {
+        Self::remove_helper(&mut self.root, key, 0)
+    }
+
+    fn remove_helper(node: &mut IntNode<T>, key: u64, depth: u32) -> bool {
+        if depth == KEY_BITS {
+            return node.value.take().is_some();
+        }
+
+        let slot = &mut node.children[bit(key, depth)];
+        let child = match slot {
+            Some(child) => child,
+            None => return false,
+        };
+        let removed = Self::remove_helper(child, key, depth + 1);
+        if removed && child.value.is_none() && !child.has_children() {
+            *slot = None;
+        }
+        removed
+    }
+
+    /// Returns true if no key is stored.
+    pub fn is_empty(&self) -> bool {
+        // Removal prunes emptied nodes, so any child leads to a value
+        !self.root.has_children()
+    }
+
+    /// Iterates over all entries in ascending key order.
+    pub fn iter(&self) -> IntIter<'_, T> {
+        IntIter {
+            stack: vec![(&self.root, 0)],
+        }
+    }
+
+    /// The keys whose top `bits` bits equal those of `prefix`, in ascending
+    /// order. With `bits` 0 that is every key.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bits` is more than 64.
+    pub fn keys_with_prefix(&self, prefix: u64, bits: u8) -> impl Iterator<Item = u64> + '_ {
+        let bits = u32::from(bits);
+        assert!(
+            bits <= KEY_BITS,
+            "prefix of {} bits is longer than a key",
+            bits
+        );
+        // The low bits of `prefix` are ignored
+        let stack = match self.find_node(prefix, bits) {
+            Some(node) => vec![(node, prefix.checked_shr(KEY_BITS - bits).unwrap_or(0))],
+            None => Vec::new(),
+        };
+        IntIter { stack }.map(|(key, _)| key)
+    }
+
+    /// The stored key whose XOR with `x` is largest, with its value, or None
+    /// if the trie is empty. Walks down greedily, taking the opposite of
+    /// `x`'s bit wherever that branch exists.
+    pub fn max_xor_with(&self, x: u64) -> Option<(u64, &T)> {
+        if self.is_empty() {
+            return None;
+        }
+
+        let mut current_node = &self.root;
+        let mut key = 0;
+        for depth in 0..KEY_BITS {
+            let wanted = 1 - bit(x, depth);
+            let taken = if current_node.child(wanted).is_some() {
+                wanted
+            } else {
+                1 - wanted
+            };
+            // Every node on a non-empty trie has a child above the leaves
+            current_node = current_node.child(taken).unwrap();
+            key = key << 1 | taken as u64;
+        }
+        Some((key, current_node.value.as_ref().unwrap()))
+    }
+}
+
+/// Iterator over an [`IntTrie`]'s entries in ascending key order.
+pub struct IntIter<'a, T> {
+    // Pre-order walk: node and the bits of the key leading to it,
+    // right-aligned
+    stack: Vec<(&'a IntNode<T>, u64)>,
+}
+
+impl<'a, T> Iterator for IntIter<'a, T> {
+    type Item = (u64, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((node, key)) = self.stack.pop() {
+            if let Some(value) = &node.value {
+                return Some((key, value));
+            }
+            for bit in [1, 0] {
+                if let Some(child) = node.child(bit) {
+                    self.stack.push((child, key << 1 | bit as u64));
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::XorShift64;
+
+    #[test]
+    fn insert_get_remove() {
+        let mut trie = IntTrie::new();
+        assert!(trie.is_empty());
+        for key in [5, 0, u64::MAX, 1 << 63, 3] {
+            assert!(trie.insert(key, key / 2));
+        }
+        assert!(!trie.insert(5, 0));
+        assert_eq!(trie.get(5), Some(&2));
+        assert_eq!(trie.get(u64::MAX), Some(&(u64::MAX / 2)));
+        assert_eq!(trie.get(4), None);
+        assert!(trie.contains_key(0));
+
+        assert_eq!(
+            trie.iter().map(|(key, _)| key).collect::<Vec<_>>(),
+            [0, 3, 5, 1 << 63, u64::MAX]
+        );
+
+        assert!(trie.remove(1 << 63));
+        assert!(!trie.remove(1 << 63));
+        assert!(!trie.remove(6));
+        // Only u64::MAX's path is left in the high half
+        assert!(trie.root.child(1).unwrap().child(0).is_none());
+        for key in [5, 0, u64::MAX, 3] {
+            assert!(trie.remove(key));
+        }
+        assert!(trie.is_empty());
+        assert_eq!(trie.root, IntNode::new());
+    }
+
+    #[test]
+    fn keys_with_prefix() {
+        let mut trie = IntTrie::new();
+        for key in [0b1010, 0b1011, 0b1100, 0b0010, 1 << 63] {
+            trie.insert(key, ());
+        }
+        // Top 62 bits of 0b1000..=0b1011
+        assert_eq!(
+            trie.keys_with_prefix(0b1000, 62).collect::<Vec<_>>(),
+            [0b1010, 0b1011]
+        );
+        assert_eq!(
+            trie.keys_with_prefix(0b1010, 64).collect::<Vec<_>>(),
+            [0b1010]
+        );
+        assert_eq!(trie.keys_with_prefix(0b1001, 64).next(), None);
+        assert_eq!(
+            trie.keys_with_prefix(1 << 63, 1).collect::<Vec<_>>(),
+            [1 << 63]
+        );
+        assert_eq!(trie.keys_with_prefix(u64::MAX, 0).count(), 5);
+        assert_eq!(trie.keys_with_prefix(0, 1).count(), 4);
+    }
+
+    #[test]
+    fn max_xor_matches_brute_force() {
+        let mut rng = XorShift64(0xdead_beef_cafe_f00d);
+        assert_eq!(IntTrie::<()>::new().max_xor_with(7), None);
+        for round in 0..50 {
+            let mut trie = IntTrie::new();
+            let mut keys = Vec::new();
+            for _ in 0..1 + rng.below(60) {
+                // Small keys share long prefixes, large ones exercise all bits
+                let key = if round % 2 == 0 {
+                    rng.below(64) as u64
+                } else {
+                    rng.next_u64()
+                };
+                if trie.insert(key, key) {
+                    keys.push(key);
+                }
+            }
+
+            for &x in keys.iter().chain(&[0, u64::MAX, rng.next_u64()]) {
+                let best = keys.iter().map(|key| key ^ x).max().unwrap();
+                let (key, value) = trie.max_xor_with(x).unwrap();
+                assert_eq!(key ^ x, best);
+                assert_eq!(*value, key);
+            }
+            // The maximum XOR pair, O(n²) versus O(64 n)
+            let brute = keys
+                .iter()
+                .flat_map(|a| keys.iter().map(move |b| a ^ b))
+                .max();
+            let fast = keys
+                .iter()
+                .map(|&a| a ^ trie.max_xor_with(a).unwrap().0)
+                .max();
+            assert_eq!(fast, brute);
+        }
+    }
+}
@@ -0,0 +1,372 @@
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use crate::int::IntNode;
+
+// Addresses are handled as their bits left-aligned in a u128, so IPv4 and
+// IPv6 share the bit walk
+fn address_bits(addr: IpAddr) -> u128 {
+    match addr {
+        IpAddr::V4(addr) => u128::from(u32::from(addr)) << 96,
+        IpAddr::V6(addr) => u128::from(addr),
+    }
+}
+
+// Bit of left-aligned `bits` at `depth`, counting from the most significant
+fn bit(bits: u128, depth: u8) -> usize {
+    ((bits >> (127 - depth)) & 1) as usize
+}
+
+// `bits` with everything past the first `len` bits cleared
+fn mask(bits: u128, len: u8) -> u128 {
+    bits & u128::MAX.checked_shl(128 - u32::from(len)).unwrap_or(0)
+}
+
+/// A CIDR prefix such as `10.0.0.0/8` or `2001:db8::/32`: an address whose
+/// bits past the prefix length are all zero.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct IpPrefix {
+    addr: IpAddr,
+    len: u8,
+}
+
+impl IpPrefix {
+    /// The prefix of the first `len` bits of `addr`; the remaining (host)
+    /// bits are cleared.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `len` is longer than the address (32 bits for IPv4, 128
+    /// for IPv6).
+    pub fn new(addr: IpAddr, len: u8) -> IpPrefix {
+        let max_len = match addr {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        assert!(
+            len <= max_len,
+            "prefix length {} is longer than the address",
+            len
+        );
+        IpPrefix::from_bits(addr.is_ipv6(), mask(address_bits(addr), len), len)
+    }
+
+    fn from_bits(is_ipv6: bool, bits: u128, len: u8) -> IpPrefix {
+        let addr = if is_ipv6 {
+            IpAddr::V6(Ipv6Addr::from(bits))
+        } else {
+            IpAddr::V4(Ipv4Addr::from((bits >> 96) as u32))
+        };
+        IpPrefix { addr, len }
+    }
+
+    /// The network address, with the host bits cleared.
+    pub fn addr(&self) -> IpAddr {
+        self.addr
+    }
+
+    /// The prefix length in bits.
+    pub fn len(&self) -> u8 {
+        self.len
+    }
+
+    /// Returns true for a zero-length prefix, such as the default route
+    /// `0.0.0.0/0`, which covers every address of its family.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl fmt::Display for IpPrefix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.len)
+    }
+}
+
+/// Routing table mapping CIDR prefixes to values, answering which stored
+/// prefix most specifically covers an address.
+///
+/// Internally it is a binary trie over the address bits, one per address
+/// family, where a prefix of length n is stored n levels deep. A
+/// zero-length prefix (the default route) lives on the family's root.
+/// IPv4 and IPv6 are kept apart, so an IPv4-mapped IPv6 address doesn't
+/// match IPv4 prefixes.
+#[derive(Debug, PartialEq)]
+pub struct IpPrefixTrie<T> {
+    v4: IntNode<T>,
+    v6: IntNode<T>,
+}
+
+impl<T> Default for IpPrefixTrie<T> {
+    fn default() -> IpPrefixTrie<T> {
+        IpPrefixTrie::new()
+    }
+}
+
+impl<T> IpPrefixTrie<T> {
+    /// Creates an empty table.
+    pub fn new() -> IpPrefixTrie<T> {
+        IpPrefixTrie {
+            v4: IntNode::new(),
+            v6: IntNode::new(),
+        }
+    }
+
+    fn root(&self, addr: IpAddr) -> &IntNode<T> {
+        match addr {
+            IpAddr::V4(_) => &self.v4,
+            IpAddr::V6(_) => &self.v6,
+        }
+    }
+
+    /// Inserts `value` for the prefix of the first `prefix_len` bits of
+    /// `addr`; host bits past the prefix are ignored. Returns false, leaving
+    /// the table untouched, if the prefix is already stored.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `prefix_len` is longer than the address.
+    pub fn insert(&mut self, addr: IpAddr, prefix_len: u8, value: T) -> bool {
+        let prefix = IpPrefix::new(addr, prefix_len);
+        let bits = address_bits(prefix.addr);
+        let mut current_node = match addr {
+            IpAddr::V4(_) => &mut self.v4,
+            IpAddr::V6(_) => &mut self.v6,
+        };
+        for depth in 0..prefix_len {
+            current_node = current_node.children[bit(bits, depth)]
+                .get_or_insert_with(|| Box::new(IntNode::new()));
+        }
+
+        if current_node.value.is_some() {
+            return false;
+        }
+        current_node.value = Some(value);
+        true
+    }
+
+    /// The value stored for exactly this prefix, if any.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `prefix_len` is longer than the address.
+    pub fn get(&self, addr: IpAddr, prefix_len: u8) -> Option<&T> {
+        let bits = address_bits(IpPrefix::new(addr, prefix_len).addr);
+        let mut current_node = self.root(addr);
+        for depth in 0..prefix_len {
+            current_node = current_node.child(bit(bits, depth))?;
+        }
+        current_node.value.as_ref()
+    }
+
+    /// The most specific (longest) stored prefix covering `addr`, with its
+    /// value. Less specific prefixes on the way, such as a default route,
+    /// only match when nothing longer does.
+    pub fn longest_match(&self, addr: IpAddr) -> Option<(IpPrefix, &T)> {
+        let bits = address_bits(addr);
+        let mut current_node = self.root(addr);
+        let mut best = current_node.value.as_ref().map(|value| (0, value));
+        let max_len = if addr.is_ipv6() { 128 } else { 32 };
+        for depth in 0..max_len {
+            current_node = match current_node.child(bit(bits, depth)) {
+                Some(child) => child,
+                None => break,
+            };
+            if let Some(value) = &current_node.value {
+                best = Some((depth + 1, value));
+            }
+        }
+        best.map(|(len, value)| (IpPrefix::new(addr, len), value))
+    }
+
+    /// Removes the prefix of the first `prefix_len` bits of `addr`, pruning
+    /// nodes left without values or children. Returns whether it was
+    /// removed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `prefix_len` is longer than the address.
+    pub fn remove(&mut self, addr: IpAddr, prefix_len: u8) -> bool {
+        let bits = address_bits(IpPrefix::new(addr, prefix_len).addr);
+        let root = match addr {
+            IpAddr::V4(_) => &mut self.v4,
+            IpAddr::V6(_) => &mut self.v6,
+        };
+        Self::remove_helper(root, bits, 0, prefix_len)
+    }
+
+    fn remove_helper(node: &mut IntNode<T>, bits: u128, depth: u8, len: u8) -> bool {
+        if depth == len {
+            return node.value.take().is_some();
+        }
+
+        let slot = &mut node.children[bit(bits, depth)];
+        let child = match slot {
+            Some(child) => child,
+            None => return false,
+        };
+        let removed = Self::remove_helper(child, bits, depth + 1, len);
+        if removed && child.value.is_none() && !child.has_children() {
+            *slot = None;
+        }
+        removed
+    }
+
+    /// Returns true if no prefix is stored.
+    pub fn is_empty(&self) -> bool {
+        [&self.v4, &self.v6]
+            .iter()
+            .all(|root| root.value.is_none() && !root.has_children())
+    }
+
+    /// Iterates over the stored prefixes: IPv4 before IPv6, each in address
+    /// order with a prefix before the longer prefixes it covers.
+    pub fn iter(&self) -> IpPrefixIter<'_, T> {
+        IpPrefixIter {
+            stack: vec![(&self.v6, true, 0, 0), (&self.v4, false, 0, 0)],
+        }
+    }
+}
+
+/// Iterator over an [`IpPrefixTrie`]'s prefixes and values.
+pub struct IpPrefixIter<'a, T> {
+    // Pre-order walk: node, whether it is in the IPv6 trie, and its
+    // left-aligned prefix bits and length
+    stack: Vec<(&'a IntNode<T>, bool, u128, u8)>,
+}
+
+impl<'a, T> Iterator for IpPrefixIter<'a, T> {
+    type Item = (IpPrefix, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((node, is_ipv6, bits, len)) = self.stack.pop() {
+            for bit in [1, 0] {
+                if let Some(child) = node.child(bit) {
+                    let child_bits = bits | (bit as u128) << (127 - len);
+                    self.stack.push((child, is_ipv6, child_bits, len + 1));
+                }
+            }
+            if let Some(value) = &node.value {
+                return Some((IpPrefix::from_bits(is_ipv6, bits, len), value));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(addr: &str) -> IpAddr {
+        addr.parse().unwrap()
+    }
+
+    fn routes() -> IpPrefixTrie<&'static str> {
+        let mut table = IpPrefixTrie::new();
+        for (addr, len, name) in [
+            ("0.0.0.0", 0, "default"),
+            ("10.0.0.0", 8, "ten"),
+            ("10.1.0.0", 16, "ten-one"),
+            ("10.1.2.3", 32, "host"),
+            ("192.168.0.0", 16, "lan"),
+            ("2001:db8::", 32, "doc"),
+            ("2001:db8::1", 128, "host6"),
+        ] {
+            assert!(table.insert(ip(addr), len, name));
+        }
+        table
+    }
+
+    fn longest(table: &IpPrefixTrie<&'static str>, addr: &str) -> Option<(String, &'static str)> {
+        table
+            .longest_match(ip(addr))
+            .map(|(prefix, name)| (prefix.to_string(), *name))
+    }
+
+    #[test]
+    fn longest_match() {
+        let table = routes();
+        let known = [
+            ("10.1.2.3", "10.1.2.3/32", "host"),
+            ("10.1.2.4", "10.1.0.0/16", "ten-one"),
+            ("10.200.0.1", "10.0.0.0/8", "ten"),
+            ("192.168.44.1", "192.168.0.0/16", "lan"),
+            ("8.8.8.8", "0.0.0.0/0", "default"),
+            ("2001:db8::1", "2001:db8::1/128", "host6"),
+            ("2001:db8::2", "2001:db8::/32", "doc"),
+        ];
+        for (addr, prefix, name) in known {
+            assert_eq!(
+                longest(&table, addr),
+                Some((String::from(prefix), name)),
+                "{}",
+                addr
+            );
+        }
+        // No IPv6 default route, and IPv4-mapped addresses stay IPv6
+        assert_eq!(longest(&table, "2001:db9::1"), None);
+        assert_eq!(longest(&table, "::ffff:10.1.2.3"), None);
+    }
+
+    #[test]
+    fn insert_get_remove() {
+        let mut table = routes();
+        // Host bits are ignored
+        assert!(!table.insert(ip("10.9.9.9"), 8, "again"));
+        assert_eq!(table.get(ip("10.255.0.0"), 8), Some(&"ten"));
+        assert_eq!(table.get(ip("10.1.0.0"), 15), None);
+
+        // With the more specific route gone, the covering one answers
+        assert!(table.remove(ip("10.1.0.0"), 16));
+        assert!(!table.remove(ip("10.1.0.0"), 16));
+        assert_eq!(longest(&table, "10.1.2.4").unwrap().1, "ten");
+        assert_eq!(longest(&table, "10.1.2.3").unwrap().1, "host");
+
+        assert!(table.remove(ip("0.0.0.0"), 0));
+        assert_eq!(longest(&table, "8.8.8.8"), None);
+        assert!(table.insert(ip("::"), 0, "default6"));
+        assert_eq!(longest(&table, "2001:db9::1").unwrap().1, "default6");
+
+        for (addr, len) in [
+            ("10.0.0.0", 8),
+            ("10.1.2.3", 32),
+            ("192.168.0.0", 16),
+            ("2001:db8::", 32),
+            ("2001:db8::1", 128),
+            ("::", 0),
+        ] {
+            assert!(table.remove(ip(addr), len));
+        }
+        assert!(table.is_empty());
+        assert_eq!(table, IpPrefixTrie::new());
+    }
+
+    #[test]
+    fn iter() {
+        let table = routes();
+        let prefixes: Vec<String> = table.iter().map(|(prefix, _)| prefix.to_string()).collect();
+        assert_eq!(
+            prefixes,
+            [
+                "0.0.0.0/0",
+                "10.0.0.0/8",
+                "10.1.0.0/16",
+                "10.1.2.3/32",
+                "192.168.0.0/16",
+                "2001:db8::/32",
+                "2001:db8::1/128",
+            ]
+        );
+        let prefix = IpPrefix::new(ip("10.1.2.3"), 8);
+        assert_eq!(prefix.addr(), ip("10.0.0.0"));
+        assert_eq!(prefix.len(), 8);
+        assert!(IpPrefix::new(ip("::1"), 0).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "prefix length 33 is longer than the address")]
+    fn prefix_too_long() {
+        IpPrefixTrie::new().insert(ip("10.0.0.0"), 33, ());
+    }
+}
@@ -0,0 +1,250 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hash};
+
+use crate::node::{GenericNode, TrieNode};
+
+/// Iterator over a trie's entries in lexicographic key order, from
+/// [`Trie::iter`](crate::Trie::iter) and
+/// [`Trie::iter_prefix`](crate::Trie::iter_prefix).
+pub struct Iter<'a, T, S = RandomState> {
+    // Pre-order walk over a subtree, which visits keys in lexicographic
+    // order. One key buffer is shared between steps and cloned for each entry.
+    //
+    // Node, key length (in bytes) before it, and whether its char is part of
+    // the key (false for the node the walk starts from)
+    stack: Vec<(&'a TrieNode<T, S>, usize, bool)>,
+    key: String,
+}
+
+impl<'a, T, S> Iter<'a, T, S> {
+    // Walk the subtree under `node`, whose own key is `key`
+    pub(crate) fn new(node: &'a TrieNode<T, S>, key: String) -> Iter<'a, T, S> {
+        Iter {
+            stack: vec![(node, key.len(), false)],
+            key,
+        }
+    }
+}
+
+impl<'a, T, S: BuildHasher + Default> Iter<'a, T, S> {
+    // Walk the subtree under `node`, whose own key is `key`, starting right
+    // after the key `key` + `rest`, which needn't be stored. The walk down
+    // `rest` stacks the subtrees sorting after it, nearest last, so nothing
+    // before it is visited.
+    pub(crate) fn after(node: &'a TrieNode<T, S>, mut key: String, rest: &str) -> Iter<'a, T, S> {
+        let mut stack = Vec::new();
+        let mut current_node = node;
+        for c in rest.chars() {
+            for child in current_node.children_in_order().rev() {
+                if child.key_char() <= c {
+                    break;
+                }
+                stack.push((child, key.len(), true));
+            }
+            current_node = match current_node.child(c) {
+                Some(child) => child,
+                None => return Iter { stack, key },
+            };
+            key.push(c);
+        }
+        // All of the last node's descendants sort after its own key
+        for child in current_node.children_in_order().rev() {
+            stack.push((child, key.len(), true));
+        }
+        Iter { stack, key }
+    }
+}
+
+impl<'a, T, S> Iter<'a, T, S> {
+    pub(crate) fn empty() -> Iter<'a, T, S> {
+        Iter {
+            stack: Vec::new(),
+            key: String::new(),
+        }
+    }
+}
+
+impl<'a, T, S: BuildHasher + Default> Iter<'a, T, S> {
+    // The next entry with its key borrowed from the shared buffer, for
+    // walks that don't need to keep the keys
+    pub(crate) fn next_entry(&mut self) -> Option<(&str, &'a T)> {
+        while let Some((node, parent_len, push_char)) = self.stack.pop() {
+            self.key.truncate(parent_len);
+            if push_char {
+                self.key.push(node.key_char());
+            }
+            for child in node.children_in_order().rev() {
+                self.stack.push((child, self.key.len(), true));
+            }
+            if let Some(value) = node.value() {
+                return Some((&self.key, value));
+            }
+        }
+        None
+    }
+}
+
+impl<'a, T, S: BuildHasher + Default> Iterator for Iter<'a, T, S> {
+    type Item = (String, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_entry()
+            .map(|(key, value)| (String::from(key), value))
+    }
+}
+
+/// Iterator over a trie's entries in lexicographic key order with mutable
+/// values, from [`Trie::iter_mut`](crate::Trie::iter_mut).
+pub struct IterMut<'a, T, S = RandomState> {
+    // Same walk as Iter. Each node is popped off the stack exactly once and
+    // split into its value and children, so the `&mut` borrows handed out
+    // never overlap.
+    stack: Vec<(&'a mut TrieNode<T, S>, usize, bool)>,
+    key: String,
+}
+
+impl<'a, T, S> IterMut<'a, T, S> {
+    pub(crate) fn new(node: &'a mut TrieNode<T, S>) -> IterMut<'a, T, S> {
+        IterMut {
+            stack: vec![(node, 0, false)],
+            key: String::new(),
+        }
+    }
+}
+
+impl<'a, T, S: BuildHasher + Default> Iterator for IterMut<'a, T, S> {
+    type Item = (String, &'a mut T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((node, parent_len, push_char)) = self.stack.pop() {
+            self.key.truncate(parent_len);
+            if push_char {
+                self.key.push(node.key_char());
+            }
+            let (value, children) = node.split_mut();
+            for child in children.into_iter().rev() {
+                self.stack.push((child, self.key.len(), true));
+            }
+            if let Some(value) = value.as_mut() {
+                return Some((self.key.clone(), value));
+            }
+        }
+        None
+    }
+}
+
+/// Iterator over a [`GenericTrie`](crate::GenericTrie)'s entries in
+/// lexicographic key order.
+pub struct GenericIter<'a, K, T, S = RandomState, P = f64> {
+    // Same walk as Iter, with the key kept as a Vec of symbols
+    #[allow(clippy::type_complexity)]
+    stack: Vec<(&'a GenericNode<K, T, S, P>, usize, bool)>,
+    key: Vec<K>,
+}
+
+impl<'a, K, T, S, P> GenericIter<'a, K, T, S, P> {
+    // Walk the subtree under `node`, whose own key is `key`
+    pub(crate) fn new(
+        node: &'a GenericNode<K, T, S, P>,
+        key: Vec<K>,
+    ) -> GenericIter<'a, K, T, S, P> {
+        GenericIter {
+            stack: vec![(node, key.len(), false)],
+            key,
+        }
+    }
+
+    pub(crate) fn empty() -> GenericIter<'a, K, T, S, P> {
+        GenericIter {
+            stack: Vec::new(),
+            key: Vec::new(),
+        }
+    }
+}
+
+impl<'a, K, T, S, P> Iterator for GenericIter<'a, K, T, S, P>
+where
+    K: Ord + Hash + Clone,
+    S: BuildHasher + Default,
+    P: PartialOrd + Clone,
+{
+    type Item = (Vec<K>, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((node, parent_len, push_key)) = self.stack.pop() {
+            self.key.truncate(parent_len);
+            if push_key {
+                self.key.push(node.key().clone());
+            }
+            for child in node.children_in_order().rev() {
+                self.stack.push((child, self.key.len(), true));
+            }
+            if let Some(value) = node.value() {
+                return Some((self.key.clone(), value));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Trie;
+
+    #[test]
+    fn prefix_iteration() {
+        let mut trie = Trie::<u32>::new();
+        trie.insert("a", 1);
+        trie.insert("aa", 2);
+        trie.insert("aaa", 3);
+        trie.insert("ab", 4);
+        trie.insert("b", 5);
+        assert_eq!(
+            trie.iter_prefix("aa").collect::<Vec<_>>(),
+            vec![(String::from("aa"), &2), (String::from("aaa"), &3)]
+        );
+        assert_eq!(
+            trie.keys_with_prefix("a").collect::<Vec<_>>(),
+            vec!["a", "aa", "aaa", "ab"]
+        );
+        assert_eq!(trie.iter_prefix("").count(), 5);
+        assert_eq!(trie.iter_prefix("aab").next(), None);
+        assert_eq!(trie.keys_with_prefix("c").next(), None);
+        assert_eq!(
+            trie.iter_prefix("b").map(|(_, value)| *value).sum::<u32>(),
+            5
+        );
+        assert_eq!(trie.values_with_prefix("aa").sum::<u32>(), 5);
+        assert_eq!(
+            trie.values_with_prefix("a").collect::<Vec<_>>(),
+            vec![&1, &2, &3, &4]
+        );
+        assert_eq!(trie.values_with_prefix("c").next(), None);
+    }
+
+    #[test]
+    fn mutable_iteration() {
+        let mut trie = Trie::<usize>::new();
+        assert_eq!(trie.iter_mut().next(), None);
+        // 40 children under "k", more than a small node holds
+        let keys: Vec<String> = (b'0'..b'0' + 40)
+            .map(|c| format!("k{}", char::from(c)))
+            .collect();
+        for (i, key) in keys.iter().enumerate() {
+            trie.insert(key, i);
+        }
+        trie.insert("", 100);
+
+        for (_, value) in trie.iter_mut() {
+            *value *= 2;
+        }
+        assert_eq!(trie.get(""), Some(&200));
+        for (i, key) in keys.iter().enumerate() {
+            assert_eq!(trie.get(key), Some(&(2 * i)));
+        }
+        // Same order as iter()
+        let mut_keys: Vec<String> = trie.iter_mut().map(|(key, _)| key).collect();
+        let ref_keys: Vec<String> = trie.iter().map(|(key, _)| key).collect();
+        assert_eq!(mut_keys, ref_keys);
+    }
+}
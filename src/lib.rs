@@ -0,0 +1,131 @@
+//! Trie (prefix tree) data structures, after the one in CMU's BusTub.
+//!
+//! [`Trie`] is the general purpose structure: it maps string keys to values
+//! with one node per char and supports ordered iteration, prefix and range
+//! queries, rank/select and more. The other tries trade generality for speed
+//! or a different notion of "character":
+//!
+//! - [`AsciiTrie`] only accepts `[a-z]` keys and indexes children directly.
+//! - [`ArenaTrie`] keeps all nodes in one flat arena.
+//! - [`SlabTrie`] allocates nodes from large slabs, linked by pointers.
+//! - [`TstTrie`] is a ternary search tree, trading comparisons for memory.
+//! - [`BytesTrie`] is keyed by arbitrary byte strings.
+//! - [`IntTrie`] is a binary trie keyed by `u64`, with XOR queries.
+//! - [`IpPrefixTrie`] is a routing table of CIDR prefixes.
+//! - [`PersistentTrie`] is immutable: updates return new versions that
+//!   share unchanged subtrees.
+//! - [`WeightedTrie`] answers top-k autocomplete queries with the values as
+//!   weights, for any `Ord` type. [`Trie::top_k_by_prefix`] does the same
+//!   by priorities inserted along with the values.
+//! - [`ConcurrentTrie`] can be shared and updated across threads.
+//! - [`FrozenTrie`], built by [`Trie::freeze`], is a read-only trie that
+//!   threads share without locking.
+//! - [`UndoTrie`] rolls changes back to a checkpoint.
+//! - [`TrieSet`] is a set of strings.
+//! - [`MultiTrie`] stores any number of values per key.
+//! - [`GenericTrie`] is keyed by sequences of any symbol type, e.g. token
+//!   ids; [`Trie`] is its `char` specialization.
+//! - [`SegmentTrie`] is keyed by delimiter-separated segments, such as
+//!   path components. [`Router`] builds URL routing with parameter
+//!   captures on top of it.
+//! - [`PathTrie`] is keyed by filesystem paths, one level per component.
+//! - [`Dawg`], built by [`Trie::minimize`], is a read-only trie sharing
+//!   equivalent suffixes.
+//! - [`AcMatcher`], built by [`Trie::build_matcher`], finds every key
+//!   occurring in a text in one pass.
+//! - `GraphemeTrie`, behind the `graphemes` feature, keys on grapheme
+//!   clusters.
+//!
+//! Tries of [`TrieSerialize`] values can be saved with `Trie::serialize` and
+//! loaded back with `Trie::deserialize`.
+//!
+//! The `rand` feature adds `Trie::sample` and `Trie::random_key` for
+//! picking random keys.
+//!
+//! The `rayon` feature adds `Trie::par_from_iter` and rayon's
+//! `ParallelExtend` and `FromParallelIterator` for building tries on all
+//! cores.
+//!
+//! ```
+//! use bustub::Trie;
+//!
+//! let mut trie = Trie::new();
+//! trie.insert("tea", 1);
+//! trie.insert("ten", 2);
+//! trie.insert("to", 3);
+//! assert_eq!(trie.get("ten"), Some(&2));
+//! assert_eq!(trie.keys_with_prefix("te").collect::<Vec<_>>(), ["tea", "ten"]);
+//! ```
+
+mod arena;
+mod ascii;
+mod bloom;
+mod bytes;
+mod dawg;
+mod error;
+mod frozen;
+mod fuzzy;
+mod generic;
+#[cfg(feature = "graphemes")]
+mod grapheme;
+mod inserter;
+mod int;
+mod ip;
+mod iter;
+mod matcher;
+mod multi;
+mod node;
+#[cfg(feature = "rayon")]
+mod par;
+mod path;
+mod persistent;
+mod ranked;
+mod router;
+#[cfg(feature = "rand")]
+mod sample;
+mod segment;
+mod serialize;
+mod set;
+mod slab;
+mod stats;
+mod sync;
+#[cfg(test)]
+mod test_util;
+mod tokenize;
+mod trie;
+mod tst;
+mod undo;
+mod weighted;
+
+pub use arena::{ArenaIter, ArenaIterMut, ArenaTrie};
+pub use ascii::{AsciiIter, AsciiKeyError, AsciiTrie};
+pub use bloom::PrefixBloom;
+pub use bytes::{BytesIter, BytesTrie};
+pub use dawg::{Dawg, DawgIter, MinimizedTrie};
+pub use error::TrieError;
+pub use frozen::FrozenTrie;
+pub use fuzzy::DistanceMetric;
+pub use generic::GenericTrie;
+#[cfg(feature = "graphemes")]
+pub use grapheme::GraphemeTrie;
+pub use inserter::Inserter;
+pub use int::{IntIter, IntTrie};
+pub use ip::{IpPrefix, IpPrefixIter, IpPrefixTrie};
+pub use iter::{GenericIter, Iter, IterMut};
+pub use matcher::{AcMatcher, FindIter, Match, TextMatch};
+pub use multi::MultiTrie;
+pub use node::{ChildrenIter, GenericNode, TrieNode};
+pub use path::PathTrie;
+pub use persistent::{PersistentIter, PersistentTrie};
+pub use router::{Params, RouteError, Router};
+pub use segment::SegmentTrie;
+pub use serialize::TrieSerialize;
+pub use set::TrieSet;
+pub use slab::{SlabIter, SlabTrie};
+pub use stats::{MemoryUsage, TrieStats};
+pub use sync::ConcurrentTrie;
+pub use tokenize::Segment;
+pub use trie::{DebugFull, Page, PrefixStatus, Trie};
+pub use tst::{TstIter, TstTrie};
+pub use undo::{CheckpointId, UndoTrie};
+pub use weighted::WeightedTrie;
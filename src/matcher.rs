@@ -0,0 +1,380 @@
+use std::collections::VecDeque;
+use std::ops::Range;
+use std::str::CharIndices;
+
+use crate::trie::Trie;
+
+// Index of a state in AcMatcher::states; the root is 0
+type StateId = u32;
+
+const ROOT: StateId = 0;
+
+// One trie node turned automaton state
+#[derive(Debug)]
+struct State<V> {
+    // Transitions along trie edges, sorted by char
+    goto: Vec<(char, StateId)>,
+    // State for the longest proper suffix of this state's string that is
+    // also a trie path
+    fail: StateId,
+    // Nearest state along the fail chain that ends a key, so reporting
+    // matches skips the states without one
+    output_link: Option<StateId>,
+    // Byte length and value of the key ending here, if any
+    output: Option<(usize, V)>,
+}
+
+impl<V> State<V> {
+    fn goto(&self, c: char) -> Option<StateId> {
+        self.goto
+            .binary_search_by_key(&c, |(d, _)| *d)
+            .ok()
+            .map(|i| self.goto[i].1)
+    }
+}
+
+/// Aho–Corasick automaton compiled from a [`Trie`] by
+/// [`Trie::build_matcher`], reporting every occurrence of every key in a
+/// text in one pass over it.
+///
+/// The matcher is a snapshot: later changes to the trie aren't reflected.
+/// Keys are matched as stored, i.e. after normalization, against the raw
+/// text. The empty key is never reported.
+#[derive(Debug)]
+pub struct AcMatcher<V> {
+    states: Vec<State<V>>,
+}
+
+impl<T> Trie<T> {
+    /// Compiles the keys into an [`AcMatcher`] borrowing their values.
+    /// Failure links are computed breadth-first over the existing node
+    /// structure, so building is linear in the number of nodes (times the
+    /// alphabet size in the worst case).
+    pub fn build_matcher(&self) -> AcMatcher<&T> {
+        // States are numbered in BFS order, which is also the order the
+        // failure links have to be computed in: a state's failure target is
+        // always shallower
+        let mut states = Vec::new();
+        let mut parents = Vec::new();
+        let mut queue = VecDeque::from([(self.root(), 0)]);
+        while let Some((node, depth)) = queue.pop_front() {
+            let first_child = states.len() + queue.len() + 1;
+            let children = node.sorted_children();
+            let goto = children
+                .iter()
+                .enumerate()
+                .map(|(i, child)| (child.key_char(), (first_child + i) as StateId))
+                .collect();
+            let id = states.len() as StateId;
+            for child in children {
+                parents.push((id, child.key_char()));
+                queue.push_back((child, depth + child.key_char().len_utf8()));
+            }
+
+            let output = node
+                .value()
+                .filter(|_| depth > 0)
+                .map(|value| (depth, value));
+            states.push(State {
+                goto,
+                fail: ROOT,
+                output_link: None,
+                output,
+            });
+        }
+
+        // parents[i] is the parent and edge char of state i + 1
+        for (i, &(parent, c)) in parents.iter().enumerate() {
+            let id = i + 1;
+            let fail = if parent == ROOT {
+                ROOT
+            } else {
+                let mut state = states[parent as usize].fail;
+                loop {
+                    if let Some(next) = states[state as usize].goto(c) {
+                        break next;
+                    }
+                    if state == ROOT {
+                        break ROOT;
+                    }
+                    state = states[state as usize].fail;
+                }
+            };
+            let fail_state = &states[fail as usize];
+            let output_link = if fail_state.output.is_some() {
+                Some(fail)
+            } else {
+                fail_state.output_link
+            };
+            states[id].fail = fail;
+            states[id].output_link = output_link;
+        }
+
+        AcMatcher { states }
+    }
+
+    /// Every key occurrence in `text`, found by walking the trie from each
+    /// char boundary as far as the text allows. Overlapping occurrences are
+    /// all reported, ordered by start and then by length. Keys are matched
+    /// as stored, and the empty key is never reported.
+    ///
+    /// This costs O(text length × longest key); for many texts or long
+    /// keys, [`Trie::build_matcher`] does it in one pass.
+    pub fn find_matches<'t>(&self, text: &'t str) -> Vec<TextMatch<'t, &T>> {
+        let mut matches = Vec::new();
+        for (start, _) in text.char_indices() {
+            let mut current_node = self.root();
+            for (i, c) in text[start..].char_indices() {
+                current_node = match current_node.child(c) {
+                    Some(child) => child,
+                    None => break,
+                };
+                if let Some(value) = current_node.value() {
+                    matches.push(Match {
+                        text,
+                        start,
+                        end: start + i + c.len_utf8(),
+                        value,
+                    });
+                }
+            }
+        }
+        matches
+    }
+}
+
+impl<V> AcMatcher<V> {
+    /// Iterates over every key occurrence in `text`, overlapping ones
+    /// included, ordered by end position and, for equal ends, longest key
+    /// first.
+    pub fn find_iter<'m, 't>(&'m self, text: &'t str) -> FindIter<'m, 't, V> {
+        FindIter {
+            matcher: self,
+            text,
+            chars: text.char_indices(),
+            state: ROOT,
+            pending: None,
+            end: 0,
+        }
+    }
+
+    // State after reading `c` in `state`
+    fn next_state(&self, mut state: StateId, c: char) -> StateId {
+        loop {
+            if let Some(next) = self.states[state as usize].goto(c) {
+                return next;
+            }
+            if state == ROOT {
+                return ROOT;
+            }
+            state = self.states[state as usize].fail;
+        }
+    }
+}
+
+/// One key occurrence found by [`AcMatcher::find_iter`] or
+/// [`Trie::find_matches`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Match<'t, V> {
+    text: &'t str,
+    start: usize,
+    end: usize,
+    value: V,
+}
+
+impl<'t, V: Copy> Match<'t, V> {
+    /// Byte offset in the text where the key starts.
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    /// Byte offset in the text just past the key.
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    /// The byte range of the key in the text.
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    /// The matched key, as it appears in the text.
+    pub fn key(&self) -> &'t str {
+        &self.text[self.start..self.end]
+    }
+
+    /// The value stored under the key.
+    pub fn value(&self) -> V {
+        self.value
+    }
+}
+
+/// A key occurrence reported by [`Trie::find_matches`]; the same type the
+/// automaton reports.
+pub type TextMatch<'t, V> = Match<'t, V>;
+
+/// Iterator over the key occurrences in a text, from
+/// [`AcMatcher::find_iter`].
+pub struct FindIter<'m, 't, V> {
+    matcher: &'m AcMatcher<V>,
+    text: &'t str,
+    chars: CharIndices<'t>,
+    state: StateId,
+    // Next state on the output chain still to report for the text up to
+    // `end`
+    pending: Option<StateId>,
+    end: usize,
+}
+
+impl<'m, 't, V: Copy> Iterator for FindIter<'m, 't, V> {
+    type Item = Match<'t, V>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(id) = self.pending {
+                let state = &self.matcher.states[id as usize];
+                self.pending = state.output_link;
+                let (len, value) = state.output.unwrap();
+                return Some(Match {
+                    text: self.text,
+                    start: self.end - len,
+                    end: self.end,
+                    value,
+                });
+            }
+
+            let (i, c) = self.chars.next()?;
+            self.state = self.matcher.next_state(self.state, c);
+            self.end = i + c.len_utf8();
+            let state = &self.matcher.states[self.state as usize];
+            self.pending = if state.output.is_some() {
+                Some(self.state)
+            } else {
+                state.output_link
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Trie;
+
+    fn matches(trie: &Trie<u32>, text: &str) -> Vec<(usize, usize, String, u32)> {
+        trie.build_matcher()
+            .find_iter(text)
+            .map(|m| (m.start(), m.end(), m.key().to_string(), *m.value()))
+            .collect()
+    }
+
+    fn trie(keys: &[&str]) -> Trie<u32> {
+        let mut trie = Trie::new();
+        for (i, key) in keys.iter().enumerate() {
+            trie.insert(key, i as u32);
+        }
+        trie
+    }
+
+    // Every (start, end, key) occurrence by trying each key at each offset
+    fn brute_force(keys: &[&str], text: &str) -> Vec<(usize, usize, String)> {
+        let mut found = Vec::new();
+        for (start, _) in text.char_indices() {
+            for key in keys.iter().filter(|key| !key.is_empty()) {
+                if text[start..].starts_with(key) {
+                    found.push((start, start + key.len(), key.to_string()));
+                }
+            }
+        }
+        found.sort_by_key(|(start, end, _)| (*end, *start));
+        found
+    }
+
+    #[test]
+    fn overlapping_patterns() {
+        let trie = trie(&["he", "she", "his", "hers"]);
+        assert_eq!(
+            matches(&trie, "ushers"),
+            vec![
+                (1, 4, String::from("she"), 1),
+                (2, 4, String::from("he"), 0),
+                (2, 6, String::from("hers"), 3),
+            ]
+        );
+        assert!(matches(&trie, "").is_empty());
+        assert!(matches(&trie, "xyz").is_empty());
+    }
+
+    #[test]
+    fn prefixes_of_each_other() {
+        let keys = ["a", "aa", "aaa", "ab", "b", ""];
+        let trie = trie(&keys);
+        let found: Vec<(usize, usize, String)> = matches(&trie, "aaab")
+            .into_iter()
+            .map(|(start, end, key, _)| (start, end, key))
+            .collect();
+        assert_eq!(found, brute_force(&keys, "aaab"));
+        assert_eq!(found.len(), 8);
+    }
+
+    #[test]
+    fn multi_byte_text() {
+        let keys = ["né", "été", "é", "日本", "本語"];
+        let trie = trie(&keys);
+        let text = "un été né au 日本語";
+        let found = matches(&trie, text);
+        for (start, end, key, _) in &found {
+            assert_eq!(&text[*start..*end], key);
+        }
+        let found: Vec<(usize, usize, String)> = found
+            .into_iter()
+            .map(|(start, end, key, _)| (start, end, key))
+            .collect();
+        assert_eq!(found, brute_force(&keys, text));
+    }
+
+    #[test]
+    fn find_matches_nested_terms() {
+        let trie = trie(&["java", "javascript", "script", "café", "é", "naïve", ""]);
+        let text = "Naïve javascript at the café: java, Javascript, scripté.";
+        let found: Vec<(usize, &str, u32)> = trie
+            .find_matches(text)
+            .iter()
+            .map(|m| (m.start(), m.key(), *m.value()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (7, "java", 0),
+                (7, "javascript", 1),
+                (11, "script", 2),
+                (25, "café", 3),
+                (28, "é", 4),
+                (32, "java", 0),
+                (42, "script", 2),
+                (50, "script", 2),
+                (56, "é", 4),
+            ]
+        );
+        for m in trie.find_matches(text) {
+            assert_eq!(&text[m.range()], m.key());
+        }
+
+        // The same occurrences as the automaton, in another order
+        let mut found = trie.find_matches(text);
+        found.sort_by_key(|m| (m.end(), m.start()));
+        assert_eq!(
+            found,
+            trie.build_matcher().find_iter(text).collect::<Vec<_>>()
+        );
+        assert!(trie.find_matches("").is_empty());
+    }
+
+    #[test]
+    fn reused_across_texts() {
+        let trie = trie(&["cat", "at", "tac"]);
+        let matcher = trie.build_matcher();
+        for (text, count) in [("cat", 2), ("tacat", 3), ("dog", 0), ("catcat", 4)] {
+            assert_eq!(matcher.find_iter(text).count(), count, "{}", text);
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use crate::trie::Trie;
+
+/// A trie storing any number of values per key, e.g. for inverted indexes
+/// or tag stores.
+#[derive(Debug, Default)]
+pub struct MultiTrie<T> {
+    // Keys with no values left are removed, so no stored Vec is empty
+    trie: Trie<Vec<T>>,
+}
+
+impl<T> MultiTrie<T> {
+    /// Creates an empty trie.
+    pub fn new() -> MultiTrie<T> {
+        MultiTrie { trie: Trie::new() }
+    }
+
+    /// Appends `value` to the values of `key`.
+    pub fn insert(&mut self, key: &str, value: T) {
+        match self.trie.get_mut(key) {
+            Some(values) => values.push(value),
+            None => {
+                self.trie.insert(key, vec![value]);
+            }
+        }
+    }
+
+    /// All values of `key`, in insertion order.
+    pub fn get_all(&self, key: &str) -> &[T] {
+        self.trie.get(key).map_or(&[], Vec::as_slice)
+    }
+
+    /// Removes the first value of `key` equal to `value`, and the key itself
+    /// once it has no values left. Returns whether a value was removed.
+    pub fn remove_one(&mut self, key: &str, value: &T) -> bool
+    where
+        T: PartialEq,
+    {
+        let values = match self.trie.get_mut(key) {
+            Some(values) => values,
+            None => return false,
+        };
+        let position = match values.iter().position(|v| v == value) {
+            Some(position) => position,
+            None => return false,
+        };
+
+        values.remove(position);
+        if values.is_empty() {
+            self.trie.remove_if(key, |_| true);
+        }
+        true
+    }
+
+    /// Total number of values (not keys) under keys starting with `prefix`.
+    pub fn count_with_prefix(&self, prefix: &str) -> usize {
+        self.trie
+            .iter_prefix(prefix)
+            .map(|(_, values)| values.len())
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_and_get_all() {
+        let mut tags = MultiTrie::new();
+        tags.insert("rust", 1);
+        tags.insert("rust", 2);
+        tags.insert("rust", 1);
+        tags.insert("ruby", 3);
+        tags.insert("", 4);
+        assert_eq!(tags.get_all("rust"), &[1, 2, 1]);
+        assert_eq!(tags.get_all("ruby"), &[3]);
+        assert_eq!(tags.get_all("ru"), &[] as &[i32]);
+        assert_eq!(tags.get_all(""), &[4]);
+    }
+
+    #[test]
+    fn remove_one() {
+        let mut tags = MultiTrie::new();
+        tags.insert("rust", 1);
+        tags.insert("rust", 2);
+        tags.insert("rust", 1);
+        tags.insert("rustc", 5);
+
+        assert!(tags.remove_one("rust", &1));
+        assert_eq!(tags.get_all("rust"), &[2, 1]);
+        assert!(!tags.remove_one("rust", &3));
+        assert!(!tags.remove_one("go", &1));
+        assert!(tags.remove_one("rust", &2));
+        assert!(tags.remove_one("rust", &1));
+        assert!(tags.get_all("rust").is_empty());
+        assert!(!tags.trie.contains_key("rust"));
+        assert_eq!(tags.get_all("rustc"), &[5]);
+    }
+
+    #[test]
+    fn count_with_prefix() {
+        let mut tags = MultiTrie::new();
+        for (key, value) in [("car", 1), ("car", 2), ("cart", 3), ("cat", 4), ("dog", 5)] {
+            tags.insert(key, value);
+        }
+        assert_eq!(tags.count_with_prefix("car"), 3);
+        assert_eq!(tags.count_with_prefix("ca"), 4);
+        assert_eq!(tags.count_with_prefix(""), 5);
+        assert_eq!(tags.count_with_prefix("x"), 0);
+    }
+}
@@ -0,0 +1,960 @@
+use std::borrow::Borrow;
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hash};
+
+// Nodes with at most this many children keep them in a sorted Vec; one more
+// promotes the node to a HashMap
+const SMALL_NODE_MAX_CHILDREN: usize = 16;
+
+// A HashMap node shrinking to this many children is demoted back to a Vec.
+// The gap to SMALL_NODE_MAX_CHILDREN stops a node on the boundary from
+// converting back and forth on every insert/remove.
+const LARGE_NODE_MIN_CHILDREN: usize = SMALL_NODE_MAX_CHILDREN / 2;
+
+// Bytes a child's entry in its parent's small-node Vec takes beyond the
+// node itself: the key char next to it, padded to the node's 8-byte
+// alignment. memory_estimate charges every node this flat amount where a
+// layout with a HashMap per node would charge an empty HashMap's base cost;
+// here a childless node's empty Vec allocates nothing, so the key slot is
+// the only per-node container cost. Spare capacity and the tables of wide
+// nodes are left to memory_usage, which measures the actual containers
+// instead of assuming tight ones.
+pub(crate) const CHILD_SLOT_OVERHEAD: usize = 8;
+
+// A wide node's children, by key symbol
+type ChildMap<K, T, S, P> = HashMap<K, GenericNode<K, T, S, P>, S>;
+
+// Child storage of a GenericNode. Most nodes have only a handful of children,
+// for which a sorted Vec with binary search is smaller and faster than a
+// HashMap; wide nodes switch to a (boxed, to keep the node small) HashMap.
+#[derive(Debug)]
+enum Children<K, T, S = RandomState, P = f64> {
+    Small(Vec<(K, GenericNode<K, T, S, P>)>),
+    #[allow(clippy::box_collection)]
+    Large(Box<ChildMap<K, T, S, P>>),
+}
+
+impl<K, T, S, P> Children<K, T, S, P> {
+    fn new() -> Children<K, T, S, P> {
+        Children::Small(Vec::new())
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Children::Small(children) => children.len(),
+            Children::Large(children) => children.len(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Iterate over (key, child) pairs. Small nodes yield them in key order,
+    // large nodes in arbitrary order.
+    fn iter(&self) -> ChildrenIter<'_, K, T, S, P> {
+        let inner = match self {
+            Children::Small(children) => ChildrenIterInner::Small(children.iter()),
+            Children::Large(children) => ChildrenIterInner::Large(children.iter()),
+        };
+        ChildrenIter { inner }
+    }
+
+    // Like iter, but lending the keys instead of cloning them
+    fn entries(&self) -> impl Iterator<Item = (&K, &GenericNode<K, T, S, P>)> {
+        let (small, large) = match self {
+            Children::Small(children) => (Some(children.iter().map(|(k, child)| (k, child))), None),
+            Children::Large(children) => (None, Some(children.iter())),
+        };
+        small
+            .into_iter()
+            .flatten()
+            .chain(large.into_iter().flatten())
+    }
+}
+
+impl<K: Ord + Hash, T, S: BuildHasher + Default, P> Children<K, T, S, P> {
+    fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    fn get<Q>(&self, key: &Q) -> Option<&GenericNode<K, T, S, P>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + Hash + ?Sized,
+    {
+        match self {
+            Children::Small(children) => children
+                .binary_search_by(|(k, _)| k.borrow().cmp(key))
+                .ok()
+                .map(|i| &children[i].1),
+            Children::Large(children) => children.get(key),
+        }
+    }
+
+    fn get_mut(&mut self, key: &K) -> Option<&mut GenericNode<K, T, S, P>> {
+        match self {
+            Children::Small(children) => match children.binary_search_by(|(k, _)| k.cmp(key)) {
+                Ok(i) => Some(&mut children[i].1),
+                Err(_) => None,
+            },
+            Children::Large(children) => children.get_mut(key),
+        }
+    }
+
+    // Insert a child for a key that has none yet and return it, promoting
+    // the node to a HashMap when it outgrows the Vec
+    fn insert(&mut self, key: K, child: GenericNode<K, T, S, P>) -> &mut GenericNode<K, T, S, P> {
+        if let Children::Small(children) = self {
+            if children.len() == SMALL_NODE_MAX_CHILDREN {
+                let children = std::mem::take(children);
+                *self = Children::Large(Box::new(children.into_iter().collect()));
+            }
+        }
+
+        match self {
+            Children::Small(children) => {
+                let i = children
+                    .binary_search_by(|(k, _)| k.cmp(&key))
+                    .expect_err("key already has a child");
+                children.insert(i, (key, child));
+                &mut children[i].1
+            }
+            Children::Large(children) => children.entry(key).or_insert(child),
+        }
+    }
+
+    fn remove(&mut self, key: &K) -> Option<GenericNode<K, T, S, P>> {
+        let removed = match self {
+            Children::Small(children) => match children.binary_search_by(|(k, _)| k.cmp(key)) {
+                Ok(i) => Some(children.remove(i).1),
+                Err(_) => None,
+            },
+            Children::Large(children) => children.remove(key),
+        };
+
+        if let Children::Large(children) = self {
+            if children.len() <= LARGE_NODE_MIN_CHILDREN {
+                let mut children: Vec<(K, GenericNode<K, T, S, P>)> =
+                    std::mem::take(children.as_mut()).into_iter().collect();
+                children.sort_by(|(a, _), (b, _)| a.cmp(b));
+                *self = Children::Small(children);
+            }
+        }
+        removed
+    }
+
+    // Drop spare capacity, e.g. after removals
+    fn shrink_to_fit(&mut self) {
+        match self {
+            Children::Small(children) => children.shrink_to_fit(),
+            Children::Large(children) => children.shrink_to_fit(),
+        }
+    }
+
+    // Approximate heap bytes owned by the child container itself, not
+    // counting the children's own allocations
+    fn heap_size(&self) -> usize {
+        let entry_size = std::mem::size_of::<(K, GenericNode<K, T, S, P>)>();
+        match self {
+            Children::Small(children) => children.capacity() * entry_size,
+            Children::Large(children) => {
+                // hashbrown keeps capacity at 7/8 of a power-of-two bucket
+                // count, plus one control byte per bucket and a trailing group
+                let capacity = children.capacity();
+                let buckets = if capacity == 0 {
+                    0
+                } else if capacity < 8 {
+                    capacity + 1
+                } else {
+                    (capacity * 8 / 7).next_power_of_two()
+                };
+                let table = if buckets == 0 {
+                    0
+                } else {
+                    buckets * (entry_size + 1) + 16
+                };
+                std::mem::size_of::<HashMap<K, GenericNode<K, T, S, P>, S>>() + table
+            }
+        }
+    }
+}
+
+impl<K, T, S, P> Default for Children<K, T, S, P> {
+    fn default() -> Children<K, T, S, P> {
+        Children::new()
+    }
+}
+
+impl<K, T, S, P> PartialEq for Children<K, T, S, P>
+where
+    K: Ord + Hash,
+    T: PartialEq,
+    S: BuildHasher + Default,
+    P: PartialOrd + Clone,
+{
+    // Equal when they hold the same children, regardless of representation
+    fn eq(&self, other: &Children<K, T, S, P>) -> bool {
+        self.len() == other.len()
+            && self
+                .entries()
+                .all(|(key, child)| other.get(key) == Some(child))
+    }
+}
+
+/// Iterator over a [`GenericNode`]'s children, from [`GenericNode::children`].
+pub struct ChildrenIter<'a, K, T, S = RandomState, P = f64> {
+    inner: ChildrenIterInner<'a, K, T, S, P>,
+}
+
+enum ChildrenIterInner<'a, K, T, S, P> {
+    Small(std::slice::Iter<'a, (K, GenericNode<K, T, S, P>)>),
+    Large(std::collections::hash_map::Iter<'a, K, GenericNode<K, T, S, P>>),
+}
+
+impl<'a, K: Clone, T, S, P> Iterator for ChildrenIter<'a, K, T, S, P> {
+    type Item = (K, &'a GenericNode<K, T, S, P>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.inner {
+            ChildrenIterInner::Small(iter) => iter.next().map(|(k, child)| (k.clone(), child)),
+            ChildrenIterInner::Large(iter) => iter.next().map(|(k, child)| (k.clone(), child)),
+        }
+    }
+}
+
+// Why GenericNode::insert_child refused a child
+#[derive(Debug, PartialEq)]
+pub(crate) enum InsertChildError {
+    // The node already has a child for this key
+    ChildExists,
+    // The child is keyed on a different symbol than the one it was to be
+    // attached under
+    KeyMismatch,
+}
+
+// Priorities of a node whose subtree holds a value inserted with one
+#[derive(Debug, Clone)]
+struct Rank<P> {
+    // The priority of the node's own value, if it was inserted with one
+    own: Option<P>,
+    // Highest priority in the subtree, `own` included
+    max: P,
+}
+
+// The higher of two priorities, the first on a tie or if they're
+// incomparable
+fn higher<'p, P: PartialOrd>(a: Option<&'p P>, b: Option<&'p P>) -> Option<&'p P> {
+    match (a, b) {
+        (Some(a), Some(b)) if b > a => Some(b),
+        (a, b) => a.or(b),
+    }
+}
+
+/// A single node of a [`GenericTrie`](crate::GenericTrie): the key symbol on
+/// the edge leading to it, an optional value, and its children. A value
+/// may carry a priority of type `P`, and every node knows the highest
+/// priority in its subtree.
+///
+/// Nodes can be read freely, e.g. to write custom traversals. Structural
+/// mutation stays inside the crate so a trie's invariants can't be broken
+/// from outside.
+#[derive(Debug)]
+pub struct GenericNode<K, T, S = RandomState, P = f64> {
+    key: K,
+    value: Option<T>,
+    children: Children<K, T, S, P>,
+    // Number of values in the subtree, the node's own included. The
+    // primitives below keep it right for the node they change; changes
+    // made further down have to be carried up, see update_at.
+    value_count: usize,
+    // Set exactly for the nodes whose subtree holds a priority, and kept
+    // right the same way as value_count. Boxed, so tries without
+    // priorities pay only for the pointer.
+    rank: Option<Box<Rank<P>>>,
+}
+
+/// A node of a [`Trie`](crate::Trie), keyed on chars.
+pub type TrieNode<T, S = RandomState> = GenericNode<char, T, S>;
+
+impl<K, T, S, P> GenericNode<K, T, S, P> {
+    /// Creates a childless node for `key`, optionally holding a value.
+    pub fn new(key: K, value: Option<T>) -> GenericNode<K, T, S, P> {
+        GenericNode {
+            value_count: usize::from(value.is_some()),
+            value,
+            key,
+            children: Children::new(),
+            rank: None,
+        }
+    }
+
+    /// The key symbol on the edge from the parent to this node. A root's is
+    /// a placeholder (`K::default()`) that is not part of any key.
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// Returns true if the node has at least one child.
+    pub fn has_children(&self) -> bool {
+        !self.children.is_empty()
+    }
+
+    /// Number of children.
+    pub fn child_count(&self) -> usize {
+        self.children.len()
+    }
+
+    /// Iterates over `(key symbol, child)` pairs. The order is unspecified;
+    /// use [`GenericNode::sorted_children`] when it matters.
+    pub fn children(&self) -> ChildrenIter<'_, K, T, S, P> {
+        self.children.iter()
+    }
+
+    /// The value stored at this node, if any.
+    pub fn value(&self) -> Option<&T> {
+        self.value.as_ref()
+    }
+
+    pub(crate) fn value_mut(&mut self) -> Option<&mut T> {
+        self.value.as_mut()
+    }
+
+    pub(crate) fn set_value(&mut self, value: T) {
+        if self.value.replace(value).is_none() {
+            self.value_count += 1;
+        }
+    }
+
+    /// The priority this node's value was inserted with, if any.
+    pub fn priority(&self) -> Option<&P> {
+        self.rank.as_ref()?.own.as_ref()
+    }
+
+    /// The highest priority in this node's subtree, including its own. Every
+    /// node keeps track of it, so it is O(1).
+    pub fn max_priority(&self) -> Option<&P> {
+        Some(&self.rank.as_ref()?.max)
+    }
+
+    // Approximate heap bytes of the node's priorities, if it has any
+    pub(crate) fn rank_heap_size(&self) -> usize {
+        self.rank
+            .as_ref()
+            .map_or(0, |_| std::mem::size_of::<Rank<P>>())
+    }
+
+    // The children without their keys, in no particular order. Unlike
+    // children() this never clones a key.
+    pub(crate) fn child_nodes(&self) -> impl Iterator<Item = &GenericNode<K, T, S, P>> {
+        self.children.entries().map(|(_, child)| child)
+    }
+
+    // Sets the maximum priority worked out for the node by its caller
+    fn set_max_priority(&mut self, max: Option<P>) {
+        match (&mut self.rank, max) {
+            (Some(rank), Some(max)) => rank.max = max,
+            // A node without a maximum has no priority of its own either
+            (rank, max) => *rank = max.map(|max| Box::new(Rank { own: None, max })),
+        }
+    }
+
+    /// Counts the nodes in this node's subtree, including itself.
+    pub fn count_nodes(&self) -> usize {
+        let mut count = 0;
+        let mut stack = vec![self];
+        while let Some(node) = stack.pop() {
+            count += 1;
+            stack.extend(node.child_nodes());
+        }
+        count
+    }
+
+    // Whether this node's subtree, itself included, holds a value. Not so
+    // for the branches left by Trie::remove_lazy.
+    pub(crate) fn leads_to_value(&self) -> bool {
+        self.value_count > 0
+    }
+
+    /// Number of values stored in this node's subtree, including its own.
+    /// Every node keeps this count, so it is O(1).
+    pub fn count_values(&self) -> usize {
+        self.value_count
+    }
+
+    // count_values, but counted by visiting the whole subtree. Debug builds
+    // also check every node's kept count against it on the way.
+    pub(crate) fn count_values_by_traversal(&self) -> usize {
+        // Nodes in breadth-first order with their parent's index, so that
+        // walking them backwards each subtree is counted before its parent
+        let mut nodes = vec![(self, 0)];
+        let mut i = 0;
+        while i < nodes.len() {
+            let node = nodes[i].0;
+            nodes.extend(node.child_nodes().map(|child| (child, i)));
+            i += 1;
+        }
+        let mut counts: Vec<usize> = nodes
+            .iter()
+            .map(|(node, _)| usize::from(node.value.is_some()))
+            .collect();
+        for i in (0..nodes.len()).rev() {
+            debug_assert_eq!(nodes[i].0.value_count, counts[i], "stale value count");
+            if i > 0 {
+                counts[nodes[i].1] += counts[i];
+            }
+        }
+        counts[0]
+    }
+}
+
+impl<K, T, S, P: PartialOrd + Clone> GenericNode<K, T, S, P> {
+    // Sets the priority of the node's value, which must be there, and works
+    // out the maximum anew from it and the children's
+    pub(crate) fn set_priority(&mut self, priority: Option<P>) {
+        let max = self
+            .child_nodes()
+            .map(GenericNode::max_priority)
+            .fold(priority.as_ref(), higher)
+            .cloned();
+        self.rank = max.map(|max| Box::new(Rank { own: priority, max }));
+    }
+
+    // Works out the maximum priority anew, after a child's changed
+    fn refresh_max_priority(&mut self) {
+        let own = self.rank.take().and_then(|rank| rank.own);
+        self.set_priority(own);
+    }
+
+    pub(crate) fn take_value(&mut self) -> Option<T> {
+        self.take_entry().map(|(value, _)| value)
+    }
+
+    // Takes the value out along with its priority, which only goes with it
+    pub(crate) fn take_entry(&mut self) -> Option<(T, Option<P>)> {
+        let value = self.value.take()?;
+        self.value_count -= 1;
+        let priority = self.rank.as_mut().and_then(|rank| rank.own.take());
+        if priority.is_some() {
+            self.refresh_max_priority();
+        }
+        Some((value, priority))
+    }
+
+    // Carries into the value count and maximum priority a change below a
+    // child reached through child_mut or split_mut: `added` values gained
+    // and `removed` lost
+    pub(crate) fn descendants_changed(&mut self, added: usize, removed: usize) {
+        self.value_count = self.value_count + added - removed;
+        self.refresh_max_priority();
+    }
+}
+
+impl<K: Ord + Hash, T, S: BuildHasher + Default, P: PartialOrd + Clone> GenericNode<K, T, S, P> {
+    /// Returns true if the node has a child for `key`.
+    pub fn has_child(&self, key: impl Borrow<K>) -> bool {
+        self.children.contains_key(key.borrow())
+    }
+
+    /// Attaches `child` under `key` and returns it. Fails if a child for
+    /// `key` already exists or `child` is keyed on another symbol.
+    pub(crate) fn insert_child(
+        &mut self,
+        key: K,
+        child: GenericNode<K, T, S, P>,
+    ) -> Result<&mut GenericNode<K, T, S, P>, InsertChildError> {
+        if key != child.key {
+            return Err(InsertChildError::KeyMismatch);
+        }
+        if self.children.contains_key(&key) {
+            return Err(InsertChildError::ChildExists);
+        }
+
+        self.value_count += child.value_count;
+        if let Some(max) = child.max_priority() {
+            if self.max_priority().is_none_or(|own_max| max > own_max) {
+                self.set_max_priority(Some(max.clone()));
+            }
+        }
+        Ok(self.children.insert(key, child))
+    }
+
+    // Follows `key` down from this node and hands the node it reaches to
+    // `f`, creating the missing ones on the way if `insert` is set; if not,
+    // a missing node means None. Whatever `f` changes in the reached node's
+    // value count and maximum priority is carried into the nodes above it,
+    // so `f` may change the subtree through the other primitives freely.
+    pub(crate) fn update_at<I, R, F>(&mut self, key: I, insert: bool, f: F) -> Option<R>
+    where
+        I: IntoIterator<Item = K>,
+        K: Clone,
+        F: FnOnce(&mut GenericNode<K, T, S, P>) -> R,
+    {
+        // The nodes above can't stay borrowed on the way down, so the path
+        // is walked again afterwards, if anything they track changed at all
+        let mut path = Vec::new();
+        let mut current_node = &mut *self;
+        for k in key {
+            if insert && !current_node.has_child(&k) {
+                current_node = current_node
+                    .insert_child(k.clone(), GenericNode::new(k.clone(), None))
+                    .unwrap();
+            } else {
+                current_node = current_node.child_mut(&k)?;
+            }
+            path.push(k);
+        }
+        let before = current_node.value_count;
+        let max_before = current_node.max_priority().cloned();
+        let result = f(current_node);
+        let after = current_node.value_count;
+        let max_after = current_node.max_priority().cloned();
+
+        let mut maxima = if max_after != max_before {
+            let raised = match (&max_before, &max_after) {
+                (_, None) => false,
+                (None, Some(_)) => true,
+                (Some(before), Some(after)) => after > before,
+            };
+            Some(self.path_maxima(&path, max_after, raised).into_iter())
+        } else {
+            None
+        };
+        if after != before || maxima.is_some() {
+            let mut current_node = self;
+            for k in path {
+                current_node.value_count = current_node.value_count - before + after;
+                if let Some(maxima) = &mut maxima {
+                    current_node.set_max_priority(maxima.next().unwrap());
+                }
+                current_node = current_node.child_mut(k).unwrap();
+            }
+        }
+        Some(result)
+    }
+
+    // The maximum priorities of the nodes along `path`, root first, for the
+    // node at its end now having `max`. Each is the higher of the next one
+    // down and the node's own and other children's priorities; if `max`
+    // was `raised`, the node's current maximum stands in for those, saving
+    // the look at its children.
+    fn path_maxima(&self, path: &[K], max: Option<P>, raised: bool) -> Vec<Option<P>> {
+        let mut others = Vec::with_capacity(path.len());
+        let mut current_node = self;
+        for k in path {
+            let other = if raised {
+                current_node.max_priority()
+            } else {
+                current_node
+                    .child_nodes()
+                    .filter(|child| child.key != *k)
+                    .map(GenericNode::max_priority)
+                    .fold(current_node.priority(), higher)
+            };
+            others.push(other);
+            current_node = current_node.child(k).unwrap();
+        }
+
+        let mut max = max;
+        let mut maxima: Vec<Option<P>> = others
+            .into_iter()
+            .rev()
+            .map(|other| {
+                if let Some(other) = other {
+                    if max.as_ref().is_none_or(|max| other > max) {
+                        max = Some(other.clone());
+                    }
+                }
+                max.clone()
+            })
+            .collect();
+        maxima.reverse();
+        maxima
+    }
+
+    // Node reached by following `key` down from this one, creating the
+    // missing ones on the way. Values set below it aren't counted by the
+    // nodes above; see update_at.
+    #[cfg(feature = "rayon")]
+    pub(crate) fn descendant_or_insert<I>(&mut self, key: I) -> &mut GenericNode<K, T, S, P>
+    where
+        I: IntoIterator<Item = K>,
+        K: Clone,
+    {
+        let mut current_node = self;
+        for k in key {
+            if !current_node.has_child(&k) {
+                current_node = current_node
+                    .insert_child(k.clone(), GenericNode::new(k, None))
+                    .unwrap();
+            } else {
+                current_node = current_node.child_mut(k).unwrap();
+            }
+        }
+        current_node
+    }
+
+    /// The child for `key`, if any.
+    pub fn child(&self, key: impl Borrow<K>) -> Option<&GenericNode<K, T, S, P>> {
+        self.children.get(key.borrow())
+    }
+
+    /// Like [`GenericNode::child`], but looking the child up by a borrowed
+    /// form of the key, e.g. a `&str` for `String` keys, without building
+    /// a `K`.
+    pub fn child_by<Q>(&self, key: &Q) -> Option<&GenericNode<K, T, S, P>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + Hash + ?Sized,
+    {
+        self.children.get(key)
+    }
+
+    pub(crate) fn child_mut(
+        &mut self,
+        key: impl Borrow<K>,
+    ) -> Option<&mut GenericNode<K, T, S, P>> {
+        self.children.get_mut(key.borrow())
+    }
+
+    // Approximate heap bytes of the child container, spare capacity
+    // included; the children sit inline in it
+    pub(crate) fn children_heap_size(&self) -> usize {
+        self.children.heap_size()
+    }
+
+    pub(crate) fn shrink_children(&mut self) {
+        self.children.shrink_to_fit();
+    }
+
+    /// Detaches and returns the child for `key`, with its whole subtree.
+    pub(crate) fn remove_child(&mut self, key: impl Borrow<K>) -> Option<GenericNode<K, T, S, P>> {
+        let child = self.children.remove(key.borrow())?;
+        self.value_count -= child.value_count;
+        if child.rank.is_some() {
+            self.refresh_max_priority();
+        }
+        Some(child)
+    }
+
+    // Copy of this subtree with the same keys and child layout, holding
+    // `f`'s image of every value
+    pub(crate) fn clone_with_map<U, F: FnMut(&T) -> U>(&self, f: &mut F) -> GenericNode<K, U, S, P>
+    where
+        K: Clone,
+    {
+        let mut node = GenericNode::new(self.key.clone(), self.value.as_ref().map(&mut *f));
+        node.children = match &self.children {
+            Children::Small(children) => Children::Small(
+                children
+                    .iter()
+                    .map(|(k, child)| (k.clone(), child.clone_with_map(f)))
+                    .collect(),
+            ),
+            Children::Large(children) => Children::Large(Box::new(
+                children
+                    .iter()
+                    .map(|(k, child)| (k.clone(), child.clone_with_map(f)))
+                    .collect(),
+            )),
+        };
+        node.value_count = self.value_count;
+        node.rank = self.rank.clone();
+        node
+    }
+
+    /// The children ordered by key symbol.
+    pub fn sorted_children(&self) -> Vec<&GenericNode<K, T, S, P>> {
+        let mut children: Vec<&GenericNode<K, T, S, P>> =
+            self.children.entries().map(|(_, child)| child).collect();
+        if let Children::Large(_) = self.children {
+            children.sort_by(|a, b| a.key.cmp(&b.key));
+        }
+        children
+    }
+
+    // Takes the node apart into its value, the value's priority and its
+    // children, in key order
+    pub(crate) fn into_parts(mut self) -> (Option<T>, Option<P>, Vec<Self>) {
+        let children = match std::mem::take(&mut self.children) {
+            Children::Small(children) => children.into_iter().map(|(_, child)| child).collect(),
+            Children::Large(children) => {
+                let mut children: Vec<GenericNode<K, T, S, P>> = children.into_values().collect();
+                children.sort_by(|a, b| a.key.cmp(&b.key));
+                children
+            }
+        };
+        let priority = self.rank.take().and_then(|rank| rank.own);
+        (self.value.take(), priority, children)
+    }
+
+    // The children in key order, like sorted_children, but only allocating
+    // for large nodes: a small node's children are sorted already
+    pub(crate) fn children_in_order(
+        &self,
+    ) -> impl DoubleEndedIterator<Item = &GenericNode<K, T, S, P>> {
+        let (small, large) = match &self.children {
+            Children::Small(children) => (Some(children.iter().map(|(_, child)| child)), None),
+            Children::Large(_) => (None, Some(self.sorted_children())),
+        };
+        small
+            .into_iter()
+            .flatten()
+            .chain(large.into_iter().flatten())
+    }
+
+    // The value and the children in key order, borrowed mutably together so
+    // a walk can hand out the value while still descending. Setting or
+    // taking the value, or changing the children's counts, leaves this
+    // node's count and priorities stale: fix them with descendants_changed,
+    // or only do so on a trie about to be dropped.
+    pub(crate) fn split_mut(&mut self) -> (&mut Option<T>, Vec<&mut Self>) {
+        let children = match &mut self.children {
+            Children::Small(children) => children.iter_mut().map(|(_, child)| child).collect(),
+            Children::Large(children) => {
+                let mut children: Vec<&mut GenericNode<K, T, S, P>> =
+                    children.values_mut().collect();
+                children.sort_by(|a, b| a.key.cmp(&b.key));
+                children
+            }
+        };
+        (&mut self.value, children)
+    }
+}
+
+impl<T, S: BuildHasher + Default, P: PartialOrd + Clone> GenericNode<char, T, S, P> {
+    /// The char on the edge from the parent to this node (`'\0'` for a root).
+    pub fn key_char(&self) -> char {
+        self.key
+    }
+
+    /// Iterates over the chars this node has children for, in no particular
+    /// order. Together with [`GenericNode::child`] this is enough to walk
+    /// the trie without depending on how children are stored.
+    pub fn child_chars(&self) -> impl Iterator<Item = char> + '_ {
+        self.children.entries().map(|(key_char, _)| *key_char)
+    }
+
+    /// Old name of [`TrieNode::key_char`].
+    #[deprecated(since = "0.2.0", note = "renamed to `key_char`")]
+    pub fn get_key_char(&self) -> char {
+        self.key_char()
+    }
+
+    /// Old name of [`GenericNode::child`].
+    #[deprecated(since = "0.2.0", note = "renamed to `child`")]
+    pub fn get_child(&self, key_char: char) -> Option<&GenericNode<char, T, S, P>> {
+        self.child(key_char)
+    }
+}
+
+impl<K, T, S, P> PartialEq for GenericNode<K, T, S, P>
+where
+    K: Ord + Hash,
+    T: PartialEq,
+    S: BuildHasher + Default,
+    P: PartialOrd + Clone,
+{
+    fn eq(&self, other: &GenericNode<K, T, S, P>) -> bool {
+        self.key == other.key
+            && self.value == other.value
+            && self.priority() == other.priority()
+            && self.children == other.children
+    }
+}
+
+impl<K, T, S, P> Drop for GenericNode<K, T, S, P> {
+    // The derived drop glue recurses once per level, which overflows the
+    // stack on very long keys. Instead, detach each node's children onto a
+    // work list before it drops, so every node drops without descendants.
+    fn drop(&mut self) {
+        let mut pending = vec![std::mem::take(&mut self.children)];
+        while let Some(children) = pending.pop() {
+            match children {
+                Children::Small(children) => {
+                    for (_, mut child) in children {
+                        pending.push(std::mem::take(&mut child.children));
+                    }
+                }
+                Children::Large(children) => {
+                    for (_, mut child) in *children {
+                        pending.push(std::mem::take(&mut child.children));
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::XorShift64;
+    use crate::Trie;
+
+    #[test]
+    fn insert_child() {
+        let mut root = TrieNode::<u32>::new('a', None);
+        let res = root.insert_child('b', TrieNode::new('b', None));
+        assert_eq!(res.unwrap().key_char(), 'b');
+
+        let res = root.insert_child('b', TrieNode::new('b', None));
+        assert_eq!(res, Err(InsertChildError::ChildExists));
+
+        let res = root.insert_child('d', TrieNode::new('b', None));
+        assert_eq!(res, Err(InsertChildError::KeyMismatch));
+        assert!(!root.has_child('d'));
+
+        let res = root.insert_child('c', TrieNode::new('c', None));
+        assert_eq!(res.unwrap().key_char(), 'c');
+    }
+
+    #[test]
+    fn remove_child() {
+        let mut root = TrieNode::<u32>::new('a', None);
+        root.insert_child('b', TrieNode::new('b', None)).unwrap();
+        root.insert_child('c', TrieNode::new('c', None)).unwrap();
+
+        root.remove_child('b');
+        assert!(!root.has_child('b'));
+        assert!(root.has_children());
+        assert_eq!(root.child_mut('b'), None);
+
+        root.remove_child('c');
+        assert!(!root.has_child('c'));
+        assert!(!root.has_children());
+        assert_eq!(root.child_mut('c'), None);
+    }
+
+    #[test]
+    fn hybrid_children_memory() {
+        assert!(
+            std::mem::size_of::<Children<char, u32>>()
+                < std::mem::size_of::<HashMap<char, TrieNode<u32>>>()
+        );
+        let mut small = Children::<char, u32>::new();
+        let mut map = HashMap::new();
+        for c in ['x', 'y', 'z'] {
+            small.insert(c, TrieNode::new(c, None));
+            map.insert(c, TrieNode::new(c, None));
+        }
+        let large = Children::Large(Box::new(map));
+        assert!(small.heap_size() < large.heap_size());
+        assert!(small == large);
+    }
+
+    #[test]
+    fn hybrid_children_promote_and_demote() {
+        let mut root = TrieNode::<u32>::new('\0', None);
+        for c in ('a'..='z').rev() {
+            root.insert_child(c, TrieNode::new(c, None)).unwrap();
+        }
+        assert!(matches!(root.children, Children::Large(_)));
+        assert_eq!(
+            root.sorted_children()
+                .iter()
+                .map(|child| child.key_char())
+                .collect::<String>(),
+            "abcdefghijklmnopqrstuvwxyz"
+        );
+        for c in 'a'..='r' {
+            assert!(root.remove_child(c).is_some());
+        }
+        assert!(matches!(root.children, Children::Small(_)));
+        assert!(root.has_child('s'));
+        assert!(!root.has_child('r'));
+        assert_eq!(root.child('z').unwrap().key_char(), 'z');
+    }
+
+    #[test]
+    fn hybrid_children_differential() {
+        let alphabet: Vec<char> = ('a'..='z').chain('A'..='N').collect();
+        let mut rng = XorShift64(0x2545_f491_4f6c_dd1d);
+        let mut trie = Trie::<u64>::new();
+        let mut model = std::collections::BTreeMap::<String, u64>::new();
+        for round in 0..20000 {
+            let len = 1 + rng.below(3);
+            let key: String = (0..len)
+                .map(|_| alphabet[rng.below(alphabet.len())])
+                .collect();
+            let insert_weight = if round < 10000 { 6 } else { 1 };
+            let op = rng.below(insert_weight + 4);
+            if op < insert_weight {
+                let value = rng.next_u64();
+                let expected = !model.contains_key(&key);
+                assert_eq!(trie.insert(&key, value), expected);
+                model.entry(key).or_insert(value);
+            } else if op < insert_weight + 2 {
+                assert_eq!(trie.get(&key), model.get(&key));
+            } else {
+                let has_extension = model
+                    .keys()
+                    .any(|other| other.len() > key.len() && other.starts_with(&key));
+                let expected = model.contains_key(&key) && !has_extension;
+                assert_eq!(trie.remove(&key), expected);
+                if expected {
+                    model.remove(&key);
+                }
+            }
+        }
+
+        // Every node other than the root is on the path to a stored key
+        let mut entries = Vec::new();
+        let mut stack = vec![(String::new(), trie.root())];
+        while let Some((path, node)) = stack.pop() {
+            match &node.children {
+                Children::Small(children) => assert!(children.len() <= SMALL_NODE_MAX_CHILDREN),
+                Children::Large(children) => assert!(children.len() > LARGE_NODE_MIN_CHILDREN),
+            }
+            if let Some(value) = node.value() {
+                entries.push((path.clone(), *value));
+            } else if !path.is_empty() {
+                assert!(node.has_children());
+            }
+            for child in node.sorted_children() {
+                let mut child_path = path.clone();
+                child_path.push(child.key_char());
+                stack.push((child_path, child));
+            }
+        }
+        entries.sort();
+        assert_eq!(entries, model.into_iter().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn read_only_traversal() {
+        let mut trie = Trie::<u32>::new();
+        trie.insert("ab", 1);
+        trie.insert("ac", 2);
+        let node = trie.root().child('a').unwrap();
+        assert_eq!(node.value(), None);
+        let mut children: Vec<(char, Option<&u32>)> = node
+            .children()
+            .map(|(key_char, child)| (key_char, child.value()))
+            .collect();
+        children.sort();
+        assert_eq!(children, vec![('b', Some(&1)), ('c', Some(&2))]);
+        assert_eq!(node.count_nodes(), 3);
+        assert_eq!(node.count_values(), 2);
+    }
+
+    #[test]
+    fn child_chars() {
+        let mut trie = Trie::<u32>::new();
+        assert_eq!(trie.root().child_chars().next(), None);
+        // Enough children to move the node to its map representation
+        for (i, c) in ('a'..='z').rev().enumerate() {
+            trie.insert(&format!("x{}", c), i as u32);
+        }
+        assert_eq!(trie.root().child_chars().collect::<Vec<_>>(), ['x']);
+
+        let node = trie.root().child('x').unwrap();
+        let mut chars: Vec<char> = node.child_chars().collect();
+        chars.sort();
+        assert_eq!(chars, ('a'..='z').collect::<Vec<_>>());
+        for c in node.child_chars() {
+            assert_eq!(node.child(c).unwrap().key_char(), c);
+        }
+    }
+}
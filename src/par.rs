@@ -0,0 +1,239 @@
+use std::collections::BTreeMap;
+use std::hash::BuildHasher;
+
+use rayon::iter::{FromParallelIterator, IntoParallelIterator, ParallelExtend, ParallelIterator};
+
+use crate::node::TrieNode;
+use crate::trie::Trie;
+
+impl<T: Send> Trie<T> {
+    /// Builds a trie from key-value pairs on rayon's thread pool; see
+    /// [`Trie::par_extend`](ParallelExtend::par_extend) for how the work is
+    /// split. The result is the same as [`Trie::from_iter`]'s on the same
+    /// pairs in the same order, including which value a repeated key keeps.
+    pub fn par_from_iter<K, I>(iter: I) -> Trie<T>
+    where
+        K: AsRef<str> + Send,
+        I: IntoParallelIterator<Item = (K, T)>,
+    {
+        let mut trie = Trie::new();
+        trie.par_extend(iter);
+        trie
+    }
+}
+
+impl<K, T, S> FromParallelIterator<(K, T)> for Trie<T, S>
+where
+    K: AsRef<str> + Send,
+    T: Send,
+    S: BuildHasher + Default + Send,
+{
+    fn from_par_iter<I: IntoParallelIterator<Item = (K, T)>>(iter: I) -> Trie<T, S> {
+        let mut trie = Trie::default();
+        trie.par_extend(iter);
+        trie
+    }
+}
+
+impl<K, T, S> ParallelExtend<(K, T)> for Trie<T, S>
+where
+    K: AsRef<str> + Send,
+    T: Send,
+    S: BuildHasher + Default + Send,
+{
+    /// Inserts the pairs on rayon's thread pool, with the same result as
+    /// [`Extend::extend`]: a key already stored, or repeated in the input,
+    /// keeps its first value. Values are moved, never cloned.
+    ///
+    /// The pairs are split by the subtree their keys fall in, so threads
+    /// never share a node. A subtree holding too large a share of the keys
+    /// is split again by the next char, so keys that all start alike, e.g.
+    /// paths starting with '/', still spread over the threads.
+    fn par_extend<I: IntoParallelIterator<Item = (K, T)>>(&mut self, iter: I) {
+        // In input order, which decides between repeated keys
+        let entries: Vec<(K, T)> = iter.into_par_iter().collect();
+        let grain = (entries.len() / (rayon::current_num_threads() * 8)).max(64);
+        let inserted = match self.normalizer() {
+            Some(normalizer) => {
+                let entries: Vec<(String, T)> = entries
+                    .into_par_iter()
+                    .map(|(key, value)| (normalizer(key.as_ref()), value))
+                    .collect();
+                extend_node(self.root_and_count_mut().0, entries, 0, grain)
+            }
+            None => extend_node(self.root_and_count_mut().0, entries, 0, grain),
+        };
+        *self.root_and_count_mut().1 += inserted;
+    }
+}
+
+// Inserts `entries`, whose keys all run through `start` and continue past
+// its first `offset` bytes, into the subtree under `start`. Returns the
+// number of keys newly inserted.
+fn extend_node<K, T, S>(
+    start: &mut TrieNode<T, S>,
+    entries: Vec<(K, T)>,
+    offset: usize,
+    grain: usize,
+) -> usize
+where
+    K: AsRef<str> + Send,
+    T: Send,
+    S: BuildHasher + Default + Send,
+{
+    // The chars extend_below carried on down with, each with the number of
+    // keys inserted by then. The nodes they were carried from don't count
+    // the keys inserted after, so those are added on a second walk down.
+    let mut descended = Vec::new();
+    let inserted = extend_below(&mut *start, entries, offset, grain, &mut descended);
+    let mut node = start;
+    for (c, inserted_before) in descended {
+        node.descendants_changed(inserted - inserted_before, 0);
+        node = node.child_mut(c).unwrap();
+    }
+    inserted
+}
+
+// The work of extend_node, short of updating the value counts of the nodes
+// it passes on the way down, which are recorded in `descended`
+fn extend_below<K, T, S>(
+    mut node: &mut TrieNode<T, S>,
+    mut entries: Vec<(K, T)>,
+    mut offset: usize,
+    grain: usize,
+    descended: &mut Vec<(char, usize)>,
+) -> usize
+where
+    K: AsRef<str> + Send,
+    T: Send,
+    S: BuildHasher + Default + Send,
+{
+    let mut inserted = 0;
+    loop {
+        if entries.len() <= grain {
+            for (key, value) in entries {
+                inserted += usize::from(insert_below(node, &key.as_ref()[offset..], value));
+            }
+            return inserted;
+        }
+
+        // Split by the next char, keeping the input order within each share
+        let mut shares: BTreeMap<char, Vec<(K, T)>> = BTreeMap::new();
+        for (key, value) in entries {
+            match key.as_ref()[offset..].chars().next() {
+                Some(c) => shares.entry(c).or_default().push((key, value)),
+                None => inserted += usize::from(insert_below(node, "", value)),
+            }
+        }
+
+        // While only one share is too large to insert on its own, handle
+        // the rest here and carry on down with it, without recursing
+        let mut large = shares.iter().filter(|(_, share)| share.len() > grain);
+        if let (Some((&c, _)), None) = (large.next(), large.next()) {
+            entries = shares.remove(&c).unwrap();
+            for (_, share) in shares {
+                for (key, value) in share {
+                    inserted += usize::from(insert_below(node, &key.as_ref()[offset..], value));
+                }
+            }
+            node = node.descendant_or_insert([c]);
+            offset += c.len_utf8();
+            descended.push((c, inserted));
+            continue;
+        }
+
+        // Otherwise each share goes to its own child, in parallel
+        for &c in shares.keys() {
+            node.descendant_or_insert([c]);
+        }
+        let (_, children) = node.split_mut();
+        let tasks: Vec<_> = children
+            .into_iter()
+            .filter_map(|child| Some((shares.remove(&child.key_char())?, child)))
+            .collect();
+        let below = tasks
+            .into_par_iter()
+            .map(|(share, child)| {
+                let offset = offset + child.key_char().len_utf8();
+                extend_node(child, share, offset, grain)
+            })
+            .sum::<usize>();
+        node.descendants_changed(below, 0);
+        return inserted + below;
+    }
+}
+
+// Inserts `value` under `rest`, relative to `node`, unless a value is
+// stored there already. Returns whether it was inserted.
+fn insert_below<T, S: BuildHasher + Default>(
+    node: &mut TrieNode<T, S>,
+    rest: &str,
+    value: T,
+) -> bool {
+    node.update_at(rest.chars(), true, |node| {
+        if node.value().is_some() {
+            return false;
+        }
+        node.set_value(value);
+        true
+    })
+    .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use rayon::iter::IntoParallelRefIterator;
+
+    use super::*;
+    use crate::test_util::XorShift64;
+
+    fn random_pairs(rng: &mut XorShift64, alphabet: &[char], prefix: &str) -> Vec<(String, usize)> {
+        // Short keys, so many repeat with different values
+        (0..20_000)
+            .map(|i| {
+                let mut key = String::from(prefix);
+                key.extend((0..rng.below(5)).map(|_| alphabet[rng.below(alphabet.len())]));
+                (key, i)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn same_as_sequential() {
+        let mut rng = XorShift64(0x9a7a_11e1_0000_0073);
+        let alphabet: Vec<char> = ('a'..='z').chain(['é', '中', '🙂']).collect();
+        // All keys sharing their first chars, as paths do
+        for prefix in ["", "/", "/usr/"] {
+            let pairs = random_pairs(&mut rng, &alphabet, prefix);
+            let sequential: Trie<usize> = pairs.iter().map(|(k, v)| (k.as_str(), *v)).collect();
+            let parallel = Trie::par_from_iter(pairs.par_iter().map(|(k, v)| (k.as_str(), *v)));
+            assert!(parallel.iter().eq(sequential.iter()));
+            assert_eq!(parallel, sequential);
+            assert_eq!(parallel.len(), sequential.len());
+            let collected: Trie<usize> = pairs.into_par_iter().collect();
+            assert!(collected.iter().eq(sequential.iter()));
+        }
+
+        let empty = Trie::<()>::par_from_iter(Vec::<(&str, ())>::new());
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn extend_existing() {
+        let mut rng = XorShift64(0x9a7a_11e1_0000_0091);
+        let alphabet = ['a', 'b', 'A', 'B'];
+        let pairs = random_pairs(&mut rng, &alphabet, "");
+        let mut sequential = Trie::new_with_normalizer(|key| key.to_lowercase());
+        let mut parallel = Trie::new_with_normalizer(|key| key.to_lowercase());
+        for key in ["ab", "BA", "", "aaaaaa"] {
+            sequential.insert(key, usize::MAX);
+            parallel.insert(key, usize::MAX);
+        }
+
+        sequential.extend(pairs.iter().map(|(k, v)| (k.as_str(), *v)));
+        parallel.par_extend(pairs);
+        assert!(parallel.iter().eq(sequential.iter()));
+        assert_eq!(parallel.get("Ab"), Some(&usize::MAX));
+        assert_eq!(parallel.len(), sequential.len());
+    }
+}
@@ -0,0 +1,184 @@
+use std::ffi::OsString;
+use std::path::{Component, Path, PathBuf};
+
+use crate::generic::GenericTrie;
+
+/// Trie keyed by filesystem paths, one level per path component, so
+/// "/usr/local" and "/usr/lib" share the "/" and "usr" nodes.
+///
+/// Paths are split with [`Path::components`], which brings its
+/// normalization: repeated separators, interior `.` components and a
+/// trailing separator are ignored, so "a//b/./c/" is the same key as
+/// "a/b/c". `..` is kept as a component of its own, not resolved. Keys
+/// don't have to be valid UTF-8, and the separators are the platform's.
+#[derive(Debug)]
+pub struct PathTrie<T> {
+    trie: GenericTrie<OsString, T>,
+}
+
+impl<T> Default for PathTrie<T> {
+    fn default() -> PathTrie<T> {
+        PathTrie::new()
+    }
+}
+
+fn components(path: &Path) -> impl Iterator<Item = OsString> + '_ {
+    path.components()
+        .map(|component| component.as_os_str().to_os_string())
+}
+
+impl<T> PathTrie<T> {
+    /// Creates an empty trie.
+    pub fn new() -> PathTrie<T> {
+        PathTrie {
+            trie: GenericTrie::new(),
+        }
+    }
+
+    /// Inserts `value` under `path`. Returns false, leaving the trie
+    /// untouched, if `path` is already stored.
+    pub fn insert(&mut self, path: &Path, value: T) -> bool {
+        self.trie.insert(components(path), value)
+    }
+
+    /// The value stored under `path`, if any.
+    pub fn get(&self, path: &Path) -> Option<&T> {
+        self.trie.get(components(path))
+    }
+
+    /// Returns true if `path` is stored in the trie.
+    pub fn contains_key(&self, path: &Path) -> bool {
+        self.get(path).is_some()
+    }
+
+    /// Removes `path`, returning whether it was removed.
+    pub fn remove(&mut self, path: &Path) -> bool {
+        self.trie.remove(components(path))
+    }
+
+    /// Number of stored paths.
+    pub fn len(&self) -> usize {
+        self.trie.len()
+    }
+
+    /// Returns true if no path is stored.
+    pub fn is_empty(&self) -> bool {
+        self.trie.is_empty()
+    }
+
+    /// Iterates over all entries, ordered component by component.
+    pub fn iter(&self) -> impl Iterator<Item = (PathBuf, &T)> + '_ {
+        self.trie
+            .iter()
+            .map(|(components, value)| (components.iter().collect(), value))
+    }
+
+    /// The stored paths at or below `prefix`, whole components only: "/usr"
+    /// matches "/usr/lib" but not "/usrlocal".
+    pub fn paths_with_prefix(&self, prefix: &Path) -> impl Iterator<Item = PathBuf> + '_ {
+        self.trie
+            .iter_prefix(components(prefix))
+            .map(|(components, _)| components.iter().collect())
+    }
+
+    /// The longest stored path that `path` is at or below, with its value,
+    /// e.g. the mount point a file lives on.
+    pub fn longest_prefix_match(&self, path: &Path) -> Option<(PathBuf, &T)> {
+        let mut current_node = self.trie.root();
+        let mut best = current_node.value().map(|value| (0, value));
+        let path_components: Vec<Component<'_>> = path.components().collect();
+        for (i, component) in path_components.iter().enumerate() {
+            current_node = match current_node.child_by(component.as_os_str()) {
+                Some(node) => node,
+                None => break,
+            };
+            if let Some(value) = current_node.value() {
+                best = Some((i + 1, value));
+            }
+        }
+        best.map(|(len, value)| {
+            let prefix = path_components[..len].iter().map(|c| c.as_os_str());
+            (prefix.collect::<PathBuf>(), value)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsStr;
+
+    use super::*;
+
+    #[test]
+    fn component_levels() {
+        let mut trie = PathTrie::new();
+        assert!(trie.insert(Path::new("/usr/local/bin"), 1));
+        assert!(trie.insert(Path::new("/usr/lib"), 2));
+        assert!(trie.insert(Path::new("relative/dir"), 3));
+        assert!(!trie.insert(Path::new("/usr//local/./bin/"), 4));
+        assert_eq!(trie.len(), 3);
+
+        assert_eq!(trie.get(Path::new("/usr/lib")), Some(&2));
+        assert_eq!(trie.get(Path::new("/usr/li")), None);
+        assert_eq!(trie.get(Path::new("usr/lib")), None);
+        assert!(trie.contains_key(Path::new("relative/dir/")));
+        // "/" is a component of its own
+        let root_dir = trie.trie.root().child_by(OsStr::new("/")).unwrap();
+        assert_eq!(
+            root_dir.child_by(OsStr::new("usr")).unwrap().child_count(),
+            2
+        );
+
+        assert_eq!(
+            trie.paths_with_prefix(Path::new("/usr"))
+                .collect::<Vec<_>>(),
+            [PathBuf::from("/usr/lib"), PathBuf::from("/usr/local/bin")]
+        );
+        assert_eq!(trie.paths_with_prefix(Path::new("/us")).next(), None);
+        assert_eq!(trie.iter().count(), 3);
+
+        assert!(trie.remove(Path::new("/usr/local/bin")));
+        assert!(!trie.remove(Path::new("/usr/local")));
+        let usr = trie
+            .trie
+            .root()
+            .child_by(OsStr::new("/"))
+            .unwrap()
+            .child_by(OsStr::new("usr"));
+        assert_eq!(usr.unwrap().child_count(), 1);
+        assert_eq!(trie.len(), 2);
+    }
+
+    #[test]
+    fn mount_points() {
+        let mut mounts = PathTrie::new();
+        mounts.insert(Path::new("/"), "root");
+        mounts.insert(Path::new("/home"), "home");
+        mounts.insert(Path::new("/home/shared/nfs"), "nfs");
+
+        let lookup = |path: &str| mounts.longest_prefix_match(Path::new(path));
+        assert_eq!(
+            lookup("/home/alice/notes.txt"),
+            Some((PathBuf::from("/home"), &"home"))
+        );
+        assert_eq!(
+            lookup("/home/shared/nfs/x"),
+            Some((PathBuf::from("/home/shared/nfs"), &"nfs"))
+        );
+        assert_eq!(lookup("/homes"), Some((PathBuf::from("/"), &"root")));
+        assert_eq!(lookup("relative"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_utf8_components() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let name = Path::new(OsStr::from_bytes(b"/data/caf\xe9"));
+        let mut trie = PathTrie::new();
+        assert!(trie.insert(name, ()));
+        assert!(trie.contains_key(name));
+        assert!(!trie.contains_key(Path::new("/data/café")));
+        assert_eq!(trie.iter().next(), Some((name.to_path_buf(), &())));
+    }
+}
@@ -0,0 +1,334 @@
+use std::str::Chars;
+use std::sync::Arc;
+
+// Node of a PersistentTrie. Nodes are never changed once shared: updates
+// build new ones along the key's path and point at the old children
+// elsewhere.
+#[derive(Debug)]
+struct PersistentNode<T> {
+    value: Option<Arc<T>>,
+    // Sorted by char
+    children: Vec<(char, Arc<PersistentNode<T>>)>,
+}
+
+impl<T> PersistentNode<T> {
+    fn empty() -> PersistentNode<T> {
+        PersistentNode {
+            value: None,
+            children: Vec::new(),
+        }
+    }
+
+    // Copy of the node itself; values and children are shared
+    fn shallow_clone(&self) -> PersistentNode<T> {
+        PersistentNode {
+            value: self.value.clone(),
+            children: self.children.clone(),
+        }
+    }
+
+    fn child_index(&self, c: char) -> Result<usize, usize> {
+        self.children.binary_search_by_key(&c, |(c, _)| *c)
+    }
+
+    fn child(&self, c: char) -> Option<&Arc<PersistentNode<T>>> {
+        let i = self.child_index(c).ok()?;
+        Some(&self.children[i].1)
+    }
+}
+
+/// Immutable trie with structural sharing, as in the persistent variant of
+/// BusTub's trie: [`PersistentTrie::insert`] and [`PersistentTrie::remove`]
+/// leave `self` untouched and return a new version that shares every
+/// subtree off the key's path with it. Only the nodes along that path are
+/// copied.
+///
+/// Cloning is O(1), so taking a snapshot to query later while the current
+/// version moves on costs nothing until the versions diverge. Nodes and
+/// values are held in `Arc`s: `T` needn't be `Clone`, and versions can be
+/// sent to and shared between threads when `T` is `Send + Sync`.
+#[derive(Debug)]
+pub struct PersistentTrie<T> {
+    root: Arc<PersistentNode<T>>,
+}
+
+impl<T> Clone for PersistentTrie<T> {
+    fn clone(&self) -> PersistentTrie<T> {
+        PersistentTrie {
+            root: Arc::clone(&self.root),
+        }
+    }
+}
+
+impl<T> Default for PersistentTrie<T> {
+    fn default() -> PersistentTrie<T> {
+        PersistentTrie::new()
+    }
+}
+
+impl<T> PersistentTrie<T> {
+    /// Creates an empty trie.
+    pub fn new() -> PersistentTrie<T> {
+        PersistentTrie {
+            root: Arc::new(PersistentNode::empty()),
+        }
+    }
+
+    /// The value stored under `key`, if any.
+    pub fn get(&self, key: &str) -> Option<&T> {
+        let mut current_node = &self.root;
+        for c in key.chars() {
+            current_node = current_node.child(c)?;
+        }
+        current_node.value.as_deref()
+    }
+
+    /// Returns true if `key` is stored in the trie.
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// A new version with `value` stored under `key`, replacing any value
+    /// already there.
+    pub fn insert(&self, key: &str, value: T) -> PersistentTrie<T> {
+        PersistentTrie {
+            root: Arc::new(Self::insert_helper(
+                Some(&self.root),
+                key.chars(),
+                Arc::new(value),
+            )),
+        }
+    }
+
+    // Copy of `node` (or a new node where there was none) with `value`
+    // stored `chars` below it
+    fn insert_helper(
+        node: Option<&PersistentNode<T>>,
+        mut chars: Chars<'_>,
+        value: Arc<T>,
+    ) -> PersistentNode<T> {
+        let mut copy = node.map_or_else(PersistentNode::empty, PersistentNode::shallow_clone);
+        match chars.next() {
+            None => copy.value = Some(value),
+            Some(c) => match copy.child_index(c) {
+                Ok(i) => {
+                    let child = Self::insert_helper(Some(&copy.children[i].1), chars, value);
+                    copy.children[i].1 = Arc::new(child);
+                }
+                Err(i) => {
+                    let child = Self::insert_helper(None, chars, value);
+                    copy.children.insert(i, (c, Arc::new(child)));
+                }
+            },
+        }
+        copy
+    }
+
+    /// A new version without `key`. Nodes left without values or children
+    /// are pruned. If `key` isn't stored, the new version is a clone of
+    /// `self`.
+    pub fn remove(&self, key: &str) -> PersistentTrie<T> {
+        match Self::remove_helper(&self.root, key.chars()) {
+            None => self.clone(),
+            Some(root) => PersistentTrie {
+                root: Arc::new(root.unwrap_or_else(PersistentNode::empty)),
+            },
+        }
+    }
+
+    // None if nothing is stored `chars` below `node`, otherwise the copy of
+    // `node` without it, or None in turn if that copy would be bare
+    fn remove_helper(
+        node: &PersistentNode<T>,
+        mut chars: Chars<'_>,
+    ) -> Option<Option<PersistentNode<T>>> {
+        let copy = match chars.next() {
+            None => {
+                node.value.as_ref()?;
+                let mut copy = node.shallow_clone();
+                copy.value = None;
+                copy
+            }
+            Some(c) => {
+                let i = node.child_index(c).ok()?;
+                let child = Self::remove_helper(&node.children[i].1, chars)?;
+                let mut copy = node.shallow_clone();
+                match child {
+                    Some(child) => copy.children[i].1 = Arc::new(child),
+                    None => {
+                        copy.children.remove(i);
+                    }
+                }
+                copy
+            }
+        };
+        if copy.value.is_none() && copy.children.is_empty() {
+            return Some(None);
+        }
+        Some(Some(copy))
+    }
+
+    /// Number of stored keys. Counted on the fly, so this is O(n).
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    /// Returns true if no key is stored.
+    pub fn is_empty(&self) -> bool {
+        // Removal prunes emptied nodes, so any child leads to a value
+        self.root.value.is_none() && self.root.children.is_empty()
+    }
+
+    /// Iterates over all entries in lexicographic key order.
+    pub fn iter(&self) -> PersistentIter<'_, T> {
+        PersistentIter {
+            stack: vec![(&*self.root, 0, None)],
+            key: String::new(),
+        }
+    }
+}
+
+/// Iterator over a [`PersistentTrie`]'s entries in lexicographic key order.
+pub struct PersistentIter<'a, T> {
+    // Pre-order walk, which visits keys in lexicographic order. One key
+    // buffer is shared between steps and cloned for each entry.
+    //
+    // Node, key length (in bytes) of its parent, and the char on the edge
+    // from it (none for the root)
+    stack: Vec<(&'a PersistentNode<T>, usize, Option<char>)>,
+    key: String,
+}
+
+impl<'a, T> Iterator for PersistentIter<'a, T> {
+    type Item = (String, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((node, parent_len, edge)) = self.stack.pop() {
+            self.key.truncate(parent_len);
+            self.key.extend(edge);
+            for (c, child) in node.children.iter().rev() {
+                self.stack.push((child, self.key.len(), Some(*c)));
+            }
+            if let Some(value) = &node.value {
+                return Some((self.key.clone(), value));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::XorShift64;
+    use crate::Trie;
+
+    fn subtree<'a, T>(trie: &'a PersistentTrie<T>, key: &str) -> &'a Arc<PersistentNode<T>> {
+        key.chars()
+            .fold(&trie.root, |node, c| node.child(c).unwrap())
+    }
+
+    #[test]
+    fn snapshots_are_unchanged() {
+        let mut current = PersistentTrie::new();
+        for (i, key) in ["tea", "ten", "to", "inn"].iter().enumerate() {
+            current = current.insert(key, i);
+        }
+        let snapshot = current.clone();
+        current = current.insert("tea", 10).insert("team", 11).remove("to");
+        let current = current.remove("inn").remove("nothing");
+
+        assert_eq!(
+            snapshot.iter().collect::<Vec<_>>(),
+            [
+                (String::from("inn"), &3),
+                (String::from("tea"), &0),
+                (String::from("ten"), &1),
+                (String::from("to"), &2),
+            ]
+        );
+        assert_eq!(
+            current.iter().collect::<Vec<_>>(),
+            [
+                (String::from("tea"), &10),
+                (String::from("team"), &11),
+                (String::from("ten"), &1),
+            ]
+        );
+        // Pruned in the new version only
+        assert!(current.root.child('i').is_none());
+        assert!(subtree(&snapshot, "t").child('o').is_some());
+        assert!(subtree(&current, "t").child('o').is_none());
+
+        let empty = current.remove("tea").remove("team").remove("ten");
+        assert!(empty.is_empty());
+        assert_eq!(current.len(), 3);
+    }
+
+    #[test]
+    fn untouched_subtrees_are_shared() {
+        let mut v1 = PersistentTrie::new();
+        for key in ["apple", "apply", "ban", "banana", "band", "bandana"] {
+            v1 = v1.insert(key, String::from(key));
+        }
+        let v2 = v1.insert("bank", String::from("bank"));
+
+        // Off the path: the same nodes
+        assert!(Arc::ptr_eq(subtree(&v1, "a"), subtree(&v2, "a")));
+        assert!(Arc::ptr_eq(subtree(&v1, "bana"), subtree(&v2, "bana")));
+        assert!(Arc::ptr_eq(subtree(&v1, "band"), subtree(&v2, "band")));
+        assert_eq!(Arc::strong_count(subtree(&v1, "a")), 2);
+        // On the path: copies, sharing the values
+        assert!(!Arc::ptr_eq(subtree(&v1, "ban"), subtree(&v2, "ban")));
+        let ban = subtree(&v1, "ban").value.as_ref().unwrap();
+        assert_eq!(Arc::strong_count(ban), 2);
+
+        let v3 = v2.remove("apply");
+        assert!(!Arc::ptr_eq(subtree(&v2, "appl"), subtree(&v3, "appl")));
+        assert!(Arc::ptr_eq(subtree(&v2, "b"), subtree(&v3, "b")));
+        assert!(Arc::ptr_eq(subtree(&v2, "apple"), subtree(&v3, "apple")));
+
+        // Removing a missing key shares everything
+        assert!(Arc::ptr_eq(&v3.root, &v3.remove("ba").root));
+        drop(v1);
+        assert_eq!(Arc::strong_count(subtree(&v2, "a")), 1);
+    }
+
+    #[test]
+    fn send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<PersistentTrie<String>>();
+
+        let trie = PersistentTrie::new().insert("a", 1);
+        let snapshot = trie.clone();
+        let handle = std::thread::spawn(move || snapshot.get("a").copied());
+        let trie = trie.insert("a", 2);
+        assert_eq!(handle.join().unwrap(), Some(1));
+        assert_eq!(trie.get("a"), Some(&2));
+    }
+
+    #[test]
+    fn matches_trie() {
+        let mut rng = XorShift64(0x9e25_1573_0000_0067);
+        let mut versions = vec![(PersistentTrie::new(), Trie::new())];
+        for i in 0..500 {
+            let (persistent, trie) = &versions[rng.below(versions.len())];
+            let mut trie = trie.clone_with_map(|value: &usize| *value);
+            let key: String = (0..rng.below(5))
+                .map(|_| char::from(b'a' + rng.below(3) as u8))
+                .collect();
+            trie.remove_if(&key, |_| true);
+            let persistent = if rng.below(3) == 0 {
+                persistent.remove(&key)
+            } else {
+                trie.insert(&key, i);
+                persistent.insert(&key, i)
+            };
+            versions.push((persistent, trie));
+        }
+        for (persistent, trie) in &versions {
+            assert!(persistent.iter().eq(trie.iter()));
+            assert_eq!(persistent.is_empty(), trie.is_empty());
+        }
+    }
+}
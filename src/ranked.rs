@@ -0,0 +1,114 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::hash::{BuildHasher, Hash};
+
+use crate::node::GenericNode;
+
+// A node found by top_k, with its key
+pub(crate) type Ranked<'a, K, T, S, P> = (Vec<K>, &'a GenericNode<K, T, S, P>);
+
+// The (at most) `k` nodes under `start`, whose key is `prefix`, with the
+// highest priorities, highest first, along with their keys. Equal
+// priorities are in lexicographic key order. Values without a priority
+// are left out.
+//
+// Best-first: every node knows the highest priority in its subtree, so a
+// subtree is expanded only once that is the highest left, and subtrees
+// below the k-th result never are. Finding the top `k` of `n` matches costs
+// about `k · depth · fanout` steps instead of collecting and sorting all
+// `n`.
+pub(crate) fn top_k<K, T, S, P>(
+    start: &GenericNode<K, T, S, P>,
+    prefix: Vec<K>,
+    k: usize,
+) -> Vec<Ranked<'_, K, T, S, P>>
+where
+    K: Ord + Hash + Clone,
+    S: BuildHasher + Default,
+    P: PartialOrd + Clone,
+{
+    let mut top = Vec::new();
+    let priority = match start.max_priority() {
+        Some(priority) if k > 0 => priority,
+        _ => return top,
+    };
+
+    let mut queue = BinaryHeap::new();
+    queue.push(Candidate {
+        priority,
+        key: prefix,
+        node: start,
+        subtree: true,
+    });
+    while let Some(Candidate {
+        key, node, subtree, ..
+    }) = queue.pop()
+    {
+        if !subtree {
+            top.push((key, node));
+            if top.len() == k {
+                break;
+            }
+            continue;
+        }
+        if let Some(priority) = node.priority() {
+            queue.push(Candidate {
+                priority,
+                key: key.clone(),
+                node,
+                subtree: false,
+            });
+        }
+        for child in node.child_nodes() {
+            // Subtrees without any priority are never worth a look
+            if let Some(priority) = child.max_priority() {
+                let mut child_key = key.clone();
+                child_key.push(child.key().clone());
+                queue.push(Candidate {
+                    priority,
+                    key: child_key,
+                    node: child,
+                    subtree: true,
+                });
+            }
+        }
+    }
+    top
+}
+
+// Work item of top_k's search: a node whose value is to be reported, or
+// whose subtree is yet to be expanded, with the subtree's highest priority.
+// The highest priority comes first, then the smallest key. A subtree's key
+// is a prefix of all keys in it, so on a tie it is expanded before any
+// entry it could outrank is reported.
+struct Candidate<'a, K, T, S, P> {
+    priority: &'a P,
+    key: Vec<K>,
+    node: &'a GenericNode<K, T, S, P>,
+    subtree: bool,
+}
+
+impl<K: Ord, T, S, P: PartialOrd> Ord for Candidate<'_, K, T, S, P> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Incomparable priorities, which only some `P` have, count as equal
+        self.priority
+            .partial_cmp(other.priority)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| other.key.cmp(&self.key))
+            .then_with(|| self.subtree.cmp(&other.subtree))
+    }
+}
+
+impl<K: Ord, T, S, P: PartialOrd> PartialOrd for Candidate<'_, K, T, S, P> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K: Ord, T, S, P: PartialOrd> PartialEq for Candidate<'_, K, T, S, P> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<K: Ord, T, S, P: PartialOrd> Eq for Candidate<'_, K, T, S, P> {}
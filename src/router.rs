@@ -0,0 +1,360 @@
+use std::fmt;
+
+use crate::node::GenericNode;
+use crate::segment::SegmentTrie;
+
+/// Error for a route [`Router::add_route`] can't register.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RouteError {
+    /// The exact same pattern is already registered.
+    DuplicateRoute,
+    /// A catch-all (`*name`) segment isn't the last one.
+    CatchAllNotLast,
+    /// A `:` or `*` segment has no name after it.
+    EmptyParamName,
+    /// Another route already has a differently named parameter or
+    /// catch-all, `existing`, at the same position.
+    ConflictingParam { existing: String },
+}
+
+impl fmt::Display for RouteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RouteError::DuplicateRoute => write!(f, "route is already registered"),
+            RouteError::CatchAllNotLast => write!(f, "catch-all must be the last segment"),
+            RouteError::EmptyParamName => write!(f, "parameter has no name"),
+            RouteError::ConflictingParam { existing } => {
+                write!(
+                    f,
+                    "conflicts with parameter `{}` of another route",
+                    existing
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for RouteError {}
+
+/// Parameters captured by [`Router::match_path`]: names borrowed from the
+/// route, values borrowed from the matched path.
+#[derive(Debug, Default, PartialEq)]
+pub struct Params<'r, 'p> {
+    // Few routes have more than a couple of parameters, so a Vec beats a map
+    entries: Vec<(&'r str, &'p str)>,
+}
+
+impl<'r, 'p> Params<'r, 'p> {
+    /// The value captured for parameter `name`, if the route has one.
+    pub fn get(&self, name: &str) -> Option<&'p str> {
+        self.entries
+            .iter()
+            .find(|(param, _)| *param == name)
+            .map(|(_, value)| *value)
+    }
+
+    /// Number of captured parameters.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if nothing was captured.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Iterates over `(name, value)` pairs in route order.
+    pub fn iter(&self) -> impl Iterator<Item = (&'r str, &'p str)> + '_ {
+        self.entries.iter().copied()
+    }
+}
+
+/// URL path router on top of a [`SegmentTrie`] split on `/`.
+///
+/// Route patterns are made of `/`-separated segments, each one of:
+///
+/// - a literal, matching exactly that segment;
+/// - `:name`, a parameter matching any one non-empty segment;
+/// - `*name`, a catch-all matching the non-empty rest of the path, which
+///   must be the pattern's last segment.
+///
+/// When several routes match, an exact segment beats a parameter, which
+/// beats a catch-all, deciding segment by segment from the left.
+///
+/// A single leading `/` is ignored, so "/users" and "users" are the same
+/// route, and "/" is the root. Otherwise segments are taken literally: a
+/// trailing slash or an empty segment ("a//b") is one more, empty, segment
+/// that only a literal empty segment in the pattern matches.
+#[derive(Debug)]
+pub struct Router<T> {
+    routes: SegmentTrie<T>,
+}
+
+impl<T> Default for Router<T> {
+    fn default() -> Router<T> {
+        Router::new()
+    }
+}
+
+// Path without its leading slash, if it has one
+fn strip_root(path: &str) -> &str {
+    path.strip_prefix('/').unwrap_or(path)
+}
+
+// The parameter child of `node` with the given sigil, if any. Registration
+// keeps at most one per sigil.
+fn param_child<T>(node: &GenericNode<String, T>, sigil: char) -> Option<&GenericNode<String, T>> {
+    node.child_nodes()
+        .find(|child| child.key().starts_with(sigil))
+}
+
+impl<T> Router<T> {
+    /// Creates a router with no routes.
+    pub fn new() -> Router<T> {
+        Router {
+            routes: SegmentTrie::new('/'),
+        }
+    }
+
+    /// Registers `handler` for `pattern`.
+    ///
+    /// # Errors
+    ///
+    /// - [`RouteError::DuplicateRoute`] if `pattern` is already registered.
+    /// - [`RouteError::CatchAllNotLast`] if a catch-all isn't last.
+    /// - [`RouteError::EmptyParamName`] for a bare `:` or `*` segment.
+    /// - [`RouteError::ConflictingParam`] if another route has a parameter
+    ///   or catch-all of a different name at the same position.
+    pub fn add_route(&mut self, pattern: &str, handler: T) -> Result<(), RouteError> {
+        let pattern = strip_root(pattern);
+        let segments: Vec<&str> = if pattern.is_empty() {
+            Vec::new()
+        } else {
+            pattern.split('/').collect()
+        };
+
+        let mut node = Some(self.routes.root());
+        for (i, segment) in segments.iter().enumerate() {
+            let sigil = match segment.chars().next() {
+                Some(sigil @ (':' | '*')) => Some(sigil),
+                _ => None,
+            };
+            if let Some(sigil) = sigil {
+                if segment.len() == 1 {
+                    return Err(RouteError::EmptyParamName);
+                }
+                if sigil == '*' && i + 1 != segments.len() {
+                    return Err(RouteError::CatchAllNotLast);
+                }
+                if let Some(existing) = node.and_then(|node| param_child(node, sigil)) {
+                    if existing.key() != segment {
+                        return Err(RouteError::ConflictingParam {
+                            existing: existing.key().clone(),
+                        });
+                    }
+                }
+            }
+            node = node.and_then(|node| node.child_by(*segment));
+        }
+
+        if !self.routes.insert(pattern, handler) {
+            return Err(RouteError::DuplicateRoute);
+        }
+        Ok(())
+    }
+
+    /// The handler of the best route matching `path`, with the parameters
+    /// it captured. Apart from the [`Params`], matching doesn't allocate.
+    pub fn match_path<'p>(&self, path: &'p str) -> Option<(&T, Params<'_, 'p>)> {
+        let path = strip_root(path);
+        let mut params = Params::default();
+        let rest = (!path.is_empty()).then_some(path);
+        let handler = Self::match_node(self.routes.root(), rest, &mut params)?;
+        Some((handler, params))
+    }
+
+    // Match `path`, the part still to match (None once every segment is
+    // consumed), below `node`. Backtracks on failure, leaving `params` as it
+    // found them.
+    fn match_node<'r, 'p>(
+        node: &'r GenericNode<String, T>,
+        path: Option<&'p str>,
+        params: &mut Params<'r, 'p>,
+    ) -> Option<&'r T> {
+        let path = match path {
+            Some(path) => path,
+            None => return node.value(),
+        };
+        let (segment, rest) = match path.split_once('/') {
+            Some((segment, rest)) => (segment, Some(rest)),
+            None => (path, None),
+        };
+
+        // Parameter nodes are keyed by their pattern segment, which a path
+        // segment spelled the same mustn't reach as a literal
+        let literal = node
+            .child_by(segment)
+            .filter(|_| !segment.starts_with([':', '*']));
+        if let Some(child) = literal {
+            if let Some(handler) = Self::match_node(child, rest, params) {
+                return Some(handler);
+            }
+        }
+
+        if !segment.is_empty() {
+            if let Some(child) = param_child(node, ':') {
+                params.entries.push((&child.key()[1..], segment));
+                if let Some(handler) = Self::match_node(child, rest, params) {
+                    return Some(handler);
+                }
+                params.entries.pop();
+            }
+        }
+
+        if path.is_empty() {
+            return None;
+        }
+        let child = param_child(node, '*')?;
+        let handler = child.value()?;
+        params.entries.push((&child.key()[1..], path));
+        Some(handler)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn router() -> Router<&'static str> {
+        let mut router = Router::new();
+        for route in [
+            "/",
+            "/users",
+            "/users/new",
+            "/users/:id",
+            "/users/:id/posts",
+            "/users/:id/posts/:post",
+            "/files/*path",
+            "/users/*rest",
+        ] {
+            router.add_route(route, route).unwrap();
+        }
+        router
+    }
+
+    #[test]
+    fn priority() {
+        let router = router();
+        let (handler, params) = router.match_path("/users/new").unwrap();
+        assert_eq!(*handler, "/users/new");
+        assert!(params.is_empty());
+
+        let (handler, params) = router.match_path("/users/42").unwrap();
+        assert_eq!(*handler, "/users/:id");
+        assert_eq!(params.get("id"), Some("42"));
+
+        // "new" is tried as a literal first, then as :id
+        let (handler, params) = router.match_path("/users/new/posts").unwrap();
+        assert_eq!(*handler, "/users/:id/posts");
+        assert_eq!(params.get("id"), Some("new"));
+
+        // Neither the literal nor :id lead anywhere, so the catch-all does
+        let (handler, params) = router.match_path("/users/42/likes").unwrap();
+        assert_eq!(*handler, "/users/*rest");
+        assert_eq!(params.get("rest"), Some("42/likes"));
+        assert_eq!(params.get("id"), None);
+    }
+
+    #[test]
+    fn params() {
+        let router = router();
+        let (handler, params) = router.match_path("/users/7/posts/hello").unwrap();
+        assert_eq!(*handler, "/users/:id/posts/:post");
+        assert_eq!(
+            params.iter().collect::<Vec<_>>(),
+            [("id", "7"), ("post", "hello")]
+        );
+        assert_eq!(params.len(), 2);
+
+        let (handler, params) = router.match_path("/files/css/site.css").unwrap();
+        assert_eq!(*handler, "/files/*path");
+        assert_eq!(params.get("path"), Some("css/site.css"));
+
+        assert_eq!(router.match_path("/").unwrap().0, &"/");
+        assert_eq!(router.match_path("").unwrap().0, &"/");
+        assert_eq!(router.match_path("users").unwrap().0, &"/users");
+        assert!(router.match_path("/posts").is_none());
+
+        // A path spelling out a parameter is just a value for it
+        let (handler, params) = router.match_path("/users/:id").unwrap();
+        assert_eq!(*handler, "/users/:id");
+        assert_eq!(params.get("id"), Some(":id"));
+    }
+
+    #[test]
+    fn trailing_slashes_and_empty_segments() {
+        let mut router = router();
+        // A trailing slash is an empty segment, which parameters don't match
+        assert!(router.match_path("/users/").is_none());
+        assert!(router.match_path("/files/").is_none());
+        assert_eq!(
+            router.match_path("/files/docs/").unwrap().1.get("path"),
+            Some("docs/")
+        );
+        // Neither do empty segments in the middle
+        let (handler, params) = router.match_path("/users//posts").unwrap();
+        assert_eq!(*handler, "/users/*rest");
+        assert_eq!(params.get("rest"), Some("/posts"));
+
+        router.add_route("/users/", "/users/").unwrap();
+        router.add_route("/a//b", "/a//b").unwrap();
+        assert_eq!(router.match_path("/users/").unwrap().0, &"/users/");
+        assert_eq!(router.match_path("/a//b").unwrap().0, &"/a//b");
+        assert!(router.match_path("/a/b").is_none());
+    }
+
+    #[test]
+    fn registration_errors() {
+        let mut router = router();
+        assert_eq!(
+            router.add_route("/users/:id", "again"),
+            Err(RouteError::DuplicateRoute)
+        );
+        assert_eq!(
+            router.add_route("users/new", "again"),
+            Err(RouteError::DuplicateRoute)
+        );
+        assert_eq!(
+            router.add_route("/users/:name/likes", "likes"),
+            Err(RouteError::ConflictingParam {
+                existing: String::from(":id")
+            })
+        );
+        assert_eq!(
+            router.add_route("/files/*rest", "files"),
+            Err(RouteError::ConflictingParam {
+                existing: String::from("*path")
+            })
+        );
+        assert_eq!(
+            router.add_route("/static/*path/index", "index"),
+            Err(RouteError::CatchAllNotLast)
+        );
+        assert_eq!(
+            router.add_route("/users/:/x", "x"),
+            Err(RouteError::EmptyParamName)
+        );
+        assert_eq!(
+            RouteError::CatchAllNotLast.to_string(),
+            "catch-all must be the last segment"
+        );
+
+        // Failed registrations leave no trace
+        assert!(router.match_path("/static/a/index").is_none());
+        router.add_route("/users/:id/likes", "likes").unwrap();
+        let (handler, params) = router.match_path("/users/3/likes").unwrap();
+        assert_eq!(*handler, "likes");
+        assert_eq!(params.get("id"), Some("3"));
+    }
+}
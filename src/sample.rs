@@ -0,0 +1,151 @@
+use rand::seq::IteratorRandom;
+use rand::Rng;
+
+use crate::node::TrieNode;
+use crate::trie::Trie;
+
+impl<T> Trie<T> {
+    /// `n` distinct keys chosen uniformly at random, or every key if there
+    /// are no more than `n`. The keys come in no particular order. Needs the
+    /// `rand` feature.
+    ///
+    /// This is reservoir sampling over [`Trie::iter`], so it visits every
+    /// node once whatever `n` is.
+    pub fn sample(&self, rng: &mut impl Rng, n: usize) -> Vec<String> {
+        self.iter().map(|(key, _)| key).choose_multiple(rng, n)
+    }
+
+    /// A stored entry chosen uniformly at random, or `None` if the trie is
+    /// empty. Needs the `rand` feature.
+    ///
+    /// The walk goes down from the root choosing each child with a weight
+    /// of the number of keys below it, so deep keys are as likely as
+    /// shallow ones. Nodes keep those numbers, so a draw is O(fanout) per
+    /// level.
+    pub fn random_key(&self, rng: &mut impl Rng) -> Option<(String, &T)> {
+        Self::random_key_below(self.root(), String::new(), rng)
+    }
+
+    /// A stored entry whose key starts with `prefix`, chosen uniformly at
+    /// random, or `None` if there is none; see [`Trie::random_key`]. Keys
+    /// come back in full, prefix included.
+    pub fn random_key_with_prefix(&self, prefix: &str, rng: &mut impl Rng) -> Option<(String, &T)> {
+        let prefix = self.normalize(prefix).into_owned();
+        let mut node = self.root();
+        for c in prefix.chars() {
+            node = node.child(c)?;
+        }
+        Self::random_key_below(node, prefix, rng)
+    }
+
+    fn random_key_below<'a>(
+        mut node: &'a TrieNode<T>,
+        mut key: String,
+        rng: &mut impl Rng,
+    ) -> Option<(String, &'a T)> {
+        let total = node.count_values();
+        if total == 0 {
+            return None;
+        }
+        // Index of the chosen entry among those below `node`, in the order
+        // the walk visits them
+        let mut index = rng.gen_range(0..total);
+        loop {
+            if let Some(value) = node.value() {
+                if index == 0 {
+                    return Some((key, value));
+                }
+                index -= 1;
+            }
+            for (c, child) in node.children() {
+                let count = child.count_values();
+                if index < count {
+                    key.push(c);
+                    node = child;
+                    break;
+                }
+                index -= count;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn distinct_and_uniform() {
+        let keys = ["", "a", "ab", "abc", "b", "été"];
+        let mut trie = Trie::new();
+        for key in keys {
+            trie.insert(key, ());
+        }
+        let mut rng = StdRng::seed_from_u64(7);
+        assert!(Trie::<()>::new().sample(&mut rng, 3).is_empty());
+        assert!(trie.sample(&mut rng, 0).is_empty());
+        let mut all = trie.sample(&mut rng, 10);
+        all.sort();
+        assert_eq!(all, keys);
+
+        let rounds = 6000;
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for _ in 0..rounds {
+            let mut sample = trie.sample(&mut rng, 2);
+            sample.sort();
+            sample.dedup();
+            assert_eq!(sample.len(), 2);
+            for key in sample {
+                *counts.entry(key).or_default() += 1;
+            }
+        }
+        // Each key is picked a third of the time, give or take noise
+        assert_eq!(counts.len(), keys.len());
+        for (key, count) in counts {
+            assert!((1800..2200).contains(&count), "{:?}: {}", key, count);
+        }
+    }
+
+    #[test]
+    fn random_key_is_uniform() {
+        let keys = ["", "a", "ab", "abc", "b", "été", "bcdefghij"];
+        let mut trie = Trie::new_with_normalizer(|key| key.to_lowercase());
+        for (i, key) in keys.iter().enumerate() {
+            trie.insert(key, i);
+        }
+        let mut rng = StdRng::seed_from_u64(88);
+        assert_eq!(Trie::<()>::new().random_key(&mut rng), None);
+        assert_eq!(trie.random_key_with_prefix("x", &mut rng), None);
+        assert_eq!(trie.random_key_with_prefix("abcd", &mut rng), None);
+
+        let rounds = 7000;
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for _ in 0..rounds {
+            let (key, value) = trie.random_key(&mut rng).unwrap();
+            assert_eq!(keys[*value], key);
+            *counts.entry(key).or_default() += 1;
+        }
+        // A naive walk would pick "" or "bcdefghij" far more often than
+        // "abc"; here each key comes up a seventh of the time
+        assert_eq!(counts.len(), keys.len());
+        for (key, count) in counts {
+            assert!((850..1150).contains(&count), "{:?}: {}", key, count);
+        }
+
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for _ in 0..3000 {
+            let (key, _) = trie.random_key_with_prefix("A", &mut rng).unwrap();
+            *counts.entry(key).or_default() += 1;
+        }
+        assert_eq!(counts.len(), 3);
+        for (key, count) in counts {
+            assert!(key.starts_with('a'));
+            assert!((850..1150).contains(&count), "{:?}: {}", key, count);
+        }
+    }
+}
@@ -0,0 +1,212 @@
+use crate::generic::GenericTrie;
+use crate::node::GenericNode;
+
+/// Trie whose unit is a delimiter-separated segment rather than a char, for
+/// keys like filesystem paths or MQTT topics: with `'/'` as the delimiter,
+/// "usr/local/bin" is three nodes deep.
+///
+/// Segments are taken literally, so keys round-trip exactly:
+///
+/// - Empty segments are kept: "a//b" is the three segments "a", "", "b".
+/// - A leading or trailing delimiter adds an empty segment at that end, so
+///   "/usr" and "usr/" are both distinct from "usr".
+/// - The empty key has no segments and is stored on the root.
+#[derive(Debug)]
+pub struct SegmentTrie<T> {
+    trie: GenericTrie<String, T>,
+    delimiter: char,
+}
+
+impl<T> SegmentTrie<T> {
+    /// Creates an empty trie splitting keys on `delimiter`.
+    pub fn new(delimiter: char) -> SegmentTrie<T> {
+        SegmentTrie {
+            trie: GenericTrie::new(),
+            delimiter,
+        }
+    }
+
+    /// The delimiter keys are split on.
+    pub fn delimiter(&self) -> char {
+        self.delimiter
+    }
+
+    /// The root node, for read-only traversal. Each node below it is keyed
+    /// on one segment.
+    pub fn root(&self) -> &GenericNode<String, T> {
+        self.trie.root()
+    }
+
+    // Segments of `key`; none for the empty key
+    fn segments<'k>(&self, key: &'k str) -> impl Iterator<Item = String> + 'k {
+        let segments = (!key.is_empty()).then(|| key.split(self.delimiter));
+        segments.into_iter().flatten().map(String::from)
+    }
+
+    fn join(&self, segments: &[String]) -> String {
+        let mut delimiter = [0; 4];
+        segments.join(self.delimiter.encode_utf8(&mut delimiter))
+    }
+
+    /// Inserts `value` under `key`. Returns false, leaving the trie
+    /// untouched, if `key` is already stored.
+    pub fn insert(&mut self, key: &str, value: T) -> bool {
+        let segments = self.segments(key);
+        self.trie.insert(segments, value)
+    }
+
+    /// The value stored under `key`, if any.
+    pub fn get(&self, key: &str) -> Option<&T> {
+        self.trie.get(self.segments(key))
+    }
+
+    /// Returns true if `key` is stored in the trie.
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Removes `key`, returning whether it was removed. Segments left without
+    /// values or children are pruned.
+    pub fn remove(&mut self, key: &str) -> bool {
+        let segments = self.segments(key);
+        self.trie.remove(segments)
+    }
+
+    /// Number of stored keys.
+    pub fn len(&self) -> usize {
+        self.trie.len()
+    }
+
+    /// Returns true if no key is stored.
+    pub fn is_empty(&self) -> bool {
+        self.trie.is_empty()
+    }
+
+    /// Iterates over all entries, ordered segment by segment.
+    pub fn iter(&self) -> impl Iterator<Item = (String, &T)> + '_ {
+        self.trie
+            .iter()
+            .map(|(segments, value)| (self.join(&segments), value))
+    }
+
+    /// The keys whose leading segments are exactly those of `prefix`, so
+    /// "usr/loc" doesn't match "usr/local". The empty prefix matches every
+    /// key, and a trailing delimiter only matches keys continuing with an
+    /// empty segment.
+    pub fn keys_with_prefix(&self, prefix: &str) -> impl Iterator<Item = String> + '_ {
+        self.trie
+            .iter_prefix(self.segments(prefix))
+            .map(|(segments, _)| self.join(&segments))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths() -> SegmentTrie<u32> {
+        let mut trie = SegmentTrie::new('/');
+        for (i, key) in ["usr", "usr/local", "usr/local/bin", "usr/lib", "var/log"]
+            .iter()
+            .enumerate()
+        {
+            assert!(trie.insert(key, i as u32));
+        }
+        trie
+    }
+
+    #[test]
+    fn whole_segments() {
+        let trie = paths();
+        assert_eq!(trie.get("usr/local/bin"), Some(&2));
+        assert_eq!(trie.get("usr/loc"), None);
+        assert_eq!(trie.get("var"), None);
+        assert_eq!(trie.trie.root().child_count(), 2);
+        assert_eq!(trie.len(), 5);
+
+        assert_eq!(
+            trie.keys_with_prefix("usr/local").collect::<Vec<_>>(),
+            ["usr/local", "usr/local/bin"]
+        );
+        assert_eq!(trie.keys_with_prefix("usr/loc").next(), None);
+        assert_eq!(
+            trie.iter().collect::<Vec<_>>(),
+            vec![
+                (String::from("usr"), &0),
+                (String::from("usr/lib"), &3),
+                (String::from("usr/local"), &1),
+                (String::from("usr/local/bin"), &2),
+                (String::from("var/log"), &4),
+            ]
+        );
+    }
+
+    #[test]
+    fn root_segment() {
+        let mut trie = paths();
+        assert_eq!(trie.keys_with_prefix("").count(), 5);
+        assert_eq!(trie.get(""), None);
+        assert!(trie.insert("", 9));
+        assert_eq!(trie.get(""), Some(&9));
+        assert_eq!(trie.keys_with_prefix("").next(), Some(String::new()));
+        assert!(trie.remove(""));
+        assert_eq!(trie.len(), 5);
+    }
+
+    #[test]
+    fn delimiter_edge_cases() {
+        let mut trie = SegmentTrie::new('/');
+        assert!(trie.insert("usr", 1));
+        assert!(trie.insert("/usr", 2));
+        assert!(trie.insert("usr/", 3));
+        assert!(trie.insert("a//b", 4));
+        assert!(trie.insert("/", 5));
+        assert!(!trie.insert("usr/", 6));
+
+        assert_eq!(trie.get("usr"), Some(&1));
+        assert_eq!(trie.get("/usr"), Some(&2));
+        assert_eq!(trie.get("usr/"), Some(&3));
+        assert_eq!(trie.get("a//b"), Some(&4));
+        assert_eq!(trie.get("a/b"), None);
+        assert_eq!(trie.get("/"), Some(&5));
+
+        // "a//b" is a, "", b
+        let a = trie.trie.root().child(String::from("a")).unwrap();
+        assert!(a.child(String::new()).unwrap().has_child(String::from("b")));
+
+        // Keys round-trip with their empty segments
+        let mut keys: Vec<String> = trie.iter().map(|(key, _)| key).collect();
+        keys.sort();
+        assert_eq!(keys, ["/", "/usr", "a//b", "usr", "usr/"]);
+        assert_eq!(
+            trie.keys_with_prefix("usr").collect::<Vec<_>>(),
+            ["usr", "usr/"]
+        );
+        assert_eq!(trie.keys_with_prefix("usr/").collect::<Vec<_>>(), ["usr/"]);
+        // "/" is two empty segments, so it isn't a prefix of "/usr"
+        assert_eq!(trie.keys_with_prefix("/").collect::<Vec<_>>(), ["/"]);
+
+        assert!(trie.remove("a//b"));
+        assert!(!trie.trie.root().has_child(String::from("a")));
+        assert!(!trie.remove("a/b"));
+    }
+
+    #[test]
+    fn other_delimiters() {
+        let mut topics = SegmentTrie::new('.');
+        topics.insert("sensors.kitchen.temp", 21);
+        topics.insert("sensors.kitchen.humidity", 40);
+        topics.insert("sensors.hall.temp", 19);
+        assert_eq!(topics.delimiter(), '.');
+        assert_eq!(
+            topics
+                .keys_with_prefix("sensors.kitchen")
+                .collect::<Vec<_>>(),
+            ["sensors.kitchen.humidity", "sensors.kitchen.temp"]
+        );
+
+        let mut wide = SegmentTrie::new('→');
+        wide.insert("a→b", ());
+        assert_eq!(wide.iter().next(), Some((String::from("a→b"), &())));
+    }
+}
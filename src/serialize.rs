@@ -0,0 +1,397 @@
+use std::io::{self, Read, Write};
+
+use crate::node::TrieNode;
+use crate::trie::Trie;
+
+// Start of every serialized trie: a tag plus a format version byte
+const MAGIC: [u8; 4] = *b"TRI\x01";
+
+/// Values that [`Trie::serialize`] can write out and [`Trie::deserialize`]
+/// read back, in a compact binary form of their own choosing.
+///
+/// Implemented for the integer types (little-endian, `usize` and `isize` as
+/// 64 bits), `bool`, `char`, `String`, `()`, and `Vec`s and tuples of up
+/// to four of these. Other types can compose those impls, e.g. by writing
+/// their fields in order.
+pub trait TrieSerialize: Sized {
+    /// Writes `self` to `writer`.
+    fn serialize(&self, writer: &mut impl Write) -> io::Result<()>;
+
+    /// Reads back a value written by [`TrieSerialize::serialize`].
+    ///
+    /// # Errors
+    ///
+    /// [`io::ErrorKind::InvalidData`] if the bytes don't encode a value, and
+    /// any error from `reader`.
+    fn deserialize(reader: &mut impl Read) -> io::Result<Self>;
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+// Lengths and child counts are LEB128 varints, so the common small counts
+// take one byte
+fn write_varint(writer: &mut impl Write, mut n: u64) -> io::Result<()> {
+    while n >= 0x80 {
+        writer.write_all(&[n as u8 | 0x80])?;
+        n >>= 7;
+    }
+    writer.write_all(&[n as u8])
+}
+
+fn read_varint(reader: &mut impl Read) -> io::Result<u64> {
+    let mut n = 0;
+    for shift in (0..64).step_by(7) {
+        let byte = u8::deserialize(reader)?;
+        n |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(n);
+        }
+    }
+    Err(invalid_data("varint longer than 64 bits"))
+}
+
+fn read_len(reader: &mut impl Read) -> io::Result<usize> {
+    usize::try_from(read_varint(reader)?).map_err(|_| invalid_data("length overflows usize"))
+}
+
+macro_rules! impl_int {
+    ($($int:ty),*) => {$(
+        impl TrieSerialize for $int {
+            fn serialize(&self, writer: &mut impl Write) -> io::Result<()> {
+                writer.write_all(&self.to_le_bytes())
+            }
+
+            fn deserialize(reader: &mut impl Read) -> io::Result<$int> {
+                let mut bytes = [0; std::mem::size_of::<$int>()];
+                reader.read_exact(&mut bytes)?;
+                Ok(<$int>::from_le_bytes(bytes))
+            }
+        }
+    )*};
+}
+
+impl_int!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+impl TrieSerialize for usize {
+    fn serialize(&self, writer: &mut impl Write) -> io::Result<()> {
+        (*self as u64).serialize(writer)
+    }
+
+    fn deserialize(reader: &mut impl Read) -> io::Result<usize> {
+        usize::try_from(u64::deserialize(reader)?).map_err(|_| invalid_data("usize overflow"))
+    }
+}
+
+impl TrieSerialize for isize {
+    fn serialize(&self, writer: &mut impl Write) -> io::Result<()> {
+        (*self as i64).serialize(writer)
+    }
+
+    fn deserialize(reader: &mut impl Read) -> io::Result<isize> {
+        isize::try_from(i64::deserialize(reader)?).map_err(|_| invalid_data("isize overflow"))
+    }
+}
+
+impl TrieSerialize for bool {
+    fn serialize(&self, writer: &mut impl Write) -> io::Result<()> {
+        u8::from(*self).serialize(writer)
+    }
+
+    fn deserialize(reader: &mut impl Read) -> io::Result<bool> {
+        match u8::deserialize(reader)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(invalid_data("invalid bool")),
+        }
+    }
+}
+
+impl TrieSerialize for char {
+    fn serialize(&self, writer: &mut impl Write) -> io::Result<()> {
+        u32::from(*self).serialize(writer)
+    }
+
+    fn deserialize(reader: &mut impl Read) -> io::Result<char> {
+        char::from_u32(u32::deserialize(reader)?).ok_or_else(|| invalid_data("invalid char"))
+    }
+}
+
+impl TrieSerialize for String {
+    fn serialize(&self, writer: &mut impl Write) -> io::Result<()> {
+        write_varint(writer, self.len() as u64)?;
+        writer.write_all(self.as_bytes())
+    }
+
+    fn deserialize(reader: &mut impl Read) -> io::Result<String> {
+        let len = read_len(reader)?;
+        // Read through `take` rather than into a buffer of `len` bytes, so a
+        // corrupt length can't allocate more than the input holds
+        let mut bytes = Vec::new();
+        reader.take(len as u64).read_to_end(&mut bytes)?;
+        if bytes.len() < len {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        String::from_utf8(bytes).map_err(|_| invalid_data("invalid UTF-8"))
+    }
+}
+
+impl TrieSerialize for () {
+    fn serialize(&self, _writer: &mut impl Write) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn deserialize(_reader: &mut impl Read) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<T: TrieSerialize> TrieSerialize for Vec<T> {
+    fn serialize(&self, writer: &mut impl Write) -> io::Result<()> {
+        write_varint(writer, self.len() as u64)?;
+        self.iter().try_for_each(|item| item.serialize(writer))
+    }
+
+    fn deserialize(reader: &mut impl Read) -> io::Result<Vec<T>> {
+        // No preallocation, for the same reason as String's
+        (0..read_len(reader)?)
+            .map(|_| T::deserialize(reader))
+            .collect()
+    }
+}
+
+macro_rules! impl_tuple {
+    ($(($($name:ident),+)),*) => {$(
+        impl<$($name: TrieSerialize),+> TrieSerialize for ($($name,)+) {
+            #[allow(non_snake_case)]
+            fn serialize(&self, writer: &mut impl Write) -> io::Result<()> {
+                let ($($name,)+) = self;
+                $($name.serialize(writer)?;)+
+                Ok(())
+            }
+
+            fn deserialize(reader: &mut impl Read) -> io::Result<($($name,)+)> {
+                Ok(($($name::deserialize(reader)?,)+))
+            }
+        }
+    )*};
+}
+
+impl_tuple!((A, B), (A, B, C), (A, B, C, D));
+
+impl<T: TrieSerialize> Trie<T> {
+    /// Writes the trie to `writer` in a compact binary format that
+    /// [`Trie::deserialize`] loads back without any parsing beyond the
+    /// node layout.
+    ///
+    /// After a 4-byte header, the nodes follow in pre-order, children in
+    /// key order. Each node is its char as a little-endian UTF-32 code
+    /// point (`'\0'` for the root), a flag byte telling whether it holds a
+    /// value, the value if so, and its child count as a LEB128 varint. The
+    /// normalizer, if any, isn't written. Nodes left by
+    /// [`Trie::remove_lazy`] are, unless [`Trie::compact`] runs first.
+    pub fn serialize(&self, writer: &mut impl Write) -> io::Result<()> {
+        writer.write_all(&MAGIC)?;
+        let mut stack = vec![self.root()];
+        while let Some(node) = stack.pop() {
+            node.key_char().serialize(writer)?;
+            match node.value() {
+                Some(value) => {
+                    true.serialize(writer)?;
+                    value.serialize(writer)?;
+                }
+                None => false.serialize(writer)?,
+            }
+            let children = node.sorted_children();
+            write_varint(writer, children.len() as u64)?;
+            stack.extend(children.into_iter().rev());
+        }
+        Ok(())
+    }
+
+    /// Reads a trie written by [`Trie::serialize`]. The trie has no
+    /// normalizer: keys come back as they were stored, i.e. already
+    /// normalized, and later ones are used as given.
+    ///
+    /// # Errors
+    ///
+    /// [`io::ErrorKind::InvalidData`] if the input isn't a serialized trie,
+    /// [`io::ErrorKind::UnexpectedEof`] if it is cut short, and any error
+    /// from `reader`.
+    pub fn deserialize(reader: &mut impl Read) -> io::Result<Trie<T>> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            return Err(invalid_data("not a serialized trie"));
+        }
+
+        let (root, child_count) = read_node(reader)?;
+        if root.key_char() != '\0' {
+            return Err(invalid_data("root node with a key char"));
+        }
+        // Nodes whose children are still being read, with how many are left
+        let mut stack = vec![(root, child_count)];
+        loop {
+            let (_, children_left) = stack.last_mut().unwrap();
+            if *children_left > 0 {
+                *children_left -= 1;
+                stack.push(read_node(reader)?);
+                continue;
+            }
+
+            let (node, _) = stack.pop().unwrap();
+            match stack.last_mut() {
+                Some((parent, _)) => {
+                    let c = node.key_char();
+                    parent
+                        .insert_child(c, node)
+                        .map_err(|_| invalid_data("duplicate child"))?;
+                }
+                None => return Ok(Trie::from_root(node)),
+            }
+        }
+    }
+}
+
+// A node without its children, and how many children follow it
+fn read_node<T: TrieSerialize>(reader: &mut impl Read) -> io::Result<(TrieNode<T>, u64)> {
+    let c = char::deserialize(reader)?;
+    let value = match bool::deserialize(reader)? {
+        true => Some(T::deserialize(reader)?),
+        false => None,
+    };
+    Ok((TrieNode::new(c, value), read_varint(reader)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::XorShift64;
+
+    fn round_trip<T: TrieSerialize>(trie: &Trie<T>) -> Trie<T> {
+        let mut bytes = Vec::new();
+        trie.serialize(&mut bytes).unwrap();
+        Trie::deserialize(&mut bytes.as_slice()).unwrap()
+    }
+
+    fn error_kind(bytes: &[u8]) -> io::ErrorKind {
+        Trie::<u8>::deserialize(&mut &bytes[..]).unwrap_err().kind()
+    }
+
+    #[test]
+    fn layout() {
+        let mut trie = Trie::new();
+        trie.insert("a", 7u8);
+        trie.insert("aé", 8);
+        let mut bytes = Vec::new();
+        trie.serialize(&mut bytes).unwrap();
+        assert_eq!(
+            bytes,
+            [
+                b'T', b'R', b'I', 1, // header
+                0, 0, 0, 0, 0, 1, // root: '\0', no value, one child
+                b'a', 0, 0, 0, 1, 7, 1, // 'a' = 7, one child
+                0xe9, 0, 0, 0, 1, 8, 0, // 'é' = 8, no children
+            ]
+        );
+    }
+
+    #[test]
+    fn round_trips() {
+        let mut rng = XorShift64(0x5e71_a112_e000_0064);
+        let alphabet: Vec<char> = ('a'..='z').chain(['ß', '中', '🙂', '\0']).collect();
+        let mut trie = Trie::new();
+        for i in 0..2000 {
+            let key: String = (0..rng.below(8))
+                .map(|_| alphabet[rng.below(alphabet.len())])
+                .collect();
+            trie.insert(&key, (i, key.clone(), vec![i % 2 == 0]));
+        }
+        trie.insert("", (-1, String::new(), Vec::new()));
+        let copy = round_trip(&trie);
+        assert_eq!(copy, trie);
+        assert!(copy.iter().eq(trie.iter()));
+        assert_eq!(copy.len(), trie.len());
+
+        let empty = round_trip(&Trie::<()>::new());
+        assert!(empty.is_empty());
+
+        let mut set = Trie::new_with_normalizer(|key: &str| key.to_lowercase());
+        set.insert("Hello", ());
+        let copy = round_trip(&set);
+        assert!(copy.contains_key("hello"));
+        assert!(!copy.contains_key("Hello"));
+    }
+
+    #[test]
+    fn rejects_corrupt_input() {
+        let mut trie = Trie::new();
+        trie.insert("ab", 1u8);
+        let mut bytes = Vec::new();
+        trie.serialize(&mut bytes).unwrap();
+        assert_eq!(Trie::deserialize(&mut bytes.as_slice()).ok(), Some(trie));
+
+        for len in 0..bytes.len() {
+            assert_eq!(error_kind(&bytes[..len]), io::ErrorKind::UnexpectedEof);
+        }
+        let header = &bytes[..4];
+        let invalid = [
+            // Bad header
+            b"TRI\x02".to_vec(),
+            // Root keyed 'x'
+            [header, &[b'x', 0, 0, 0, 0, 0]].concat(),
+            // Surrogate code point
+            [header, &[0, 0, 0, 0, 0, 1, 0, 0xd8, 0, 0, 1, 1, 0]].concat(),
+            // Value flag 2
+            [header, &[0, 0, 0, 0, 2]].concat(),
+            // Two children keyed 'a'
+            [
+                header,
+                &[0, 0, 0, 0, 0, 2],
+                &[b'a', 0, 0, 0, 1, 1, 0],
+                &[b'a', 0, 0, 0, 1, 2, 0],
+            ]
+            .concat(),
+            // Child count varint past 64 bits
+            [header, &[0, 0, 0, 0, 0], &[0xff; 10]].concat(),
+        ];
+        for bytes in invalid {
+            assert_eq!(
+                error_kind(&bytes),
+                io::ErrorKind::InvalidData,
+                "{:?}",
+                bytes
+            );
+        }
+    }
+
+    #[test]
+    fn values() {
+        fn round_trip_value<T: TrieSerialize + PartialEq + std::fmt::Debug>(value: T) {
+            let mut bytes = Vec::new();
+            value.serialize(&mut bytes).unwrap();
+            assert_eq!(T::deserialize(&mut bytes.as_slice()).unwrap(), value);
+        }
+
+        round_trip_value(u128::MAX);
+        round_trip_value(i16::MIN);
+        round_trip_value(usize::MAX);
+        round_trip_value(-1isize);
+        round_trip_value('中');
+        round_trip_value(String::from("naïve"));
+        round_trip_value(vec![String::new(), "x".repeat(300)]);
+        round_trip_value(vec![vec![1u8, 2], vec![]]);
+
+        let mut bytes = Vec::new();
+        write_varint(&mut bytes, 300).unwrap();
+        assert_eq!(bytes, [0xac, 0x02]);
+        bytes.extend(b"short");
+        assert_eq!(
+            String::deserialize(&mut bytes.as_slice())
+                .unwrap_err()
+                .kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+    }
+}
@@ -0,0 +1,277 @@
+use crate::node::TrieNode;
+use crate::trie::Trie;
+
+/// A set of strings backed by a [`Trie`], with the `HashSet`-like API that
+/// set use calls for.
+#[derive(Debug, Default, PartialEq)]
+pub struct TrieSet {
+    trie: Trie<()>,
+}
+
+impl TrieSet {
+    /// Creates an empty set.
+    pub fn new() -> TrieSet {
+        TrieSet { trie: Trie::new() }
+    }
+
+    /// Adds `key`, returning false if it was already present.
+    pub fn insert(&mut self, key: &str) -> bool {
+        self.trie.insert(key, ())
+    }
+
+    /// Returns true if the set contains `key`.
+    pub fn contains(&self, key: &str) -> bool {
+        self.trie.contains_key(key)
+    }
+
+    /// Removes `key`, returning whether it was present.
+    pub fn remove(&mut self, key: &str) -> bool {
+        self.trie.remove_if(key, |_| true).is_some()
+    }
+
+    /// Number of keys in the set.
+    pub fn len(&self) -> usize {
+        self.trie.len()
+    }
+
+    /// Returns true if the set has no keys.
+    pub fn is_empty(&self) -> bool {
+        self.trie.is_empty()
+    }
+
+    /// Iterates over the keys in lexicographic order.
+    pub fn iter(&self) -> impl Iterator<Item = String> + '_ {
+        self.trie.iter().map(|(key, _)| key)
+    }
+
+    /// The keys in either set.
+    ///
+    /// Both tries are walked in lockstep, so shared prefixes are visited
+    /// once and no key is looked up from the root. Runs in O(n + m) for
+    /// tries of n and m nodes.
+    pub fn union(&self, other: &TrieSet) -> TrieSet {
+        TrieSet {
+            trie: Trie::from_root(union(self.trie.root(), other.trie.root())),
+        }
+    }
+
+    /// The keys in both sets.
+    ///
+    /// Only paths present in both tries are descended, so the cost is
+    /// bounded by the shared nodes and their children: at worst O(n), and
+    /// far less when the sets have little in common.
+    pub fn intersection(&self, other: &TrieSet) -> TrieSet {
+        let root = intersection(self.trie.root(), other.trie.root())
+            .unwrap_or_else(|| TrieNode::new('\0', None));
+        TrieSet {
+            trie: Trie::from_root(root),
+        }
+    }
+
+    /// The keys in `self` but not in `other`.
+    ///
+    /// Walks `self`'s trie once, descending into `other` only along shared
+    /// paths; subtrees `other` lacks are copied whole. Runs in O(n).
+    pub fn difference(&self, other: &TrieSet) -> TrieSet {
+        let root = difference(self.trie.root(), other.trie.root())
+            .unwrap_or_else(|| TrieNode::new('\0', None));
+        TrieSet {
+            trie: Trie::from_root(root),
+        }
+    }
+}
+
+// Merge of two nodes reached by the same path
+fn union(a: &TrieNode<()>, b: &TrieNode<()>) -> TrieNode<()> {
+    let mut node = TrieNode::new(a.key_char(), a.value().or(b.value()).copied());
+    for (c, a_child) in a.children() {
+        let child = match b.child(c) {
+            Some(b_child) => union(a_child, b_child),
+            None => a_child.clone_with_map(&mut |_| ()),
+        };
+        node.insert_child(c, child).unwrap();
+    }
+    for (c, b_child) in b.children().filter(|(c, _)| !a.has_child(*c)) {
+        node.insert_child(c, b_child.clone_with_map(&mut |_| ()))
+            .unwrap();
+    }
+    node
+}
+
+// Common part of two nodes reached by the same path, or None if they have
+// no key in common
+fn intersection(a: &TrieNode<()>, b: &TrieNode<()>) -> Option<TrieNode<()>> {
+    let value = a.value().and(b.value()).copied();
+    let mut node = TrieNode::new(a.key_char(), value);
+    for (c, a_child) in a.children() {
+        if let Some(child) = b
+            .child(c)
+            .and_then(|b_child| intersection(a_child, b_child))
+        {
+            node.insert_child(c, child).unwrap();
+        }
+    }
+    (node.value().is_some() || node.has_children()).then_some(node)
+}
+
+// Keys under `a` that aren't under `b`, both reached by the same path, or
+// None if there are none
+fn difference(a: &TrieNode<()>, b: &TrieNode<()>) -> Option<TrieNode<()>> {
+    let value = if b.value().is_some() {
+        None
+    } else {
+        a.value().copied()
+    };
+    let mut node = TrieNode::new(a.key_char(), value);
+    for (c, a_child) in a.children() {
+        let child = match b.child(c) {
+            Some(b_child) => difference(a_child, b_child),
+            None => Some(a_child.clone_with_map(&mut |_| ())),
+        };
+        if let Some(child) = child {
+            node.insert_child(c, child).unwrap();
+        }
+    }
+    (node.value().is_some() || node.has_children()).then_some(node)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::XorShift64;
+    use std::collections::HashSet;
+
+    #[test]
+    fn insert_contains_remove() {
+        let mut set = TrieSet::new();
+        assert!(set.is_empty());
+        assert!(set.insert("tea"));
+        assert!(set.insert("ten"));
+        assert!(set.insert(""));
+        assert!(!set.insert("tea"));
+        assert_eq!(set.len(), 3);
+        assert!(set.contains("tea"));
+        assert!(set.contains(""));
+        assert!(!set.contains("te"));
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec!["", "tea", "ten"]);
+
+        assert!(set.remove("tea"));
+        assert!(!set.remove("tea"));
+        assert!(!set.contains("tea"));
+        assert!(set.remove(""));
+        assert_eq!(set.len(), 1);
+    }
+
+    #[test]
+    fn matches_hash_set() {
+        let mut rng = XorShift64(0x9e37_79b9_7f4a_7c15);
+        let mut set = TrieSet::new();
+        let mut model = HashSet::new();
+        for _ in 0..5000 {
+            let len = rng.below(4);
+            let key: String = (0..len)
+                .map(|_| (b'a' + rng.below(3) as u8) as char)
+                .collect();
+            match rng.below(3) {
+                0 => assert_eq!(set.insert(&key), model.insert(key)),
+                1 => assert_eq!(set.remove(&key), model.remove(&key)),
+                _ => assert_eq!(set.contains(&key), model.contains(&key)),
+            }
+        }
+        assert_eq!(set.len(), model.len());
+        let mut expected: Vec<String> = model.into_iter().collect();
+        expected.sort();
+        assert_eq!(set.iter().collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn set_algebra() {
+        fn check(a: &[&str], b: &[&str]) {
+            let (mut set_a, mut set_b) = (TrieSet::new(), TrieSet::new());
+            a.iter()
+                .for_each(|key| assert!(set_a.insert(key) || a.is_empty()));
+            b.iter()
+                .for_each(|key| assert!(set_b.insert(key) || b.is_empty()));
+            let model_a: HashSet<&str> = a.iter().copied().collect();
+            let model_b: HashSet<&str> = b.iter().copied().collect();
+
+            let sorted = |keys: Vec<&&str>| {
+                let mut keys: Vec<String> = keys.into_iter().map(|key| key.to_string()).collect();
+                keys.sort();
+                keys
+            };
+            let collect = |set: TrieSet| set.iter().collect::<Vec<_>>();
+            assert_eq!(
+                collect(set_a.union(&set_b)),
+                sorted(model_a.union(&model_b).collect())
+            );
+            assert_eq!(
+                collect(set_a.intersection(&set_b)),
+                sorted(model_a.intersection(&model_b).collect())
+            );
+            assert_eq!(
+                collect(set_a.difference(&set_b)),
+                sorted(model_a.difference(&model_b).collect())
+            );
+            assert_eq!(
+                collect(set_b.difference(&set_a)),
+                sorted(model_b.difference(&model_a).collect())
+            );
+        }
+
+        // Disjoint, including disjoint from the first char
+        check(&["tea", "ten"], &["cat", "cow"]);
+        check(&["tea", "ten"], &["tap", "toe"]);
+        // Nested: every key of one is a prefix of a key of the other
+        check(&["a", "ab", "abc"], &["ab", "abcd", "abcde"]);
+        check(&["", "in", "inn"], &["i", "inner", "inn"]);
+        // Overlapping, wide enough for map-backed children
+        let letters: Vec<String> = ('a'..='z').map(|c| format!("x{}", c)).collect();
+        let letters: Vec<&str> = letters.iter().map(|key| key.as_str()).collect();
+        check(&letters, &letters[10..]);
+        check(&letters[..20], &["xa", "xb", "y", "x"]);
+        // Empty sets and identical sets
+        check(&[], &["a"]);
+        check(&[], &[]);
+        check(&["same", "keys"], &["same", "keys"]);
+    }
+
+    #[test]
+    fn set_algebra_random() {
+        let mut rng = XorShift64(0x5851_f42d_4c95_7f2d);
+        for _ in 0..50 {
+            let mut sets = [TrieSet::new(), TrieSet::new()];
+            let mut models = [HashSet::new(), HashSet::new()];
+            for i in 0..2 {
+                for _ in 0..rng.below(40) {
+                    let len = rng.below(5);
+                    let key: String = (0..len)
+                        .map(|_| (b'a' + rng.below(3) as u8) as char)
+                        .collect();
+                    sets[i].insert(&key);
+                    models[i].insert(key);
+                }
+            }
+
+            let sorted = |keys: HashSet<&String>| {
+                let mut keys: Vec<String> = keys.into_iter().cloned().collect();
+                keys.sort();
+                keys
+            };
+            let [a, b] = &sets;
+            let [model_a, model_b] = &models;
+            assert_eq!(
+                a.union(b).iter().collect::<Vec<_>>(),
+                sorted(model_a.union(model_b).collect())
+            );
+            assert_eq!(
+                a.intersection(b).iter().collect::<Vec<_>>(),
+                sorted(model_a.intersection(model_b).collect())
+            );
+            assert_eq!(
+                a.difference(b).iter().collect::<Vec<_>>(),
+                sorted(model_a.difference(model_b).collect())
+            );
+        }
+    }
+}
@@ -0,0 +1,438 @@
+use std::collections::HashSet;
+use std::mem::ManuallyDrop;
+use std::ptr::NonNull;
+
+// Slots in an Arena's first slab, unless it is given a capacity
+const FIRST_SLAB_SLOTS: usize = 64;
+
+// Allocator handing out nodes from a few large slabs instead of one global
+// allocation each. A slab is never moved or resized once allocated, so the
+// pointers it hands out stay valid until they are freed or the arena is
+// dropped; each new slab is as large as all the previous ones together.
+pub(crate) struct Arena<N> {
+    // Start and capacity of each slab. Slots are only ever reached through
+    // these raw pointers, never through a reference to a whole slab, which
+    // would invalidate the pointers handed out into it.
+    slabs: Vec<(NonNull<N>, usize)>,
+    // Slots handed out from the last slab so far
+    used: usize,
+    // Slots given back by free, reused before the last slab is used further
+    free: Vec<NonNull<N>>,
+}
+
+// SAFETY: the arena owns its nodes as a Vec<N> would, and only hands out
+// access to them through &self and &mut self
+unsafe impl<N: Send> Send for Arena<N> {}
+unsafe impl<N: Sync> Sync for Arena<N> {}
+
+impl<N> Arena<N> {
+    // An arena whose first slab holds `slots` nodes
+    pub(crate) fn with_capacity(slots: usize) -> Arena<N> {
+        let mut arena = Arena {
+            slabs: Vec::new(),
+            used: 0,
+            free: Vec::new(),
+        };
+        arena.add_slab(slots.max(1));
+        arena
+    }
+
+    fn add_slab(&mut self, slots: usize) {
+        let mut slab = ManuallyDrop::new(Vec::<N>::with_capacity(slots));
+        // A Vec of zero-sized nodes reports usize::MAX, which is still
+        // fine as a slot count
+        let slots = slab.capacity();
+        self.slabs
+            .push((NonNull::new(slab.as_mut_ptr()).unwrap(), slots));
+        self.used = 0;
+    }
+
+    // Moves `node` into a free slot and returns a pointer to it
+    pub(crate) fn alloc(&mut self, node: N) -> NonNull<N> {
+        let slot = match self.free.pop() {
+            Some(slot) => slot,
+            None => {
+                let &(_, slots) = self.slabs.last().unwrap();
+                if self.used == slots {
+                    let total = self.slabs.iter().map(|&(_, slots)| slots).sum();
+                    self.add_slab(total);
+                }
+                let &(start, _) = self.slabs.last().unwrap();
+                self.used += 1;
+                // SAFETY: used - 1 is below the slab's capacity
+                unsafe { start.add(self.used - 1) }
+            }
+        };
+        // SAFETY: the slot is in a slab and holds no live node
+        unsafe { slot.write(node) };
+        slot
+    }
+
+    // Moves the node out of its slot, which is reused by later allocations
+    //
+    // SAFETY: `node` must come from this arena's alloc and not have been
+    // freed since, and no reference to the node may outlive the call
+    pub(crate) unsafe fn free(&mut self, node: NonNull<N>) -> N {
+        let node_value = node.read();
+        self.free.push(node);
+        node_value
+    }
+}
+
+impl<N> Drop for Arena<N> {
+    // The nodes still allocated are dropped first, running their (and so
+    // their values') destructors in slot order, which is allocation order
+    // but for freed slots reused, and only then are the slabs holding them
+    // deallocated. Nodes don't own each other, so no drop recurses, however
+    // deep the trie.
+    fn drop(&mut self) {
+        let free: HashSet<NonNull<N>> = self.free.drain(..).collect();
+        let last = self.slabs.len() - 1;
+        for (i, &(start, slots)) in self.slabs.iter().enumerate() {
+            let used = if i == last { self.used } else { slots };
+            for slot in 0..used {
+                // SAFETY: the slot was handed out by alloc and, not being
+                // on the free list, still holds a live node
+                unsafe {
+                    let slot = start.add(slot);
+                    if !free.contains(&slot) {
+                        slot.drop_in_place();
+                    }
+                }
+            }
+        }
+        for &(start, slots) in &self.slabs {
+            // SAFETY: the slab was allocated by a Vec with this capacity,
+            // and its nodes have all been dropped or moved out
+            drop(unsafe { Vec::from_raw_parts(start.as_ptr(), 0, slots) });
+        }
+    }
+}
+
+// Node of a SlabTrie. Children are pointers into the trie's arena, sorted
+// by char.
+struct SlabNode<T> {
+    key_char: char,
+    value: Option<T>,
+    children: Vec<(char, NonNull<SlabNode<T>>)>,
+}
+
+impl<T> SlabNode<T> {
+    fn new(key_char: char) -> SlabNode<T> {
+        SlabNode {
+            key_char,
+            value: None,
+            children: Vec::new(),
+        }
+    }
+
+    fn child_position(&self, key_char: char) -> Result<usize, usize> {
+        self.children.binary_search_by_key(&key_char, |(c, _)| *c)
+    }
+
+    fn child(&self, key_char: char) -> Option<NonNull<SlabNode<T>>> {
+        let i = self.child_position(key_char).ok()?;
+        Some(self.children[i].1)
+    }
+}
+
+/// Trie whose nodes are allocated from an arena of a few large slabs
+/// instead of one at a time, with plain pointers as child links. Building a
+/// large dictionary touches far fewer allocations, and nodes created
+/// together sit together in memory.
+///
+/// Unlike [`ArenaTrie`](crate::ArenaTrie), whose children are indices into
+/// one `Vec` that moves as it grows, the slabs never move: a child link is
+/// followed without a bounds check or an offset from the arena's start.
+/// Removed nodes' slots are reused by later inserts. Dropping the trie
+/// drops the values in the order their nodes were allocated, a reused slot
+/// taking the place of the node freed from it, and never recurses.
+pub struct SlabTrie<T> {
+    // Declared before root, which points into it, for readers; dropping a
+    // NonNull does nothing either way
+    arena: Arena<SlabNode<T>>,
+    root: NonNull<SlabNode<T>>,
+    len: usize,
+}
+
+// SAFETY: the trie owns all its nodes through the arena, and the pointers
+// never leave it
+unsafe impl<T: Send> Send for SlabTrie<T> {}
+unsafe impl<T: Sync> Sync for SlabTrie<T> {}
+
+impl<T> Default for SlabTrie<T> {
+    fn default() -> SlabTrie<T> {
+        SlabTrie::new()
+    }
+}
+
+impl<T> SlabTrie<T> {
+    /// Creates an empty trie.
+    pub fn new() -> SlabTrie<T> {
+        SlabTrie::with_capacity(FIRST_SLAB_SLOTS)
+    }
+
+    /// Creates an empty trie whose first slab has room for `nodes` nodes,
+    /// root included, e.g. a dictionary's node count from a previous build.
+    pub fn with_capacity(nodes: usize) -> SlabTrie<T> {
+        let mut arena = Arena::with_capacity(nodes);
+        let root = arena.alloc(SlabNode::new('\0'));
+        SlabTrie {
+            arena,
+            root,
+            len: 0,
+        }
+    }
+
+    // The node behind a pointer reached from the root
+    fn node(&self, node: NonNull<SlabNode<T>>) -> &SlabNode<T> {
+        // SAFETY: every pointer reachable from the root is to a live node
+        // in the arena, which lives as long as the trie
+        unsafe { node.as_ref() }
+    }
+
+    // Like node, but mutable. Only one node is borrowed at a time.
+    fn node_mut(&mut self, mut node: NonNull<SlabNode<T>>) -> &mut SlabNode<T> {
+        // SAFETY: as in node, and `&mut self` rules out other borrows
+        unsafe { node.as_mut() }
+    }
+
+    fn find(&self, key: &str) -> Option<NonNull<SlabNode<T>>> {
+        let mut current_node = self.root;
+        for c in key.chars() {
+            current_node = self.node(current_node).child(c)?;
+        }
+        Some(current_node)
+    }
+
+    /// Inserts `value` under `key`. Returns false, leaving the trie
+    /// untouched, if `key` is already stored.
+    pub fn insert(&mut self, key: &str, value: T) -> bool {
+        let mut current_node = self.root;
+        for c in key.chars() {
+            current_node = match self.node(current_node).child_position(c) {
+                Ok(i) => self.node(current_node).children[i].1,
+                Err(i) => {
+                    let child = self.arena.alloc(SlabNode::new(c));
+                    self.node_mut(current_node).children.insert(i, (c, child));
+                    child
+                }
+            };
+        }
+
+        let node = self.node_mut(current_node);
+        if node.value.is_some() {
+            return false;
+        }
+        node.value = Some(value);
+        self.len += 1;
+        true
+    }
+
+    /// Removes `key` and returns its value, freeing the nodes left without
+    /// values or children for reuse.
+    pub fn remove(&mut self, key: &str) -> Option<T> {
+        let mut path = vec![self.root];
+        for c in key.chars() {
+            path.push(self.node(*path.last().unwrap()).child(c)?);
+        }
+        let removed = self.node_mut(*path.last().unwrap()).value.take()?;
+        self.len -= 1;
+
+        while path.len() > 1 {
+            let node = path.pop().unwrap();
+            let node_ref = self.node(node);
+            if node_ref.value.is_some() || !node_ref.children.is_empty() {
+                break;
+            }
+            let key_char = node_ref.key_char;
+            let parent = self.node_mut(*path.last().unwrap());
+            let i = parent.child_position(key_char).unwrap();
+            parent.children.remove(i);
+            // SAFETY: the node was live and, just unlinked from its parent,
+            // is no longer reachable
+            drop(unsafe { self.arena.free(node) });
+        }
+        Some(removed)
+    }
+
+    /// The value stored under `key`, if any.
+    pub fn get(&self, key: &str) -> Option<&T> {
+        self.node(self.find(key)?).value.as_ref()
+    }
+
+    /// Returns true if `key` is stored in the trie.
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Number of stored keys.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if no key is stored.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Iterates over all entries in lexicographic key order.
+    pub fn iter(&self) -> SlabIter<'_, T> {
+        SlabIter {
+            trie: self,
+            stack: vec![(self.root, 0)],
+            key: String::new(),
+        }
+    }
+}
+
+impl<T: std::fmt::Debug> std::fmt::Debug for SlabTrie<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+/// Iterator over a [`SlabTrie`]'s entries in lexicographic key order.
+pub struct SlabIter<'a, T> {
+    trie: &'a SlabTrie<T>,
+    // Pre-order walk, sharing one key buffer between steps. Node, and the
+    // key length (in bytes) of its parent.
+    stack: Vec<(NonNull<SlabNode<T>>, usize)>,
+    key: String,
+}
+
+impl<'a, T> Iterator for SlabIter<'a, T> {
+    type Item = (String, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((node_ptr, parent_len)) = self.stack.pop() {
+            let node = self.trie.node(node_ptr);
+            self.key.truncate(parent_len);
+            if node_ptr != self.trie.root {
+                self.key.push(node.key_char);
+            }
+            for &(_, child) in node.children.iter().rev() {
+                self.stack.push((child, self.key.len()));
+            }
+            if let Some(value) = &node.value {
+                return Some((self.key.clone(), value));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::collections::BTreeMap;
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::test_util::XorShift64;
+
+    #[test]
+    fn insert_get_remove() {
+        let mut trie = SlabTrie::new();
+        for (i, key) in ["to", "tea", "ten", "i", "in", ""].iter().enumerate() {
+            assert!(trie.insert(key, i));
+        }
+        assert!(!trie.insert("tea", 9));
+        assert_eq!(trie.get("tea"), Some(&1));
+        assert_eq!(trie.get(""), Some(&5));
+        assert_eq!(trie.get("te"), None);
+        assert_eq!(trie.len(), 6);
+        assert_eq!(
+            trie.iter().map(|(key, _)| key).collect::<Vec<_>>(),
+            ["", "i", "in", "tea", "ten", "to"]
+        );
+
+        assert_eq!(trie.remove("te"), None);
+        assert_eq!(trie.remove("tea"), Some(1));
+        assert_eq!(trie.remove(""), Some(5));
+        assert_eq!(trie.remove("tea"), None);
+        assert!(trie.contains_key("ten"));
+        assert_eq!(trie.len(), 4);
+        // "tea"'s leaf went on the free list and is reused
+        assert_eq!(trie.arena.free.len(), 1);
+        assert!(trie.insert("tee", 6));
+        assert!(trie.arena.free.is_empty());
+    }
+
+    #[test]
+    fn grows_in_slabs() {
+        let mut trie = SlabTrie::with_capacity(4);
+        let first = trie.arena.slabs[0].0;
+        for i in 0..1000u32 {
+            trie.insert(&format!("{:03}", i), i);
+        }
+        // Earlier slabs stay where they were as more are added
+        assert_eq!(trie.arena.slabs[0].0, first);
+        assert!(trie.arena.slabs.len() < 12);
+        assert_eq!(trie.get("042"), Some(&42));
+        assert_eq!(trie.iter().count(), 1000);
+    }
+
+    #[test]
+    fn drop_order() {
+        // Records its id when dropped
+        struct Noisy(u32, Rc<RefCell<Vec<u32>>>);
+        impl Drop for Noisy {
+            fn drop(&mut self) {
+                self.1.borrow_mut().push(self.0);
+            }
+        }
+
+        let dropped = Rc::new(RefCell::new(Vec::new()));
+        let mut trie = SlabTrie::with_capacity(2);
+        for (key, id) in [("b", 0), ("a", 1), ("ab", 2), ("c", 3)] {
+            trie.insert(key, Noisy(id, Rc::clone(&dropped)));
+        }
+        assert!(!trie.insert("a", Noisy(4, Rc::clone(&dropped))));
+        assert_eq!(*dropped.borrow(), [4]);
+        drop(trie.remove("b"));
+        // Into the slot "b" freed
+        trie.insert("d", Noisy(5, Rc::clone(&dropped)));
+        drop(trie);
+        // Allocation order, with "d" in "b"'s old slot
+        assert_eq!(*dropped.borrow(), [4, 0, 5, 1, 2, 3]);
+    }
+
+    #[test]
+    fn differential() {
+        let mut rng = XorShift64(0x51ab_0000_0000_0097);
+        let mut trie = SlabTrie::new();
+        let mut model = BTreeMap::new();
+        for round in 0..5000 {
+            let key: String = (0..rng.below(5))
+                .map(|_| char::from(b'a' + rng.below(4) as u8))
+                .collect();
+            if rng.below(2) == 0 {
+                assert_eq!(trie.insert(&key, round), !model.contains_key(&key));
+                model.entry(key).or_insert(round);
+            } else {
+                assert_eq!(trie.remove(&key), model.remove(&key));
+            }
+        }
+        assert_eq!(trie.len(), model.len());
+        assert!(trie
+            .iter()
+            .map(|(key, value)| (key, *value))
+            .eq(model.into_iter()));
+    }
+
+    #[test]
+    fn deep_key() {
+        std::thread::spawn(|| {
+            let key = "a".repeat(1_000_000);
+            let mut trie = SlabTrie::new();
+            trie.insert(&key, 1);
+            trie.insert(&key[..10], 2);
+            assert_eq!(trie.iter().count(), 2);
+            assert_eq!(trie.remove(&key), Some(1));
+            trie.insert(&key, 3);
+        })
+        .join()
+        .unwrap();
+    }
+}
@@ -0,0 +1,362 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::BuildHasher;
+
+use crate::node::TrieNode;
+use crate::trie::Trie;
+
+/// Summary of a trie's shape, from [`Trie::stats`]. Its `Display` output
+/// is a one-line summary meant for logs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrieStats {
+    /// Number of stored keys, i.e. of nodes holding a value.
+    pub num_keys: usize,
+    /// Number of nodes, root included.
+    pub num_nodes: usize,
+    /// Depth of the deepest node, in chars; 0 for a trie with only a root.
+    pub max_depth: usize,
+    /// Mean depth of the nodes holding values, i.e. the mean key length in
+    /// chars; 0.0 for an empty trie.
+    pub avg_depth: f64,
+    /// Most children any single node has.
+    pub max_fanout: usize,
+    /// Number of nodes at each depth, indexed by depth: the first entry is
+    /// the root, and there are `max_depth + 1` entries.
+    pub depth_histogram: Vec<usize>,
+    /// Number of nodes with 0, 1, 2, and 3 or more children.
+    pub fanout_histogram: [usize; 4],
+    /// Number of nodes other than the root with one child and no value:
+    /// the nodes a radix trie would merge into the edge above them.
+    pub chain_nodes: usize,
+}
+
+impl fmt::Display for TrieStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [leaves, one, two, more] = self.fanout_histogram;
+        write!(
+            f,
+            "{} keys, {} nodes, depth max {} avg {:.2}, fanout max {} \
+             (0/1/2/3+: {}/{}/{}/{}), {} chain nodes, nodes per depth {:?}",
+            self.num_keys,
+            self.num_nodes,
+            self.max_depth,
+            self.avg_depth,
+            self.max_fanout,
+            leaves,
+            one,
+            two,
+            more,
+            self.chain_nodes,
+            self.depth_histogram
+        )
+    }
+}
+
+/// Estimated memory footprint of a trie, from [`Trie::memory_usage`].
+///
+/// The figures come from type sizes and container capacities, not from
+/// the allocator, so they are estimates: allocator overhead and rounding
+/// are missing, and a HashMap's table is sized by its known layout. They
+/// are consistent from one call to the next, which is what monitoring
+/// needs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryUsage {
+    /// Number of nodes, root included.
+    pub node_count: usize,
+    /// Bytes of the node structs themselves, and of the priorities boxed
+    /// alongside those that track any.
+    pub node_bytes: usize,
+    /// Bytes of the child containers beyond the nodes they hold: the key
+    /// chars next to them, spare capacity, and for wide nodes the boxed
+    /// `HashMap` and its table overhead. Spare capacity left by removals
+    /// shows up here until [`Trie::shrink_to_fit`].
+    pub children_bytes: usize,
+    /// Heap bytes owned by the values, as reported by the function given to
+    /// [`Trie::memory_usage_with`]; 0 from [`Trie::memory_usage`].
+    pub value_bytes: usize,
+}
+
+impl MemoryUsage {
+    /// The sum of all the parts.
+    pub fn total(&self) -> usize {
+        self.node_bytes + self.children_bytes + self.value_bytes
+    }
+}
+
+impl<T, S: BuildHasher + Default> Trie<T, S> {
+    /// Computes a [`TrieStats`] summary in a single walk over the trie.
+    pub fn stats(&self) -> TrieStats {
+        let mut stats = TrieStats {
+            num_keys: 0,
+            num_nodes: 0,
+            max_depth: 0,
+            avg_depth: 0.0,
+            max_fanout: 0,
+            depth_histogram: Vec::new(),
+            fanout_histogram: [0; 4],
+            chain_nodes: 0,
+        };
+        let mut total_depth = 0;
+        let mut stack = vec![(self.root(), 0)];
+        while let Some((node, depth)) = stack.pop() {
+            let fanout = node.child_count();
+            stats.num_nodes += 1;
+            stats.max_depth = stats.max_depth.max(depth);
+            stats.max_fanout = stats.max_fanout.max(fanout);
+            if stats.depth_histogram.len() <= depth {
+                stats.depth_histogram.resize(depth + 1, 0);
+            }
+            stats.depth_histogram[depth] += 1;
+            stats.fanout_histogram[fanout.min(3)] += 1;
+            if node.value().is_some() {
+                stats.num_keys += 1;
+                total_depth += depth;
+            } else if fanout == 1 && depth > 0 {
+                stats.chain_nodes += 1;
+            }
+            stack.extend(node.children().map(|(_, child)| (child, depth + 1)));
+        }
+
+        if stats.num_keys > 0 {
+            stats.avg_depth = total_depth as f64 / stats.num_keys as f64;
+        }
+        stats
+    }
+
+    /// Counts the nodes that hold a value and those that don't, in one
+    /// walk. The root is included, in the first count if the empty key is
+    /// stored and in the second otherwise, so the first is always the
+    /// number of keys and the two add up to the number of nodes. Many
+    /// valueless nodes, most with one child, suggest radix compression
+    /// would pay off.
+    pub fn valued_vs_internal(&self) -> (usize, usize) {
+        let (mut valued, mut internal) = (0, 0);
+        let mut stack = vec![self.root()];
+        while let Some(node) = stack.pop() {
+            match node.value() {
+                Some(_) => valued += 1,
+                None => internal += 1,
+            }
+            stack.extend(node.children().map(|(_, child)| child));
+        }
+        (valued, internal)
+    }
+
+    /// Counts the nodes under each char, i.e. how often each char labels an
+    /// edge, in one walk. The root has no char and isn't counted. The keys
+    /// are the trie's effective alphabet.
+    pub fn char_frequency(&self) -> HashMap<char, usize> {
+        let mut frequency = HashMap::new();
+        let mut stack = vec![self.root()];
+        while let Some(node) = stack.pop() {
+            for (c, child) in node.children() {
+                *frequency.entry(c).or_insert(0) += 1;
+                stack.push(child);
+            }
+        }
+        frequency
+    }
+
+    /// Estimates the trie's memory footprint, in one read-only walk. Heap
+    /// data owned by values isn't counted; see [`Trie::memory_usage_with`].
+    pub fn memory_usage(&self) -> MemoryUsage {
+        self.memory_usage_with(|_| 0)
+    }
+
+    /// [`Trie::memory_usage`], adding `value_bytes(value)` for every value,
+    /// e.g. `String::capacity` for `String` values.
+    pub fn memory_usage_with<F: Fn(&T) -> usize>(&self, value_bytes: F) -> MemoryUsage {
+        let node_size = std::mem::size_of::<TrieNode<T, S>>();
+        let mut usage = MemoryUsage {
+            node_count: 0,
+            node_bytes: 0,
+            children_bytes: 0,
+            value_bytes: 0,
+        };
+        let mut stack = vec![self.root()];
+        while let Some(node) = stack.pop() {
+            usage.node_count += 1;
+            usage.node_bytes += node_size + node.rank_heap_size();
+            // The children are counted as nodes already
+            usage.children_bytes += node.children_heap_size() - node.child_count() * node_size;
+            usage.value_bytes += node.value().map_or(0, &value_bytes);
+            stack.extend(node.children().map(|(_, child)| child));
+        }
+        usage
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats() {
+        let mut trie = Trie::<u32>::new();
+        assert_eq!(
+            trie.stats(),
+            TrieStats {
+                num_keys: 0,
+                num_nodes: 1,
+                max_depth: 0,
+                avg_depth: 0.0,
+                max_fanout: 0,
+                depth_histogram: vec![1],
+                fanout_histogram: [1, 0, 0, 0],
+                chain_nodes: 0,
+            }
+        );
+
+        for (i, key) in ["a", "aa", "aaa", "aaaa"].iter().enumerate() {
+            trie.insert(key, i as u32);
+        }
+        assert_eq!(
+            trie.stats(),
+            TrieStats {
+                num_keys: 4,
+                num_nodes: 5,
+                max_depth: 4,
+                avg_depth: 2.5,
+                max_fanout: 1,
+                depth_histogram: vec![1; 5],
+                fanout_histogram: [1, 4, 0, 0],
+                chain_nodes: 0,
+            }
+        );
+
+        trie.insert("b", 4);
+        trie.insert("ab", 5);
+        trie.insert("ac", 6);
+        let stats = trie.stats();
+        assert_eq!(stats.num_keys, 7);
+        assert_eq!(stats.num_nodes, 8);
+        assert_eq!(stats.max_fanout, 3);
+        assert_eq!(stats.avg_depth, 15.0 / 7.0);
+        assert_eq!(stats.depth_histogram, [1, 2, 3, 1, 1]);
+        assert_eq!(stats.fanout_histogram, [4, 2, 1, 1]);
+    }
+
+    #[test]
+    fn stats_shape() {
+        // root -> t -> {e -> {a, n}, o}, plus "inn" with "in" valueless
+        let mut trie = Trie::<u32>::new();
+        for (i, key) in ["tea", "ten", "to", "inn", "t"].iter().enumerate() {
+            trie.insert(key, i as u32);
+        }
+        let stats = trie.stats();
+        assert_eq!(
+            stats,
+            TrieStats {
+                num_keys: 5,
+                num_nodes: 9,
+                max_depth: 3,
+                avg_depth: 12.0 / 5.0,
+                max_fanout: 2,
+                depth_histogram: vec![1, 2, 3, 3],
+                fanout_histogram: [4, 2, 3, 0],
+                chain_nodes: 2,
+            }
+        );
+        assert_eq!(
+            stats.to_string(),
+            "5 keys, 9 nodes, depth max 3 avg 2.40, fanout max 2 (0/1/2/3+: 4/2/3/0), \
+             2 chain nodes, nodes per depth [1, 2, 3, 3]"
+        );
+
+        // One long key: a single chain
+        let mut chain = Trie::<u32>::new();
+        chain.insert(&"x".repeat(100), 0);
+        let stats = chain.stats();
+        assert_eq!((stats.num_nodes, stats.max_depth), (101, 100));
+        assert_eq!(stats.avg_depth, 100.0);
+        assert_eq!(stats.depth_histogram, vec![1; 101]);
+        assert_eq!(stats.fanout_histogram, [1, 100, 0, 0]);
+        assert_eq!(stats.chain_nodes, 99);
+
+        // One-char keys: all leaves under a wide root
+        let mut flat = Trie::<u32>::new();
+        for c in 'a'..='z' {
+            flat.insert(&c.to_string(), 0);
+        }
+        let stats = flat.stats();
+        assert_eq!((stats.num_keys, stats.num_nodes), (26, 27));
+        assert_eq!((stats.max_depth, stats.max_fanout), (1, 26));
+        assert_eq!(stats.avg_depth, 1.0);
+        assert_eq!(stats.depth_histogram, [1, 26]);
+        assert_eq!(stats.fanout_histogram, [26, 0, 0, 1]);
+        assert_eq!(stats.chain_nodes, 0);
+    }
+
+    #[test]
+    fn valued_vs_internal() {
+        let mut trie = Trie::<u32>::new();
+        assert_eq!(trie.valued_vs_internal(), (0, 1));
+        for (i, key) in ["a", "aa", "aaa", "aaaa"].iter().enumerate() {
+            trie.insert(key, i as u32);
+        }
+        // Only the root holds no value
+        assert_eq!(trie.valued_vs_internal(), (4, 1));
+
+        trie.insert("", 4);
+        trie.insert("bcd", 5);
+        assert_eq!(trie.valued_vs_internal(), (6, 2));
+        let stats = trie.stats();
+        let (valued, internal) = trie.valued_vs_internal();
+        assert_eq!(
+            (valued, valued + internal),
+            (stats.num_keys, stats.num_nodes)
+        );
+    }
+
+    #[test]
+    fn char_frequency() {
+        let mut trie = Trie::<u32>::new();
+        assert!(trie.char_frequency().is_empty());
+        for (i, key) in ["a", "aa", "aaa", "aaaa"].iter().enumerate() {
+            trie.insert(key, i as u32);
+        }
+        assert_eq!(trie.char_frequency(), HashMap::from([('a', 4)]));
+
+        // Shared prefixes are counted once
+        trie.insert("ab", 4);
+        trie.insert("abc", 5);
+        trie.insert("", 6);
+        assert_eq!(
+            trie.char_frequency(),
+            HashMap::from([('a', 4), ('b', 1), ('c', 1)])
+        );
+    }
+
+    #[test]
+    fn memory_usage() {
+        let mut trie = Trie::<String>::new();
+        let empty = trie.memory_usage();
+        assert_eq!(empty.node_count, 1);
+        assert_eq!(empty.children_bytes, 0);
+        assert_eq!(empty.total(), std::mem::size_of::<TrieNode<String>>());
+
+        // Grows with every insert, wide (HashMap) nodes included
+        let mut last = empty;
+        for c in ('a'..='z').chain('0'..='9') {
+            trie.insert(&format!("{}key", c), c.to_string().repeat(10));
+            let usage = trie.memory_usage();
+            assert!(usage.total() > last.total());
+            assert_eq!(usage.node_count, last.node_count + 4);
+            last = usage;
+        }
+        let with_values = trie.memory_usage_with(String::capacity);
+        assert_eq!(with_values.value_bytes, 36 * 10);
+        assert_eq!(with_values.total(), last.total() + 360);
+
+        // Shrinks with every removal, down to the keys that are left
+        for c in 'a'..='z' {
+            trie.remove(&format!("{}key", c));
+            let usage = trie.memory_usage();
+            assert!(usage.total() < last.total());
+            assert_eq!(usage.node_count, last.node_count - 4);
+            last = usage;
+        }
+        assert_eq!(last.node_count, 1 + 10 * 4);
+        assert_eq!(trie.get("5key").map(String::as_str), Some("5555555555"));
+    }
+}
@@ -0,0 +1,240 @@
+use std::sync::{PoisonError, RwLock};
+
+use crate::trie::Trie;
+
+// Enough shards that a handful of writers rarely meet on one
+const DEFAULT_SHARDS: usize = 16;
+
+/// A [`Trie`] that threads can share and update through `&self`, for
+/// lookup tables that are read far more often than written.
+///
+/// Keys are split across shards by their first char, each shard a `Trie`
+/// behind its own `RwLock`: any number of reads proceed in parallel, and a
+/// write only blocks the operations on its shard. Values are returned by
+/// clone, since a reference can't outlive the shard's lock; see
+/// [`ConcurrentTrie::get_with`] to look without cloning.
+///
+/// A thread panicking while it holds a shard's lock doesn't poison the trie
+/// for the others: every operation leaves a shard consistent before it can
+/// panic.
+#[derive(Debug)]
+pub struct ConcurrentTrie<T> {
+    shards: Vec<RwLock<Trie<T>>>,
+}
+
+impl<T> Default for ConcurrentTrie<T> {
+    fn default() -> ConcurrentTrie<T> {
+        ConcurrentTrie::new()
+    }
+}
+
+impl<T> ConcurrentTrie<T> {
+    /// Creates an empty trie with 16 shards.
+    pub fn new() -> ConcurrentTrie<T> {
+        ConcurrentTrie::with_shards(DEFAULT_SHARDS)
+    }
+
+    /// Creates an empty trie with `shards` shards; 1 gives one lock over a
+    /// single trie.
+    ///
+    /// # Panics
+    ///
+    /// If `shards` is 0.
+    pub fn with_shards(shards: usize) -> ConcurrentTrie<T> {
+        assert!(shards > 0, "a ConcurrentTrie needs at least one shard");
+        ConcurrentTrie {
+            shards: (0..shards).map(|_| RwLock::new(Trie::new())).collect(),
+        }
+    }
+
+    // The shard holding `key`, chosen by its first char. The empty key goes
+    // to the first shard.
+    fn shard(&self, key: &str) -> &RwLock<Trie<T>> {
+        let c = key.chars().next().map_or(0, u32::from);
+        &self.shards[c as usize % self.shards.len()]
+    }
+
+    /// Inserts `value` under `key`. Returns false, leaving the trie
+    /// untouched, if `key` is already stored.
+    pub fn insert(&self, key: &str, value: T) -> bool {
+        let mut shard = self
+            .shard(key)
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        shard.insert(key, value)
+    }
+
+    /// Removes `key`, returning whether it was removed.
+    pub fn remove(&self, key: &str) -> bool {
+        let mut shard = self
+            .shard(key)
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        shard.remove_if(key, |_| true).is_some()
+    }
+
+    /// Calls `f` on the value stored under `key`, if any, while holding the
+    /// shard's read lock, and returns its result.
+    pub fn get_with<R, F: FnOnce(&T) -> R>(&self, key: &str, f: F) -> Option<R> {
+        let shard = self
+            .shard(key)
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        shard.get(key).map(f)
+    }
+
+    /// Returns true if `key` is stored in the trie.
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.get_with(key, |_| ()).is_some()
+    }
+
+    /// Number of stored keys. Shards are counted one after the other, so
+    /// with concurrent writes the result may match no single moment.
+    pub fn len(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| shard.read().unwrap_or_else(PoisonError::into_inner).len())
+            .sum()
+    }
+
+    /// Returns true if no key is stored. Like [`ConcurrentTrie::len`], not
+    /// a snapshot under concurrent writes.
+    pub fn is_empty(&self) -> bool {
+        self.shards.iter().all(|shard| {
+            shard
+                .read()
+                .unwrap_or_else(PoisonError::into_inner)
+                .is_empty()
+        })
+    }
+
+    /// Joins the shards into one ordinary [`Trie`], without copying nodes.
+    pub fn into_trie(self) -> Trie<T> {
+        let mut trie = Trie::new();
+        for shard in self.shards {
+            // Shards never share a first char, and only one holds the empty
+            // key
+            trie.graft(shard.into_inner().unwrap_or_else(PoisonError::into_inner));
+        }
+        trie
+    }
+}
+
+impl<T: Clone> ConcurrentTrie<T> {
+    /// A clone of the value stored under `key`, if any.
+    pub fn get(&self, key: &str) -> Option<T> {
+        self.get_with(key, T::clone)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::thread;
+
+    use super::*;
+    use crate::test_util::XorShift64;
+
+    #[test]
+    fn shared_between_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<ConcurrentTrie<String>>();
+
+        let trie = ConcurrentTrie::with_shards(3);
+        assert!(trie.insert("tea", 1));
+        assert!(trie.insert("ten", 2));
+        assert!(trie.insert("", 0));
+        assert!(!trie.insert("tea", 3));
+        thread::scope(|scope| {
+            scope.spawn(|| assert!(trie.insert("inn", 4)));
+            scope.spawn(|| assert_eq!(trie.get("ten"), Some(2)));
+        });
+        assert_eq!(trie.get_with("tea", |value| value * 10), Some(10));
+        assert_eq!(trie.get("te"), None);
+        assert!(trie.contains_key(""));
+        assert_eq!(trie.len(), 4);
+        assert!(trie.remove("tea"));
+        assert!(!trie.remove("tea"));
+
+        let trie = trie.into_trie();
+        assert_eq!(
+            trie.iter().collect::<Vec<_>>(),
+            [
+                (String::new(), &0),
+                (String::from("inn"), &4),
+                (String::from("ten"), &2),
+            ]
+        );
+        assert!(ConcurrentTrie::<()>::new().is_empty());
+    }
+
+    #[test]
+    fn stress() {
+        const WRITERS: u64 = 4;
+        const READERS: u64 = 4;
+        const OPS: usize = 2000;
+
+        let trie = ConcurrentTrie::<String>::new();
+        // Every value spells its key, so readers can check what they see.
+        // Each writer owns the keys ending in its id, and returns what it
+        // left stored under them.
+        let random_key = |rng: &mut XorShift64, writer: u64| -> String {
+            let stem: String = (0..1 + rng.below(3))
+                .map(|_| char::from(b'a' + rng.below(20) as u8))
+                .collect();
+            format!("{}#{}", stem, writer)
+        };
+        let expected: Vec<HashMap<String, String>> = thread::scope(|scope| {
+            for reader in 0..READERS {
+                let trie = &trie;
+                scope.spawn(move || {
+                    let mut rng = XorShift64(0x5eed_0000_0000_0070 + reader);
+                    for i in 0..OPS {
+                        let writer = rng.below(WRITERS as usize) as u64;
+                        let key = random_key(&mut rng, writer);
+                        if let Some(value) = trie.get(&key) {
+                            assert!(value.starts_with(&key), "{} under {}", value, key);
+                        }
+                        if i % 100 == 0 {
+                            assert!(trie.len() <= OPS * WRITERS as usize);
+                        }
+                    }
+                });
+            }
+            let writers: Vec<_> = (0..WRITERS)
+                .map(|writer| {
+                    let trie = &trie;
+                    scope.spawn(move || {
+                        let mut rng = XorShift64(0x5eed_0000_0000_0170 + writer);
+                        let mut stored = HashMap::new();
+                        for i in 0..OPS {
+                            let key = random_key(&mut rng, writer);
+                            if rng.below(3) == 0 {
+                                assert_eq!(trie.remove(&key), stored.remove(&key).is_some());
+                            } else {
+                                let value = format!("{}={}", key, i);
+                                let inserted = trie.insert(&key, value.clone());
+                                assert_eq!(inserted, !stored.contains_key(&key));
+                                stored.entry(key).or_insert(value);
+                            }
+                        }
+                        stored
+                    })
+                })
+                .collect();
+            writers
+                .into_iter()
+                .map(|writer| writer.join().unwrap())
+                .collect()
+        });
+
+        let expected: HashMap<String, String> = expected.into_iter().flatten().collect();
+        assert_eq!(trie.len(), expected.len());
+        for (key, value) in &expected {
+            assert_eq!(trie.get(key).as_ref(), Some(value));
+        }
+        let trie = trie.into_trie();
+        assert_eq!(trie.len(), expected.len());
+        assert!(trie.iter().all(|(key, value)| expected[&key] == *value));
+    }
+}
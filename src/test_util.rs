@@ -0,0 +1,41 @@
+use crate::node::GenericNode;
+
+// Small deterministic PRNG for the randomized tests
+pub(crate) struct XorShift64(pub(crate) u64);
+
+impl XorShift64 {
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.0 = x;
+        x
+    }
+
+    pub(crate) fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+// Checks that every node under `node` tracks the highest priority in its
+// subtree: its own or a child's, whichever is higher. That holding node by
+// node makes it hold for whole subtrees.
+pub(crate) fn check_max_priorities<K, T, S, P>(node: &GenericNode<K, T, S, P>)
+where
+    P: PartialOrd + std::fmt::Debug,
+{
+    let mut stack = vec![node];
+    while let Some(node) = stack.pop() {
+        let mut max = node.priority();
+        for child in node.child_nodes() {
+            if let Some(child_max) = child.max_priority() {
+                if max.is_none_or(|max| child_max > max) {
+                    max = Some(child_max);
+                }
+            }
+            stack.push(child);
+        }
+        assert_eq!(node.max_priority(), max, "stale maximum priority");
+    }
+}
@@ -0,0 +1,277 @@
+use crate::error::TrieError;
+use crate::trie::Trie;
+
+/// One piece of a text cut up by [`Trie::segment`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Segment<'t, V> {
+    /// A stored key, with its value.
+    Word {
+        text: &'t str,
+        start: usize,
+        value: V,
+    },
+    /// A single char at which no stored key matches: either no key starts
+    /// with it, or every key that does runs on past what the text holds
+    /// there.
+    Unknown { text: &'t str, start: usize },
+}
+
+impl<'t, V> Segment<'t, V> {
+    /// The segment's slice of the text.
+    pub fn text(&self) -> &'t str {
+        match self {
+            Segment::Word { text, .. } | Segment::Unknown { text, .. } => text,
+        }
+    }
+
+    /// Byte offset in the text where the segment starts.
+    pub fn start(&self) -> usize {
+        match self {
+            Segment::Word { start, .. } | Segment::Unknown { start, .. } => *start,
+        }
+    }
+
+    /// Byte offset in the text just past the segment.
+    pub fn end(&self) -> usize {
+        self.start() + self.text().len()
+    }
+}
+
+impl<T> Trie<T> {
+    // The longest non-empty key `text` starts with, as its byte length and
+    // value
+    fn longest_key_at(&self, text: &str) -> Option<(usize, &T)> {
+        let mut best = None;
+        let mut current_node = self.root();
+        for (i, c) in text.char_indices() {
+            current_node = match current_node.child(c) {
+                Some(child) => child,
+                None => break,
+            };
+            if let Some(value) = current_node.value() {
+                best = Some((i + c.len_utf8(), value));
+            }
+        }
+        best
+    }
+
+    /// Cuts `text` into stored keys by greedy longest match, e.g. to split
+    /// text without delimiters into dictionary words: at each position the
+    /// longest key there is taken. A char where no key matches becomes an
+    /// [`Segment::Unknown`] of its own, even if it starts a key that the
+    /// text goes on to break off, so the segments always cover the whole
+    /// text, in order.
+    ///
+    /// Greedy isn't optimal: with "in", "inside" and "sidewalk" stored,
+    /// "insidewalk" is "inside" followed by unknown chars. Keys are matched
+    /// as stored, and the empty key is ignored.
+    pub fn segment<'t>(&self, text: &'t str) -> Vec<Segment<'t, &T>> {
+        let mut segments = Vec::new();
+        let mut start = 0;
+        while let Some(c) = text[start..].chars().next() {
+            let segment = match self.longest_key_at(&text[start..]) {
+                Some((len, value)) => Segment::Word {
+                    text: &text[start..start + len],
+                    start,
+                    value,
+                },
+                None => Segment::Unknown {
+                    text: &text[start..start + c.len_utf8()],
+                    start,
+                },
+            };
+            start = segment.end();
+            segments.push(segment);
+        }
+        segments
+    }
+
+    /// [`Trie::segment`] for callers that need every char covered by a
+    /// key: all segments are [`Segment::Word`]s.
+    ///
+    /// # Errors
+    ///
+    /// [`TrieError::UnmatchedChar`] with the byte offset of the first char
+    /// where no key matches, whether no key starts with it or only keys
+    /// the text doesn't complete.
+    pub fn segment_strict<'t>(&self, text: &'t str) -> Result<Vec<Segment<'t, &T>>, TrieError> {
+        let mut segments = Vec::new();
+        let mut start = 0;
+        while start < text.len() {
+            let (len, value) = self
+                .longest_key_at(&text[start..])
+                .ok_or(TrieError::UnmatchedChar { offset: start })?;
+            segments.push(Segment::Word {
+                text: &text[start..start + len],
+                start,
+                value,
+            });
+            start += len;
+        }
+        Ok(segments)
+    }
+
+    /// Copies `text`, replacing key occurrences with `f(key, value)`.
+    /// Matching is leftmost-longest, scanning from the left: with "ab" and
+    /// "abc" stored, "abcd" becomes f("abc") followed by "d". Matches don't
+    /// overlap, replacements aren't scanned again, and text outside matches
+    /// is copied through. Keys are matched as stored, and the empty key is
+    /// ignored.
+    pub fn replace_all_with<F>(&self, text: &str, f: F) -> String
+    where
+        F: Fn(&str, &T) -> String,
+    {
+        let mut replaced = String::with_capacity(text.len());
+        let mut start = 0;
+        while let Some(c) = text[start..].chars().next() {
+            match self.longest_key_at(&text[start..]) {
+                Some((len, value)) => {
+                    replaced.push_str(&f(&text[start..start + len], value));
+                    start += len;
+                }
+                None => {
+                    replaced.push(c);
+                    start += c.len_utf8();
+                }
+            }
+        }
+        replaced
+    }
+}
+
+impl<T: AsRef<str>> Trie<T> {
+    /// Copies `text` with every key occurrence replaced by its value, as a
+    /// replacement table: with "colour" mapped to "color", "colours"
+    /// becomes "colors". Matching is as in [`Trie::replace_all_with`].
+    pub fn replace_all(&self, text: &str) -> String {
+        self.replace_all_with(text, |_, value| String::from(value.as_ref()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dictionary(words: &[&str]) -> Trie<usize> {
+        let mut trie = Trie::new();
+        for (i, word) in words.iter().enumerate() {
+            trie.insert(word, i);
+        }
+        trie
+    }
+
+    fn texts<'t>(segments: &[Segment<'t, &usize>]) -> Vec<&'t str> {
+        segments.iter().map(Segment::text).collect()
+    }
+
+    #[test]
+    fn longest_match_wins() {
+        let trie = dictionary(&["in", "inside", "side", "out", ""]);
+        let segments = trie.segment("insideoutin");
+        assert_eq!(texts(&segments), ["inside", "out", "in"]);
+        assert_eq!(
+            segments[0],
+            Segment::Word {
+                text: "inside",
+                start: 0,
+                value: &1
+            }
+        );
+        assert_eq!(segments[2].start(), 9);
+
+        // "ins" leads nowhere, so "in" is taken
+        assert_eq!(texts(&trie.segment("insx")), ["in", "s", "x"]);
+        assert_eq!(
+            trie.segment("insx")[1],
+            Segment::Unknown {
+                text: "s",
+                start: 2
+            }
+        );
+        assert!(trie.segment("").is_empty());
+    }
+
+    #[test]
+    fn covers_multi_byte_text() {
+        let trie = dictionary(&["北京", "北京大学", "大学", "生"]);
+        let text = "北京大学生在北京";
+        let segments = trie.segment(text);
+        assert_eq!(texts(&segments), ["北京大学", "生", "在", "北京"]);
+        assert!(matches!(segments[2], Segment::Unknown { start: 15, .. }));
+        // The segments tile the text
+        let mut end = 0;
+        for segment in &segments {
+            assert_eq!(segment.start(), end);
+            assert_eq!(&text[segment.start()..segment.end()], segment.text());
+            end = segment.end();
+        }
+        assert_eq!(end, text.len());
+    }
+
+    #[test]
+    fn replace_all() {
+        let mut table = Trie::new();
+        for (key, value) in [
+            ("ab", "1"),
+            ("abc", "2"),
+            ("colour", "color"),
+            (":smile:", "🙂"),
+            ("ü", "ue"),
+            ("x", ""),
+        ] {
+            table.insert(key, String::from(value));
+        }
+        // Leftmost-longest
+        assert_eq!(table.replace_all("abcd"), "2d");
+        assert_eq!(table.replace_all("abd"), "1d");
+        // Adjacent matches, and one at the very end
+        assert_eq!(table.replace_all("ababc"), "12");
+        assert_eq!(table.replace_all("a colour"), "a color");
+        // Replacements aren't rescanned, even when they spell a key
+        assert_eq!(table.replace_all(":smile:abc"), "🙂2");
+        table.insert("🙂", String::from("smile"));
+        assert_eq!(table.replace_all(":smile:"), "🙂");
+        // Empty replacements, multi-byte text around and inside matches
+        assert_eq!(table.replace_all("xüxéx"), "ueé");
+        assert_eq!(table.replace_all("für"), "fuer");
+        assert_eq!(table.replace_all(""), "");
+        assert_eq!(table.replace_all("nothing"), "nothing");
+    }
+
+    #[test]
+    fn replace_all_with() {
+        let trie = dictionary(&["one", "two", "twofold"]);
+        assert_eq!(
+            trie.replace_all_with("one, two, twofold, three", |key, value| {
+                format!("<{}:{}>", key, value)
+            }),
+            "<one:0>, <two:1>, <twofold:2>, three"
+        );
+    }
+
+    #[test]
+    fn strict() {
+        let trie = dictionary(&["in", "inside", "out"]);
+        let segments = trie.segment_strict("insideout").unwrap();
+        assert_eq!(texts(&segments), ["inside", "out"]);
+        assert_eq!(trie.segment_strict(""), Ok(Vec::new()));
+        assert_eq!(
+            trie.segment_strict("outéin"),
+            Err(TrieError::UnmatchedChar { offset: 3 })
+        );
+        // 'o' starts "out", but the text breaks it off
+        assert_eq!(
+            trie.segment_strict("inoin"),
+            Err(TrieError::UnmatchedChar { offset: 2 })
+        );
+        assert_eq!(texts(&trie.segment("inoin")), ["in", "o", "in"]);
+        assert!(matches!(
+            trie.segment("inou")[1],
+            Segment::Unknown { start: 2, .. }
+        ));
+        assert_eq!(
+            TrieError::UnmatchedChar { offset: 3 }.to_string(),
+            "no key matches at byte 3"
+        );
+    }
+}
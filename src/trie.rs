@@ -0,0 +1,3821 @@
+use std::borrow::Cow;
+use std::collections::hash_map::RandomState;
+use std::collections::{BTreeMap, HashMap};
+use std::convert::Infallible;
+use std::fmt;
+use std::hash::{BuildHasher, Hash};
+use std::io::{self, BufRead};
+
+use crate::error::TrieError;
+use crate::generic::GenericTrie;
+use crate::iter::{Iter, IterMut};
+use crate::node::{TrieNode, CHILD_SLOT_OVERHEAD};
+
+/// A trie mapping string keys to values of type `T`, with one node per
+/// char. It wraps a [`GenericTrie`] keyed on chars.
+///
+/// Keys can optionally be normalized before every operation, see
+/// [`Trie::new_with_normalizer`]. The empty key is stored on the root node.
+///
+/// For ranked autocomplete, values can be inserted with a priority, e.g. a
+/// query's popularity, see [`Trie::insert_with_priority`], and
+/// [`Trie::top_k_by_prefix`] finds the highest-priority keys under a
+/// prefix without visiting the rest.
+///
+/// Nodes with many children keep them in a `HashMap` built with `S`, std's
+/// SipHash-based `RandomState` by default. Any `BuildHasher + Default` can
+/// replace it, e.g. a faster one for hot lookups on wide nodes: create such
+/// a trie with [`Trie::default`]. The methods defined here work with any
+/// hasher; a few specialized ones, such as [`Trie::build_matcher`], need
+/// the default one.
+pub struct Trie<T, S = RandomState> {
+    inner: GenericTrie<char, T, S>,
+    normalizer: Option<fn(&str) -> String>,
+    // Number of stored keys, updated by every method that adds or removes
+    // one, so that len is O(1)
+    count: usize,
+}
+
+impl<T> Trie<T> {
+    /// Creates an empty trie. For a custom hasher, use [`Trie::default`].
+    pub fn new() -> Trie<T> {
+        Trie::default()
+    }
+
+    /// Creates an empty trie that passes every key through `normalizer`
+    /// before traversal, so e.g. differently normalized Unicode inputs reach
+    /// the same node.
+    pub fn new_with_normalizer(normalizer: fn(&str) -> String) -> Trie<T> {
+        Trie {
+            inner: GenericTrie::new(),
+            normalizer: Some(normalizer),
+            count: 0,
+        }
+    }
+
+    /// Builds a trie from a list of keys, one per line, storing
+    /// `value_fn(key)` under each. Lines are read one at a time into a
+    /// single buffer, so the input can be far larger than memory; only the
+    /// trie has to fit. Line endings (`\n` or `\r\n`) aren't part of the
+    /// keys, empty lines are skipped, and a repeated key keeps its first
+    /// value, as with [`Trie::insert`].
+    ///
+    /// # Errors
+    ///
+    /// Any error reading from `reader`, including
+    /// [`io::ErrorKind::InvalidData`] for a line that isn't UTF-8.
+    pub fn from_reader<R, F>(mut reader: R, value_fn: F) -> io::Result<Trie<T>>
+    where
+        R: BufRead,
+        F: Fn(&str) -> T,
+    {
+        let mut trie = Trie::new();
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 {
+                return Ok(trie);
+            }
+            let key = line.strip_suffix('\n').unwrap_or(&line);
+            let key = key.strip_suffix('\r').unwrap_or(key);
+            if !key.is_empty() {
+                trie.insert(key, value_fn(key));
+            }
+        }
+    }
+}
+
+impl<T, S: BuildHasher + Default> Trie<T, S> {
+    // Trie without a normalizer around an already built root
+    pub(crate) fn from_root(root: TrieNode<T, S>) -> Trie<T, S> {
+        Trie {
+            count: root.count_values(),
+            inner: GenericTrie { root },
+            normalizer: None,
+        }
+    }
+
+    /// The root node, for read-only traversal. It holds no key char and no
+    /// value.
+    pub fn root(&self) -> &TrieNode<T, S> {
+        &self.inner.root
+    }
+
+    pub(crate) fn root_mut(&mut self) -> &mut TrieNode<T, S> {
+        &mut self.inner.root
+    }
+
+    // The root along with the key count, which whoever adds or removes
+    // values through the root must keep up to date
+    pub(crate) fn root_and_count_mut(&mut self) -> (&mut TrieNode<T, S>, &mut usize) {
+        (&mut self.inner.root, &mut self.count)
+    }
+
+    // Moves `other`'s entries into the trie without copying nodes. The
+    // tries must have no first char in common, and at most one may hold
+    // the empty key.
+    pub(crate) fn graft(&mut self, mut other: Trie<T, S>) {
+        let root = &mut self.inner.root;
+        let other_root = &mut other.inner.root;
+        if let Some((value, priority)) = other_root.take_entry() {
+            assert!(root.value().is_none(), "both tries hold the empty key");
+            root.set_value(value);
+            root.set_priority(priority);
+        }
+        let child_chars: Vec<char> = other_root.child_chars().collect();
+        for c in child_chars {
+            let child = other_root.remove_child(c).unwrap();
+            root.insert_child(c, child)
+                .expect("both tries have keys starting with the same char");
+        }
+        self.count += other.count;
+    }
+
+    pub(crate) fn normalizer(&self) -> Option<fn(&str) -> String> {
+        self.normalizer
+    }
+
+    pub(crate) fn normalize<'k>(&self, key: &'k str) -> Cow<'k, str> {
+        match self.normalizer {
+            Some(normalizer) => Cow::Owned(normalizer(key)),
+            None => Cow::Borrowed(key),
+        }
+    }
+
+    // Normalizes a key given as chars. Without a normalizer they pass
+    // through untouched; a normalizer needs them collected into a string.
+    fn normalize_chars<I: IntoIterator<Item = char>>(&self, key: I) -> KeyChars<I::IntoIter> {
+        match self.normalizer {
+            Some(normalizer) => {
+                KeyChars::Normalized(normalizer(&key.into_iter().collect::<String>()), 0)
+            }
+            None => KeyChars::Raw(key.into_iter()),
+        }
+    }
+
+    /// Inserts `value` under `key`. Returns false, leaving the trie
+    /// untouched, if `key` is already stored.
+    pub fn insert(&mut self, key: &str, value: T) -> bool {
+        self.try_insert(key, value).is_ok()
+    }
+
+    /// [`Trie::insert`] with the key given as chars, e.g. straight from an
+    /// iterator pipeline. The iterator is consumed once; it is collected
+    /// into a string only if the trie has a normalizer.
+    pub fn insert_iter<I: IntoIterator<Item = char>>(&mut self, key: I, value: T) -> bool {
+        let key = self.normalize_chars(key);
+        self.try_insert_normalized(key, value, None).is_ok()
+    }
+
+    /// Inserts `value` under `key`, leaving the trie untouched on error.
+    ///
+    /// # Errors
+    ///
+    /// [`TrieError::DuplicateKey`] if `key` already has a value.
+    pub fn try_insert(&mut self, key: &str, value: T) -> Result<(), TrieError> {
+        let key = &self.normalize(key);
+        self.try_insert_normalized(key.chars(), value, None)
+    }
+
+    /// Inserts `value` under `key` with `priority`, for
+    /// [`Trie::top_k_by_prefix`] to rank it by. Returns false, leaving the
+    /// trie untouched, if `key` is already stored.
+    ///
+    /// The priority stays with the value until it is removed, through
+    /// merges, [`Trie::map_values`] and copies alike. Serialization and
+    /// conversions to the other trie types drop it.
+    ///
+    /// # Panics
+    ///
+    /// If `priority` is NaN, which has no place in a ranking.
+    pub fn insert_with_priority(&mut self, key: &str, value: T, priority: f64) -> bool {
+        assert!(!priority.is_nan(), "priority is NaN");
+        let key = &self.normalize(key);
+        self.try_insert_normalized(key.chars(), value, Some(priority))
+            .is_ok()
+    }
+
+    // The one insert path for keys given as normalized chars, keeping the
+    // key count
+    fn try_insert_normalized<I>(
+        &mut self,
+        key: I,
+        value: T,
+        priority: Option<f64>,
+    ) -> Result<(), TrieError>
+    where
+        I: IntoIterator<Item = char>,
+    {
+        self.inner.try_insert_entry(key, value, priority)?;
+        self.count += 1;
+        Ok(())
+    }
+
+    /// Inserts `value` under `key`, or if `key` is already stored, replaces
+    /// its value with `merge(existing, value)`, e.g. to add up counts or
+    /// concatenate lists. This is `HashMap`'s
+    /// `entry(key).and_modify(..).or_insert(..)` in one call, with the new
+    /// value at hand in both cases. Returns true if a merge happened, false
+    /// for a plain insert.
+    pub fn insert_with<F: FnOnce(T, T) -> T>(&mut self, key: &str, value: T, merge: F) -> bool {
+        let key = &self.normalize(key);
+        let merged = self.inner.insert_with(key.chars(), value, merge);
+        if !merged {
+            self.count += 1;
+        }
+        merged
+    }
+
+    /// Removes `key`, returning whether it was removed. Nodes left without
+    /// values or children are pruned.
+    ///
+    /// Unlike every other removal in the crate, this leaves a key in place
+    /// when other stored keys extend it, and returns false. The empty key is
+    /// the exception, since clearing the root takes no other key along.
+    /// [`Trie::remove_if`] removes any stored key, as [`GenericTrie::remove`]
+    /// and the other tries' `remove` do.
+    pub fn remove(&mut self, key: &str) -> bool {
+        let key = &self.normalize(key);
+        self.remove_leaf_normalized(key.chars())
+    }
+
+    /// [`Trie::remove`] with the key given as chars; see
+    /// [`Trie::insert_iter`].
+    pub fn remove_iter<I: IntoIterator<Item = char>>(&mut self, key: I) -> bool {
+        let key = self.normalize_chars(key);
+        self.remove_leaf_normalized(key)
+    }
+
+    fn remove_leaf_normalized<I: IntoIterator<Item = char>>(&mut self, key: I) -> bool {
+        let mut key = key.into_iter().peekable();
+        let is_empty = key.peek().is_none();
+        let removed = self.inner.remove_node_if(key, |node| {
+            node.value().is_some() && (is_empty || !node.has_children())
+        });
+        if removed.is_some() {
+            self.count -= 1;
+        }
+        removed.is_some()
+    }
+
+    /// Removes `key` and returns its value, but only if `pred` returns true
+    /// for it, e.g. to delete an entry only while it still has the version
+    /// the caller last saw. Otherwise the trie is left untouched and `None`
+    /// is returned. Nodes are pruned as with [`Trie::remove`], and only if
+    /// the value was removed.
+    pub fn remove_if<F: FnOnce(&T) -> bool>(&mut self, key: &str, pred: F) -> Option<T> {
+        let key = &self.normalize(key);
+        self.remove_if_normalized(key.chars(), pred)
+    }
+
+    // The one removal path for keys given as normalized chars, keeping the
+    // key count
+    fn remove_if_normalized<I, F>(&mut self, key: I, pred: F) -> Option<T>
+    where
+        I: IntoIterator<Item = char>,
+        F: FnOnce(&T) -> bool,
+    {
+        let removed = self.inner.remove_if(key, pred);
+        if removed.is_some() {
+            self.count -= 1;
+        }
+        removed
+    }
+
+    /// Removes `key`'s value, returning whether there was one, but leaves
+    /// its nodes in place instead of pruning them. In a run of many
+    /// removals, e.g. expiring most of a cache, that skips restructuring
+    /// the trie every time; [`Trie::compact`] prunes all the leftover
+    /// nodes, the tombstones, in one sweep later. Until then they take up
+    /// memory, and walks such as iteration pass through them.
+    pub fn remove_lazy(&mut self, key: &str) -> bool {
+        let key = &self.normalize(key);
+        let removed = self
+            .inner
+            .root
+            .update_at(key.chars(), false, |node| node.take_value().is_some())
+            .unwrap_or(false);
+        if removed {
+            self.count -= 1;
+        }
+        removed
+    }
+
+    /// Prunes the nodes left behind by [`Trie::remove_lazy`], the
+    /// tombstones: every node whose subtree holds no value, except the root.
+    pub fn compact(&mut self) {
+        // Iterative, for any key length. A tombstone's whole subtree is
+        // tombstones, so detaching each dead subtree at its top on the way
+        // down from the root prunes the same nodes as a bottom-up sweep of
+        // valueless, childless nodes would, and the kept counts stay right
+        let mut stack = vec![&mut self.inner.root];
+        while let Some(node) = stack.pop() {
+            let dead: Vec<char> = node
+                .children()
+                .filter(|(_, child)| !child.leads_to_value())
+                .map(|(c, _)| c)
+                .collect();
+            for c in dead {
+                node.remove_child(c);
+            }
+            stack.extend(node.split_mut().1);
+        }
+    }
+
+    /// Fraction of the nodes that [`Trie::compact`] would prune, from 0.0
+    /// for a compact trie to just under 1.0, e.g. to decide when compacting
+    /// is worth it. The root counts as a node but is never pruned. Only
+    /// [`Trie::remove_lazy`] leaves tombstones; every other removal prunes
+    /// the nodes it empties.
+    pub fn tombstone_ratio(&self) -> f64 {
+        let (mut nodes, mut tombstones) = (1, 0);
+        let mut stack: Vec<&TrieNode<T, S>> = self.inner.root.child_nodes().collect();
+        while let Some(node) = stack.pop() {
+            nodes += 1;
+            tombstones += usize::from(!node.leads_to_value());
+            stack.extend(node.child_nodes());
+        }
+        tombstones as f64 / nodes as f64
+    }
+
+    /// Removes every entry for which `f` returns true, returning how many
+    /// were removed. `f` sees the entries in lexicographic key order. Nodes
+    /// left without values or children are pruned, as with [`Trie::remove`].
+    pub fn remove_where<F>(&mut self, mut f: F) -> usize
+    where
+        F: FnMut(&str, &T) -> bool,
+    {
+        // One walk over the trie instead of a separate descent from the
+        // root per removed key
+        let mut removed = 0;
+        let root = std::mem::replace(&mut self.inner.root, TrieNode::new(char::default(), None));
+        let rebuilt = Self::rebuild(root, true, |key, value| {
+            if f(key, &value) {
+                removed += 1;
+                Ok::<_, Infallible>(None)
+            } else {
+                Ok(Some(value))
+            }
+        });
+        self.inner.root = match rebuilt {
+            Ok(root) => root,
+            Err((_, never)) => match never {},
+        };
+        self.count -= removed;
+        removed
+    }
+
+    /// Keeps only the keys starting with at least one of `prefixes`,
+    /// removing all others and pruning nodes left without values or
+    /// children. No prefixes at all leaves the trie empty; the empty prefix
+    /// keeps everything.
+    pub fn retain_prefixes(&mut self, prefixes: &[&str]) {
+        let mut kept = Trie::new();
+        for prefix in prefixes {
+            kept.insert(&self.normalize(prefix), ());
+        }
+        self.count -= Self::retain_prefixes_helper(&mut self.inner.root, kept.root());
+    }
+
+    // Walk `node` in lockstep with the trie of prefixes, keeping whole the
+    // subtrees a prefix ends at and dropping those no prefix leads into.
+    // Returns the number of values dropped.
+    fn retain_prefixes_helper(node: &mut TrieNode<T, S>, kept: &TrieNode<()>) -> usize {
+        if kept.value().is_some() {
+            return 0;
+        }
+
+        let mut removed = usize::from(node.take_value().is_some());
+        let child_chars: Vec<char> = node.children().map(|(c, _)| c).collect();
+        for c in child_chars {
+            match kept.child(c) {
+                Some(kept_child) => {
+                    let child = node.child_mut(c).unwrap();
+                    let child_removed = Self::retain_prefixes_helper(child, kept_child);
+                    if child.value().is_none() && !child.has_children() {
+                        node.remove_child(c);
+                    }
+                    node.descendants_changed(0, child_removed);
+                    removed += child_removed;
+                }
+                None => {
+                    removed += node.remove_child(c).map_or(0, |child| child.count_values());
+                }
+            }
+        }
+        removed
+    }
+
+    /// The value stored under `key`, if any.
+    pub fn get(&self, key: &str) -> Option<&T> {
+        let key = &self.normalize(key);
+        self.inner.get(key.chars())
+    }
+
+    /// The priority `key`'s value was inserted with, if it is stored with
+    /// one; see [`Trie::insert_with_priority`].
+    pub fn priority(&self, key: &str) -> Option<f64> {
+        let key = &self.normalize(key);
+        self.inner.priority(key.chars()).copied()
+    }
+
+    /// [`Trie::get`] with the key given as chars; see
+    /// [`Trie::insert_iter`].
+    pub fn get_iter<I: IntoIterator<Item = char>>(&self, key: I) -> Option<&T> {
+        self.inner.get(self.normalize_chars(key))
+    }
+
+    /// [`Trie::contains_key`] with the key given as chars; see
+    /// [`Trie::insert_iter`].
+    pub fn contains_iter<I: IntoIterator<Item = char>>(&self, key: I) -> bool {
+        self.get_iter(key).is_some()
+    }
+
+    /// The values stored under each of `keys`, in the same order, as from
+    /// [`Trie::get`] per key.
+    ///
+    /// The keys are looked up in sorted order, keeping the path to the
+    /// previous key: each lookup only walks down from where it diverges
+    /// from the previous one, so keys sharing long prefixes are much
+    /// cheaper than separate lookups from the root. Sorting costs
+    /// O(k log k) for k keys.
+    pub fn get_many(&self, keys: &[&str]) -> Vec<Option<&T>> {
+        let keys: Vec<Cow<'_, str>> = keys.iter().map(|key| self.normalize(key)).collect();
+        let mut order: Vec<usize> = (0..keys.len()).collect();
+        order.sort_unstable_by(|&a, &b| keys[a].cmp(&keys[b]));
+
+        let mut values = vec![None; keys.len()];
+        // Nodes on the path to the previous key as far as it exists, the
+        // root first, and the chars leading to them
+        let mut path = vec![&self.inner.root];
+        let mut path_key = String::new();
+        for i in order {
+            let key = &keys[i];
+            let shared = path_key
+                .chars()
+                .zip(key.chars())
+                .take_while(|(a, b)| a == b)
+                .count();
+            path.truncate(shared + 1);
+            let shared_len = path_key
+                .char_indices()
+                .nth(shared)
+                .map_or(path_key.len(), |(i, _)| i);
+            path_key.truncate(shared_len);
+
+            let mut current_node = *path.last().unwrap();
+            let mut found = true;
+            for c in key[shared_len..].chars() {
+                match current_node.child(c) {
+                    Some(node) => {
+                        current_node = node;
+                        path.push(node);
+                        path_key.push(c);
+                    }
+                    None => {
+                        found = false;
+                        break;
+                    }
+                }
+            }
+            if found {
+                values[i] = current_node.value();
+            }
+        }
+        values
+    }
+
+    /// Old name of [`Trie::get`].
+    #[deprecated(since = "0.2.0", note = "renamed to `get`")]
+    pub fn get_value(&self, key: &str) -> Option<&T> {
+        self.get(key)
+    }
+
+    /// Values of the nodes on the path from the root to `key` that have one,
+    /// from the shortest prefix (the empty key) to `key` itself. If `key` isn't fully
+    /// present, the walk stops where the path breaks and returns the values
+    /// found up to that point.
+    pub fn values_along_path(&self, key: &str) -> Vec<&T> {
+        let key = &self.normalize(key);
+        let mut values: Vec<&T> = self.inner.root.value().into_iter().collect();
+        let mut current_node = &self.inner.root;
+        for c in key.chars() {
+            current_node = match current_node.child(c) {
+                Some(node) => node,
+                None => break,
+            };
+            values.extend(current_node.value());
+        }
+        values
+    }
+
+    /// The nodes on the path to `key`, from the root's child for its first
+    /// char down to the node for its last, so one per char; empty for the
+    /// empty key. `None` if the path breaks off before the end of `key`.
+    /// The last node needn't hold a value: any prefix of a stored key has
+    /// a path.
+    pub fn path_to_node(&self, key: &str) -> Option<Vec<&TrieNode<T, S>>> {
+        let key = &self.normalize(key);
+        let mut path = Vec::with_capacity(key.len());
+        let mut current_node = &self.inner.root;
+        for c in key.chars() {
+            current_node = current_node.child(c)?;
+            path.push(current_node);
+        }
+        Some(path)
+    }
+
+    /// Step-by-step trace of looking `key` up, e.g. to show why a lookup
+    /// failed: one `(char, has_value)` pair per char of `key` that matched,
+    /// telling whether the node it reached holds a value. The trace stops
+    /// at the first char without a node, so it is shorter than `key` if and
+    /// only if the path breaks off. Chars are those of the normalized key.
+    /// Nodes left by [`Trie::remove_lazy`] are traced like any other, as
+    /// `(char, false)`, until [`Trie::compact`] prunes them.
+    pub fn trace(&self, key: &str) -> Vec<(char, bool)> {
+        let key = &self.normalize(key);
+        let mut trace = Vec::new();
+        let mut current_node = &self.inner.root;
+        for c in key.chars() {
+            current_node = match current_node.child(c) {
+                Some(node) => node,
+                None => break,
+            };
+            trace.push((c, current_node.value().is_some()));
+        }
+        trace
+    }
+
+    // Node reached by following an already normalized key from the root
+    fn find_node(&self, key: &str) -> Option<&TrieNode<T, S>> {
+        let mut current_node = &self.inner.root;
+        for c in key.chars() {
+            current_node = current_node.child(c)?;
+        }
+        Some(current_node)
+    }
+
+    /// Returns true if `key` is stored in the trie.
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Number of stored keys, in O(1): the trie keeps count as keys come
+    /// and go.
+    pub fn len(&self) -> usize {
+        debug_assert_eq!(self.count, self.count_by_traversal());
+        self.count
+    }
+
+    // The key count as len would report it without the maintained counter,
+    // checking every node's own count on the way in debug builds
+    fn count_by_traversal(&self) -> usize {
+        self.inner.root.count_values_by_traversal()
+    }
+
+    /// Returns true if no key is stored, in O(1) like [`Trie::len`]. Nodes
+    /// left by [`Trie::remove_lazy`] don't count.
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// The longest stored key that is a prefix of `key`, with its value. The
+    /// empty key, if stored, matches as a last resort.
+    pub fn longest_prefix_match(&self, key: &str) -> Option<(String, &T)> {
+        let key = &self.normalize(key);
+        let mut best = self.inner.root.value().map(|value| (0, value));
+        let mut current_node = &self.inner.root;
+        for (i, c) in key.char_indices() {
+            current_node = match current_node.child(c) {
+                Some(node) => node,
+                None => break,
+            };
+            if let Some(value) = current_node.value() {
+                best = Some((i + c.len_utf8(), value));
+            }
+        }
+        best.map(|(len, value)| (String::from(&key[..len]), value))
+    }
+
+    /// The stored key that is a prefix of the most `queries`, e.g. the route
+    /// covering the most requests. Among keys matching equally many, the
+    /// longest wins, then the lexicographically smallest. None if no key is
+    /// a prefix of any query.
+    ///
+    /// The queries are sorted and walked down the trie together, so a
+    /// prefix they share is walked once for all of them.
+    pub fn most_common_prefix_among(&self, queries: &[&str]) -> Option<String> {
+        let mut queries: Vec<Cow<'_, str>> = queries.iter().map(|q| self.normalize(q)).collect();
+        queries.sort_unstable();
+        let queries: Vec<&str> = queries.iter().map(|q| q.as_ref()).collect();
+        let mut best = None;
+        Self::common_prefix_helper(&self.inner.root, &queries, &mut String::new(), 0, &mut best);
+        best.map(|(_, _, key)| key)
+    }
+
+    // Walk the subtree under `node`, whose key `key` is `depth` chars long,
+    // with the sorted `queries` that start with `key`. `best` is the best
+    // key so far, with how many queries it matches and its length in chars.
+    fn common_prefix_helper(
+        node: &TrieNode<T, S>,
+        queries: &[&str],
+        key: &mut String,
+        depth: usize,
+        best: &mut Option<(usize, usize, String)>,
+    ) {
+        // Fewer queries can only continue below, so no key there can win
+        if queries.is_empty()
+            || best
+                .as_ref()
+                .is_some_and(|(count, _, _)| queries.len() < *count)
+        {
+            return;
+        }
+        if node.value().is_some() {
+            let better = best
+                .as_ref()
+                .is_none_or(|(count, best_depth, _)| queries.len() > *count || depth > *best_depth);
+            if better {
+                *best = Some((queries.len(), depth, key.clone()));
+            }
+        }
+
+        // Queries equal to the key sort first; the rest come grouped by
+        // their next char, in order
+        let len = key.len();
+        let mut rest = &queries[queries.partition_point(|q| q.len() == len)..];
+        while let Some(c) = rest.first().and_then(|q| q[len..].chars().next()) {
+            let end = rest.partition_point(|q| q[len..].starts_with(c));
+            if let Some(child) = node.child(c) {
+                key.push(c);
+                Self::common_prefix_helper(child, &rest[..end], key, depth + 1, best);
+                key.pop();
+            }
+            rest = &rest[end..];
+        }
+    }
+
+    /// Matches `s` against the trie as far as it goes: the number of leading
+    /// chars of `s` (after normalization) that follow stored nodes, and the
+    /// node the last of them reaches. A completion engine can offer keys
+    /// from that node even when `s` as a whole isn't a stored prefix. The
+    /// node is None if not even the first char matches, including for an
+    /// empty `s`; [`Trie::root`] is the place to start then. Nodes left by
+    /// [`Trie::remove_lazy`] with no value below them don't match.
+    pub fn match_prefix(&self, s: &str) -> (usize, Option<&TrieNode<T, S>>) {
+        let mut path = Vec::new();
+        let mut current_node = &self.inner.root;
+        for c in self.normalize(s).chars() {
+            current_node = match current_node.child(c) {
+                Some(node) => node,
+                None => break,
+            };
+            path.push(current_node);
+        }
+        // If a node leads to a value, so do all nodes above it, so without
+        // tombstones only the deepest node is checked
+        while path.last().is_some_and(|node| !node.leads_to_value()) {
+            path.pop();
+        }
+        (path.len(), path.last().copied())
+    }
+
+    /// Number of leading chars over which `a` and `b` walk the same path of
+    /// stored nodes. Unlike the plain common prefix of the two strings, this
+    /// stops as soon as the shared chars leave the trie: with only "tea"
+    /// stored, "ten" and "tent" diverge after 2 chars although they share
+    /// 3, and strings sharing nothing stored diverge at 0.
+    pub fn diverge_point(&self, a: &str, b: &str) -> usize {
+        let (a, b) = (self.normalize(a), self.normalize(b));
+        let mut current_node = &self.inner.root;
+        let mut shared = 0;
+        for (c, d) in a.chars().zip(b.chars()) {
+            if c != d {
+                break;
+            }
+            current_node = match current_node.child(c) {
+                Some(node) => node,
+                None => break,
+            };
+            shared += 1;
+        }
+        shared
+    }
+
+    /// A mutable reference to the value stored under `key`, if any.
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut T> {
+        let key = &self.normalize(key);
+        self.normalized_value_mut(key)
+    }
+
+    /// Old name of [`Trie::get_mut`].
+    #[deprecated(since = "0.2.0", note = "renamed to `get_mut`")]
+    pub fn get_value_mut(&mut self, key: &str) -> Option<&mut T> {
+        self.get_mut(key)
+    }
+
+    // get_mut for a key that has already been normalized
+    fn normalized_value_mut(&mut self, key: &str) -> Option<&mut T> {
+        self.inner.get_mut(key.chars())
+    }
+
+    /// Applies `f` to the value at `key` if there is one, returning the trie
+    /// for chaining.
+    pub fn and_modify<F>(&mut self, key: &str, f: F) -> &mut Trie<T, S>
+    where
+        F: FnOnce(&mut T),
+    {
+        if let Some(value) = self.get_mut(key) {
+            f(value);
+        }
+        self
+    }
+
+    /// The value at `key`, inserting `value` first if the key is absent.
+    pub fn or_insert(&mut self, key: &str, value: T) -> &mut T {
+        if self.get(key).is_none() {
+            self.insert(key, value);
+        }
+        self.get_mut(key).unwrap()
+    }
+
+    /// Mutable references to the values of several keys at once, in the order
+    /// of `keys`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the same key is passed twice.
+    pub fn get_many_mut<const N: usize>(&mut self, keys: [&str; N]) -> [Option<&mut T>; N] {
+        // Distinctness has to hold after normalization, which is what
+        // decides the node each key reaches
+        let keys = keys.map(|key| self.normalize(key));
+        for i in 0..N {
+            for j in 0..i {
+                assert!(
+                    keys[i] != keys[j],
+                    "get_many_mut called with duplicate key {:?}",
+                    keys[i]
+                );
+            }
+        }
+
+        // One walk for all the keys: each node hands out its value and its
+        // children as disjoint borrows, and every key follows its own path
+        let keys = keys.map(|key| key.chars().collect::<Vec<char>>());
+        let mut values = [(); N].map(|_| None);
+        let mut stack = vec![(&mut self.inner.root, 0, (0..N).collect::<Vec<usize>>())];
+        while let Some((node, depth, pending)) = stack.pop() {
+            let (value, children) = node.split_mut();
+            let (ending, deeper): (Vec<usize>, Vec<usize>) =
+                pending.into_iter().partition(|&i| keys[i].len() == depth);
+            // The keys are distinct, so at most one of them ends here
+            if let Some(&i) = ending.first() {
+                values[i] = value.as_mut();
+            }
+            for child in children {
+                let below: Vec<usize> = deeper
+                    .iter()
+                    .copied()
+                    .filter(|&i| keys[i][depth] == child.key_char())
+                    .collect();
+                if !below.is_empty() {
+                    stack.push((child, depth + 1, below));
+                }
+            }
+        }
+        values
+    }
+
+    /// Detaches every key starting with `prefix` into a new trie, leaving the
+    /// rest in `self`. Keys in the returned trie keep the full prefix, and it
+    /// shares this trie's normalizer.
+    pub fn split_off(&mut self, prefix: &str) -> Trie<T, S> {
+        let prefix = &self.normalize(prefix);
+        let mut split = Trie {
+            inner: GenericTrie::default(),
+            normalizer: self.normalizer,
+            count: 0,
+        };
+
+        let last_char = match prefix.chars().last() {
+            Some(c) => c,
+            None => {
+                std::mem::swap(&mut split.inner.root, &mut self.inner.root);
+                std::mem::swap(&mut split.count, &mut self.count);
+                return split;
+            }
+        };
+        let parent_key = &prefix[..prefix.len() - last_char.len_utf8()];
+
+        let mut current_node = &mut self.inner.root;
+        for c in parent_key.chars() {
+            current_node = match current_node.child_mut(c) {
+                Some(node) => node,
+                None => return split,
+            };
+        }
+        let subtree = match current_node.remove_child(last_char) {
+            Some(subtree) => subtree,
+            None => return split,
+        };
+        split.count = subtree.count_values();
+        self.count -= split.count;
+        Self::prune_path(&mut self.inner.root, parent_key, split.count);
+
+        // The path down to the subtree, built bottom-up so that every node
+        // counts the subtree's values as it is attached
+        let mut node = subtree;
+        for c in parent_key.chars().rev() {
+            let mut parent = TrieNode::new(c, None);
+            parent.insert_child(node.key_char(), node).unwrap();
+            node = parent;
+        }
+        split
+            .inner
+            .root
+            .insert_child(node.key_char(), node)
+            .unwrap();
+        split
+    }
+
+    // Drop the nodes along `key`'s path that hold no value and have no
+    // children left, deepest first, after `removed` values were detached
+    // from the end of the path
+    fn prune_path(parent_node: &mut TrieNode<T, S>, key: &str, removed: usize) {
+        let mut chars = key.chars();
+        let c = match chars.next() {
+            Some(c) => c,
+            None => return,
+        };
+
+        if let Some(node) = parent_node.child_mut(c) {
+            Self::prune_path(node, chars.as_str(), removed);
+            if node.value().is_none() && !node.has_children() {
+                parent_node.remove_child(c);
+            }
+            parent_node.descendants_changed(0, removed);
+        }
+    }
+
+    /// Exchanges the values of two stored keys. Returns false, leaving the
+    /// trie untouched, if either key is absent.
+    pub fn swap(&mut self, key_a: &str, key_b: &str) -> bool {
+        // get_many_mut normalizes the keys itself, and rules out equal ones
+        if self.normalize(key_a) == self.normalize(key_b) {
+            return self.contains_key(key_a);
+        }
+
+        // The values trade places, the priorities stay with their keys
+        match self.get_many_mut([key_a, key_b]) {
+            [Some(a), Some(b)] => {
+                std::mem::swap(a, b);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Clones the trie with every value replaced by `f` applied to it, e.g.
+    /// to project values into a type for display. Unlike consuming `self`,
+    /// this doesn't need `T: Clone`. The keys, node layout and normalizer
+    /// are unchanged.
+    pub fn clone_with_map<U, F: FnMut(&T) -> U>(&self, f: F) -> Trie<U, S> {
+        Trie {
+            inner: self.inner.clone_with_map(f),
+            normalizer: self.normalizer,
+            count: self.count,
+        }
+    }
+
+    /// Turns the trie into one holding `f(key, value)` for every entry,
+    /// moving the values into `f` instead of cloning them, e.g. to parse
+    /// raw strings into configs. The nodes are rebuilt one for one, with
+    /// no key inserted anew, and the normalizer is kept. `f` sees the
+    /// entries in lexicographic key order.
+    pub fn map_values<U, F: FnMut(&str, T) -> U>(self, mut f: F) -> Trie<U, S> {
+        match self.try_map_values(|key, value| Ok::<U, Infallible>(f(key, value))) {
+            Ok(trie) => trie,
+            Err((_, never)) => match never {},
+        }
+    }
+
+    /// [`Trie::map_values`] with a fallible `f`, stopping at the first
+    /// error.
+    ///
+    /// # Errors
+    ///
+    /// The first error `f` returns, along with the key it failed on. The
+    /// values not mapped yet are dropped.
+    pub fn try_map_values<U, E, F>(self, mut f: F) -> Result<Trie<U, S>, (String, E)>
+    where
+        F: FnMut(&str, T) -> Result<U, E>,
+    {
+        let Trie {
+            inner,
+            normalizer,
+            count,
+        } = self;
+        let root = Self::rebuild(inner.root, false, |key, value| f(key, value).map(Some))?;
+        Ok(Trie {
+            inner: GenericTrie { root },
+            normalizer,
+            count,
+        })
+    }
+
+    // Rebuilds the subtree under `root` with each value replaced by what `f`
+    // makes of it, None dropping it, and `f` seeing the entries in key
+    // order. With `prune`, nodes left with neither a value nor children are
+    // dropped, as are those the trie held already. Iterative, for any key
+    // length: the new nodes on the path to the current one, root first, are
+    // stacked with the old children still to rebuild.
+    fn rebuild<U, E, F>(
+        root: TrieNode<T, S>,
+        prune: bool,
+        mut f: F,
+    ) -> Result<TrieNode<U, S>, (String, E)>
+    where
+        F: FnMut(&str, T) -> Result<Option<U>, E>,
+    {
+        let mut key = String::new();
+        let mut map = |key: &String, value: Option<T>| match value {
+            Some(value) => f(key, value).map_err(|error| (key.clone(), error)),
+            None => Ok(None),
+        };
+
+        // A kept value keeps its priority
+        let mut rebuild_node = |c: char, key: &String, node: TrieNode<T, S>| {
+            let (value, priority, children) = node.into_parts();
+            let mut node = TrieNode::new(c, map(key, value)?);
+            if node.value().is_some() && priority.is_some() {
+                node.set_priority(priority);
+            }
+            Ok((node, children.into_iter()))
+        };
+
+        let mut path = vec![rebuild_node(char::default(), &key, root)?];
+        loop {
+            match path.last_mut().unwrap().1.next() {
+                Some(child) => {
+                    let c = child.key_char();
+                    key.push(c);
+                    path.push(rebuild_node(c, &key, child)?);
+                }
+                None => {
+                    let (node, _) = path.pop().unwrap();
+                    match path.last_mut() {
+                        Some((parent, _)) => {
+                            key.pop();
+                            if !prune || node.value().is_some() || node.has_children() {
+                                parent.insert_child(node.key_char(), node).unwrap();
+                            }
+                        }
+                        None => return Ok(node),
+                    }
+                }
+            }
+        }
+    }
+
+    /// A fresh copy of the trie, built by inserting the keys in sorted order
+    /// into new nodes and then dropping spare capacity, e.g. to tidy up
+    /// after heavy interleaved inserts and removes. Unlike
+    /// [`Trie::shrink_to_fit`], which keeps the existing containers, every
+    /// node and container is allocated anew, and in key order, for better
+    /// locality. Nodes left by [`Trie::remove_lazy`] aren't copied. The
+    /// normalizer is kept.
+    pub fn rebuild_balanced(&self) -> Trie<T, S>
+    where
+        T: Clone,
+    {
+        let mut rebuilt = Trie {
+            inner: GenericTrie::default(),
+            normalizer: self.normalizer,
+            count: self.count,
+        };
+        // The keys are stored normalized already, so bypass the normalizer.
+        // Priorities are looked up only if there are any.
+        let ranked = self.root().max_priority().is_some();
+        for (key, value) in self.iter() {
+            let priority = match ranked {
+                true => self.inner.priority(key.chars()).copied(),
+                false => None,
+            };
+            rebuilt
+                .inner
+                .try_insert_entry(key.chars(), value.clone(), priority)
+                .unwrap();
+        }
+        rebuilt.shrink_to_fit();
+        rebuilt
+    }
+
+    /// All entries, in lexicographic key order, with cloned values. Along
+    /// with [`Trie::from_sorted_vec`], a simple persistent form that needs
+    /// no particular format, e.g. one line per entry.
+    pub fn to_sorted_vec(&self) -> Vec<(String, T)>
+    where
+        T: Clone,
+    {
+        self.iter()
+            .map(|(key, value)| (key, value.clone()))
+            .collect()
+    }
+
+    /// All entries copied into a `BTreeMap`, e.g. to hand them to code
+    /// expecting an ordered map. The map iterates in the trie's order.
+    pub fn to_btreemap(&self) -> BTreeMap<String, T>
+    where
+        T: Clone,
+    {
+        self.iter()
+            .map(|(key, value)| (key, value.clone()))
+            .collect()
+    }
+
+    /// All entries copied into a `HashMap`.
+    pub fn to_hashmap(&self) -> HashMap<String, T>
+    where
+        T: Clone,
+    {
+        self.iter()
+            .map(|(key, value)| (key, value.clone()))
+            .collect()
+    }
+
+    /// Builds a trie from entries sorted by key, as returned by
+    /// [`Trie::to_sorted_vec`]. Sorted input lets every node be built once
+    /// and left for good, with no lookups from the root: only the path to
+    /// the last key is kept open. The trie has no normalizer; keys are
+    /// stored as they are.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the keys aren't strictly increasing, i.e. are out of order
+    /// or repeated.
+    pub fn from_sorted_vec(entries: Vec<(String, T)>) -> Trie<T, S> {
+        // Nodes on the path to the previous key, the root first. A node is
+        // attached to its parent once no later key can reach it.
+        let mut path = vec![TrieNode::new('\0', None)];
+        let mut previous: Option<String> = None;
+        for (key, value) in entries {
+            let mut shared = 0;
+            if let Some(previous) = &previous {
+                assert!(
+                    *previous < key,
+                    "from_sorted_vec called with {:?} after {:?}",
+                    key,
+                    previous
+                );
+                shared = previous
+                    .chars()
+                    .zip(key.chars())
+                    .take_while(|(a, b)| a == b)
+                    .count();
+            }
+            Self::close_path(&mut path, shared + 1);
+            path.extend(key.chars().skip(shared).map(|c| TrieNode::new(c, None)));
+            path.last_mut().unwrap().set_value(value);
+            previous = Some(key);
+        }
+        Self::close_path(&mut path, 1);
+        Trie::from_root(path.pop().unwrap())
+    }
+
+    // Attach the nodes of `path` beyond the first `len` to their parents
+    fn close_path(path: &mut Vec<TrieNode<T, S>>, len: usize) {
+        while path.len() > len {
+            let node = path.pop().unwrap();
+            path.last_mut()
+                .unwrap()
+                .insert_child(node.key_char(), node)
+                .unwrap();
+        }
+    }
+
+    /// Swaps keys and values, e.g. to turn a lookup table into its reverse.
+    /// Consuming the trie moves the values rather than cloning them. When
+    /// several keys share a value, the lexicographically smallest of them
+    /// wins. The inverted trie has no normalizer. See [`Trie::invert`] to
+    /// keep the trie and collect every key per value.
+    pub fn into_inverted(mut self) -> Trie<String>
+    where
+        T: Into<String>,
+    {
+        let mut inverted = Trie::new();
+        let mut key = String::new();
+        let mut stack = vec![(&mut self.inner.root, 0, false)];
+        while let Some((node, parent_len, push_char)) = stack.pop() {
+            key.truncate(parent_len);
+            if push_char {
+                key.push(node.key_char());
+            }
+            let (value, children) = node.split_mut();
+            // Insertion keeps the first of several keys for a value, and
+            // the walk is in lexicographic order
+            if let Some(value) = value.take() {
+                inverted.insert(&value.into(), key.clone());
+            }
+            for child in children.into_iter().rev() {
+                stack.push((child, key.len(), true));
+            }
+        }
+        inverted
+    }
+
+    /// Iterates over all entries in lexicographic key order.
+    pub fn iter(&self) -> Iter<'_, T, S> {
+        Iter::new(&self.inner.root, String::new())
+    }
+
+    /// Iterates over all entries in lexicographic key order, with mutable
+    /// access to the values, e.g. to update every value in place.
+    pub fn iter_mut(&mut self) -> IterMut<'_, T, S> {
+        IterMut::new(&mut self.inner.root)
+    }
+
+    /// Calls `f` on every value whose key starts with `prefix`, in
+    /// lexicographic key order, mutating them in place, e.g. to mark a
+    /// whole namespace as expiring. Unlike [`Trie::iter_mut`] on the
+    /// matching entries, no keys are built.
+    pub fn apply_prefix<F: FnMut(&mut T)>(&mut self, prefix: &str, mut f: F) {
+        let prefix = &self.normalize(prefix);
+        let mut current_node = &mut self.inner.root;
+        for c in prefix.chars() {
+            current_node = match current_node.child_mut(c) {
+                Some(node) => node,
+                None => return,
+            };
+        }
+
+        let mut stack = vec![current_node];
+        while let Some(node) = stack.pop() {
+            let (value, children) = node.split_mut();
+            if let Some(value) = value {
+                f(value);
+            }
+            stack.extend(children.into_iter().rev());
+        }
+    }
+
+    /// All entries in the order a pre-order depth-first walk visits them,
+    /// children in ascending char order. The order doesn't depend on how
+    /// nodes store their children, which makes it suitable for snapshot
+    /// tests; it is the same order [`Trie::iter`] yields. Sorting a node's
+    /// k children costs O(k log k).
+    pub fn depth_first_order(&self) -> Vec<(String, &T)> {
+        self.iter().collect()
+    }
+
+    /// Iterates over the entries whose keys start with `prefix`, in
+    /// lexicographic order. Keys are yielded in full, prefix included.
+    pub fn iter_prefix(&self, prefix: &str) -> Iter<'_, T, S> {
+        let prefix = self.normalize(prefix);
+        match self.find_node(&prefix) {
+            Some(node) => Iter::new(node, prefix.into_owned()),
+            None => Iter::empty(),
+        }
+    }
+
+    /// Calls `f` on every entry in lexicographic key order, like
+    /// [`Trie::iter`] but without allocating a `String` per key: the key is
+    /// lent out of one buffer that the walk extends and truncates as it
+    /// goes, so it is only valid during the call. Copy it out to keep it.
+    pub fn for_each_entry<'a, F: FnMut(&str, &'a T)>(&'a self, f: F) {
+        Self::for_each_in(Iter::new(&self.inner.root, String::new()), f);
+    }
+
+    /// [`Trie::for_each_entry`] over the entries whose keys start with
+    /// `prefix`. Keys are passed in full, prefix included.
+    pub fn for_each_entry_with_prefix<'a, F>(&'a self, prefix: &str, f: F)
+    where
+        F: FnMut(&str, &'a T),
+    {
+        Self::for_each_in(self.iter_prefix(prefix), f);
+    }
+
+    fn for_each_in<'a, F: FnMut(&str, &'a T)>(mut iter: Iter<'a, T, S>, mut f: F) {
+        while let Some((key, value)) = iter.next_entry() {
+            f(key, value);
+        }
+    }
+
+    /// One page of the entries whose keys start with `prefix`, in
+    /// lexicographic order: the first `limit` of them sorting after `after`,
+    /// or from the first one if `after` is `None`. Pass the page's
+    /// [`Page::next_after`] as `after` to get the next page; it is `None`
+    /// on the last page.
+    ///
+    /// The walk goes straight down `after`'s path to where the page
+    /// starts, so later pages cost no more than the first. `after` needn't
+    /// be a stored key, and both it and `prefix` are normalized. A `limit`
+    /// of 0 gives an empty page without a token.
+    pub fn keys_with_prefix_page(
+        &self,
+        prefix: &str,
+        after: Option<&str>,
+        limit: usize,
+    ) -> Page<'_, T> {
+        let prefix = self.normalize(prefix);
+        let mut iter = match self.find_node(&prefix) {
+            None => Iter::empty(),
+            Some(node) => match after.map(|after| self.normalize(after)) {
+                // Every key under the prefix sorts after it
+                None => Iter::new(node, prefix.into_owned()),
+                Some(after) if *after < *prefix => Iter::new(node, prefix.into_owned()),
+                Some(after) => match after.strip_prefix(&*prefix) {
+                    Some(rest) => Iter::after(node, prefix.to_string(), rest),
+                    // Every key under the prefix sorts before it
+                    None => Iter::empty(),
+                },
+            },
+        };
+
+        let entries: Vec<(String, &T)> = iter.by_ref().take(limit).collect();
+        let next_after = match entries.last() {
+            Some((last, _)) if iter.next_entry().is_some() => Some(last.clone()),
+            _ => None,
+        };
+        Page {
+            entries,
+            next_after,
+        }
+    }
+
+    /// Iterates over the entries whose keys `other` doesn't store, in
+    /// lexicographic order, e.g. to find what a namespace has that another
+    /// lacks. The tries are walked side by side, so a subtree `other` has
+    /// no path into is passed through without lookups. Keys are compared
+    /// as stored, i.e. after each trie's own normalizer.
+    pub fn difference<'a, U, S2>(
+        &'a self,
+        other: &'a Trie<U, S2>,
+    ) -> impl Iterator<Item = (String, &'a T)> + 'a
+    where
+        S2: BuildHasher + Default,
+    {
+        // Node, key length (in bytes) before it, whether its char is part
+        // of the key, and the node at the same key in `other`, if any
+        let mut stack = vec![(&self.inner.root, 0, false, Some(&other.inner.root))];
+        let mut key = String::new();
+        std::iter::from_fn(move || {
+            while let Some((node, parent_len, push_char, other_node)) = stack.pop() {
+                key.truncate(parent_len);
+                if push_char {
+                    key.push(node.key_char());
+                }
+                for child in node.children_in_order().rev() {
+                    let other_child = other_node.and_then(|other| other.child(child.key_char()));
+                    stack.push((child, key.len(), true, other_child));
+                }
+                if let Some(value) = node.value() {
+                    if other_node.and_then(TrieNode::value).is_none() {
+                        return Some((key.clone(), value));
+                    }
+                }
+            }
+            None
+        })
+    }
+
+    /// Iterates over the keys starting with `prefix`, in lexicographic order.
+    pub fn keys_with_prefix(&self, prefix: &str) -> impl Iterator<Item = String> + '_ {
+        self.iter_prefix(prefix).map(|(key, _)| key)
+    }
+
+    /// The first `limit` keys starting with `prefix`, in lexicographic
+    /// order, e.g. for autocomplete on a large dictionary. The walk is lazy
+    /// and stops at the `limit`-th key, so the cost depends on `limit`
+    /// rather than on how many keys match.
+    pub fn keys_with_prefix_limited(&self, prefix: &str, limit: usize) -> Vec<String> {
+        self.keys_with_prefix(prefix).take(limit).collect()
+    }
+
+    /// The (at most) `k` keys starting with `prefix` that have the highest
+    /// priorities, highest first, with their values, e.g. the most popular
+    /// completions of a query. Equal priorities are in lexicographic key
+    /// order, and values inserted without a priority are left out.
+    ///
+    /// Each node tracks the highest priority in its subtree, so a
+    /// best-first search leaves alone any subtree that can't beat the
+    /// results it already has: finding the top `k` of `n` matches costs
+    /// about `k · depth · fanout` steps instead of collecting and sorting
+    /// all `n`.
+    pub fn top_k_by_prefix(&self, prefix: &str, k: usize) -> Vec<(String, &T)> {
+        let prefix = &self.normalize(prefix);
+        self.inner
+            .top_k_by_prefix(prefix.chars(), k)
+            .into_iter()
+            .map(|(key, value)| (key.into_iter().collect(), value))
+            .collect()
+    }
+
+    /// Iterates over the keys starting with at least one of `prefixes`, in
+    /// lexicographic order. A key matching several prefixes, e.g. "apple"
+    /// for "a" and "app", is yielded once.
+    pub fn keys_starting_with_any<'a>(
+        &'a self,
+        prefixes: &[&str],
+    ) -> impl Iterator<Item = String> + 'a {
+        let mut wanted = Trie::new();
+        for prefix in prefixes {
+            wanted.insert(&self.normalize(prefix), ());
+        }
+        let mut subtrees = Vec::new();
+        Self::prefix_subtrees(
+            self.root(),
+            wanted.root(),
+            &mut String::new(),
+            &mut subtrees,
+        );
+        subtrees
+            .into_iter()
+            .flat_map(|(key, node)| Iter::new(node, key))
+            .map(|(key, _)| key)
+    }
+
+    // Walk `node` in lockstep with the trie of prefixes, as
+    // retain_prefixes_helper does, collecting the subtrees a prefix ends at
+    // in key order. The walk stops at the shortest prefix on each path, so
+    // the subtrees don't nest and no key is reached twice.
+    fn prefix_subtrees<'a>(
+        node: &'a TrieNode<T, S>,
+        wanted: &TrieNode<()>,
+        key: &mut String,
+        subtrees: &mut Vec<(String, &'a TrieNode<T, S>)>,
+    ) {
+        if wanted.value().is_some() {
+            subtrees.push((key.clone(), node));
+            return;
+        }
+
+        for wanted_child in wanted.sorted_children() {
+            let c = wanted_child.key_char();
+            if let Some(child) = node.child(c) {
+                key.push(c);
+                Self::prefix_subtrees(child, wanted_child, key, subtrees);
+                key.pop();
+            }
+        }
+    }
+
+    /// Iterates over the values of the keys starting with `prefix`, in
+    /// lexicographic key order, e.g. to aggregate a namespace.
+    pub fn values_with_prefix(&self, prefix: &str) -> impl Iterator<Item = &T> + '_ {
+        self.iter_prefix(prefix).map(|(_, value)| value)
+    }
+
+    /// Returns true if any stored key starts with `prefix`, e.g. to check
+    /// whether a namespace is populated before scanning it. Only walks down
+    /// to the prefix's node, which keeps count of the values below it, and
+    /// doesn't allocate unless the trie has a normalizer.
+    pub fn peek_prefix(&self, prefix: &str) -> bool {
+        self.find_node(&self.normalize(prefix))
+            .is_some_and(TrieNode::leads_to_value)
+    }
+
+    /// Whether `s` is a stored key, only a prefix of longer ones, or
+    /// neither, in one walk down its path; see [`PrefixStatus`]. This
+    /// answers [`Trie::contains_key`] and [`Trie::peek_prefix`] together.
+    pub fn prefix_status(&self, s: &str) -> PrefixStatus {
+        match self.find_node(&self.normalize(s)) {
+            Some(node) if node.value().is_some() => PrefixStatus::Valued,
+            // Nodes left by remove_lazy may lead nowhere
+            Some(node) if node.leads_to_value() => PrefixStatus::InternalOnly,
+            _ => PrefixStatus::NoMatch,
+        }
+    }
+
+    /// Stored keys for which `pred` returns true, in lexicographic order.
+    pub fn keys_matching<F: Fn(&str) -> bool>(&self, pred: F) -> Vec<String> {
+        self.collect_where(|key, _| pred(key).then(|| String::from(key)))
+    }
+
+    /// The keys, in lexicographic order, whose value satisfies `f`. Empty if
+    /// none does.
+    pub fn find_all_with<F: Fn(&T) -> bool>(&self, f: F) -> Vec<String> {
+        self.collect_where(|key, value| f(value).then(|| String::from(key)))
+    }
+
+    /// The keys holding `value`, in lexicographic order. Scans every entry
+    /// but, unlike [`Trie::invert`], builds nothing beyond the result.
+    pub fn keys_for_value(&self, value: &T) -> Vec<String>
+    where
+        T: PartialEq,
+    {
+        self.find_all_with(|v| v == value)
+    }
+
+    /// Reverse index from each stored value to the keys holding it, with
+    /// each key list in lexicographic order. See [`Trie::keys_for_value`]
+    /// to look up a single value.
+    pub fn invert(&self) -> HashMap<T, Vec<String>>
+    where
+        T: Eq + Hash + Clone,
+    {
+        let mut index: HashMap<T, Vec<String>> = HashMap::new();
+        for (key, value) in self.iter() {
+            index.entry(value.clone()).or_default().push(key);
+        }
+        index
+    }
+
+    /// The `Some` results of `f` over all entries, in lexicographic key
+    /// order.
+    pub fn collect_where<U, F: FnMut(&str, &T) -> Option<U>>(&self, mut f: F) -> Vec<U> {
+        // Only the keys f keeps are allocated, by f
+        let mut results = Vec::new();
+        self.for_each_entry(|key, value| results.extend(f(key, value)));
+        results
+    }
+
+    /// A regular expression, in the syntax of the `regex` crate, matching
+    /// exactly the stored keys. It isn't anchored, so wrap it as
+    /// `^(?:...)$` to test whole strings. For the keys `foo`, `foobar` and
+    /// `baz` it is `(?:baz|foo(?:bar)?)`.
+    pub fn to_regexp(&self) -> String {
+        if self.is_empty() {
+            // An empty class, which never matches
+            return String::from("[^\\s\\S]");
+        }
+        let mut regexp = String::new();
+        Self::push_regexp(&self.inner.root, &mut regexp);
+        regexp
+    }
+
+    // Append a regexp for the key suffixes stored below `root`: at every
+    // node an alternation over its children, made optional if the node
+    // itself ends a key
+    fn push_regexp(root: &TrieNode<T, S>, regexp: &mut String) {
+        // Iterative, for any key length: the stack holds the nodes whose
+        // alternation is still to be written, interleaved with the chars
+        // leading to them and the text that separates and closes the groups
+        // already open
+        enum Step<'a, T, S> {
+            Node(&'a TrieNode<T, S>),
+            Char(char),
+            Text(&'static str),
+        }
+
+        let mut stack = vec![Step::Node(root)];
+        while let Some(step) = stack.pop() {
+            let node = match step {
+                Step::Node(node) => node,
+                Step::Char(c) => {
+                    if "\\.+*?()|[]{}^$#&-~".contains(c) {
+                        regexp.push('\\');
+                    }
+                    regexp.push(c);
+                    continue;
+                }
+                Step::Text(text) => {
+                    regexp.push_str(text);
+                    continue;
+                }
+            };
+
+            // Skipping the branches left by Trie::remove_lazy
+            let mut children = node.sorted_children();
+            children.retain(|child| child.leads_to_value());
+            if children.is_empty() {
+                continue;
+            }
+            if node.value().is_some() {
+                regexp.push_str("(?:");
+                stack.push(Step::Text(")?"));
+            } else if children.len() > 1 {
+                regexp.push_str("(?:");
+                stack.push(Step::Text(")"));
+            }
+            for (i, child) in children.into_iter().enumerate().rev() {
+                stack.push(Step::Node(child));
+                stack.push(Step::Char(child.key_char()));
+                if i > 0 {
+                    stack.push(Step::Text("|"));
+                }
+            }
+        }
+    }
+
+    /// A `Debug` view listing every entry; the trie's own `Debug` output
+    /// stops after the first few.
+    pub fn debug_full(&self) -> DebugFull<'_, T, S> {
+        DebugFull { trie: self }
+    }
+
+    /// A compact, deterministic dump of the node structure, for diagnostics
+    /// and exact assertions in tests. It follows this grammar, with no
+    /// whitespace anywhere:
+    ///
+    /// ```text
+    /// dump     = [ "*" ] "{" node* "}"
+    /// node     = char [ "*" ] [ "{" node+ "}" ]
+    /// char     = [ "\" ] <the node's key char>
+    /// ```
+    ///
+    /// `*` marks a node holding a value; in `dump` it stands for the empty
+    /// key. Children are listed in ascending char order, and a char that is
+    /// one of `\`, `*`, `{` or `}` is escaped with a backslash. For the
+    /// keys "a", "ab" and "ac" the dump is `{a*{b*c*}}`. Nodes left by
+    /// [`Trie::remove_lazy`] are dumped too, without a `*`, until
+    /// [`Trie::compact`] prunes them.
+    pub fn debug_structure(&self) -> String {
+        let mut dump = String::new();
+        if self.inner.root.value().is_some() {
+            dump.push('*');
+        }
+        dump.push('{');
+
+        // None closes the children of the node opened before it
+        let mut stack = vec![None];
+        stack.extend(
+            self.inner
+                .root
+                .sorted_children()
+                .into_iter()
+                .rev()
+                .map(Some),
+        );
+        while let Some(node) = stack.pop() {
+            let node = match node {
+                Some(node) => node,
+                None => {
+                    dump.push('}');
+                    continue;
+                }
+            };
+
+            if matches!(node.key_char(), '\\' | '*' | '{' | '}') {
+                dump.push('\\');
+            }
+            dump.push(node.key_char());
+            if node.value().is_some() {
+                dump.push('*');
+            }
+            if node.has_children() {
+                dump.push('{');
+                stack.push(None);
+                stack.extend(node.sorted_children().into_iter().rev().map(Some));
+            }
+        }
+        dump
+    }
+
+    /// Lexicographically smallest stored key. Since a key sorts before all its
+    /// extensions, this is the first valued node reached by always descending
+    /// into the smallest child.
+    pub fn first_key(&self) -> Option<String> {
+        let mut key = String::new();
+        let mut stack = vec![(&self.inner.root, 0)];
+        while let Some((node, parent_len)) = stack.pop() {
+            key.truncate(parent_len);
+            if !std::ptr::eq(node, &self.inner.root) {
+                key.push(node.key_char());
+            }
+            if node.value().is_some() {
+                return Some(key);
+            }
+            for child in node.sorted_children().into_iter().rev() {
+                stack.push((child, key.len()));
+            }
+        }
+        None
+    }
+
+    /// Lexicographically largest stored key: descend into the largest child
+    /// for as long as possible, then take the deepest valued node on the way
+    /// back up.
+    pub fn last_key(&self) -> Option<String> {
+        let mut key = String::new();
+        let mut stack = vec![(&self.inner.root, 0, false)];
+        while let Some((node, parent_len, expanded)) = stack.pop() {
+            key.truncate(parent_len);
+            if !std::ptr::eq(node, &self.inner.root) {
+                key.push(node.key_char());
+            }
+            if expanded {
+                if node.value().is_some() {
+                    return Some(key);
+                }
+                continue;
+            }
+
+            stack.push((node, parent_len, true));
+            for child in node.sorted_children() {
+                stack.push((child, key.len(), false));
+            }
+        }
+        None
+    }
+
+    /// A stored key with the most chars. Ties go to the lexicographically
+    /// smallest.
+    pub fn longest_key(&self) -> Option<String> {
+        self.extreme_key(|depth, best| depth > best)
+    }
+
+    /// A stored key with the fewest chars, which is the empty key if that is
+    /// stored. Ties go to the lexicographically smallest.
+    pub fn shortest_key(&self) -> Option<String> {
+        self.extreme_key(|depth, best| depth < best)
+    }
+
+    // DFS keeping the first valued node whose depth in chars `better` prefers
+    // over the best one so far
+    fn extreme_key(&self, better: fn(usize, usize) -> bool) -> Option<String> {
+        let mut best: Option<(usize, String)> = None;
+        let mut key = String::new();
+        let mut stack = vec![(&self.inner.root, 0, 0)];
+        while let Some((node, parent_len, depth)) = stack.pop() {
+            key.truncate(parent_len);
+            if depth > 0 {
+                key.push(node.key_char());
+            }
+            if node.value().is_some() {
+                match &best {
+                    Some((best_depth, _)) if !better(depth, *best_depth) => {}
+                    _ => best = Some((depth, key.clone())),
+                }
+            }
+            for child in node.sorted_children().into_iter().rev() {
+                stack.push((child, key.len(), depth + 1));
+            }
+        }
+        best.map(|(_, key)| key)
+    }
+
+    /// All keys from shortest to longest, lexicographically within each
+    /// length, e.g. to handle general keys before the more specific ones
+    /// they prefix. Length is in chars, as for [`Trie::longest_key`]. Keys
+    /// are collected and sorted up front.
+    pub fn topological_order(&self) -> impl Iterator<Item = String> {
+        let mut keys: Vec<(usize, String)> = self
+            .iter()
+            .map(|(key, _)| (key.chars().count(), key))
+            .collect();
+        // iter() is already lexicographic, which the stable sort keeps
+        keys.sort_by_key(|(len, _)| *len);
+        keys.into_iter().map(|(_, key)| key)
+    }
+
+    /// Entries whose keys lie in the half-open range `[start, end)`, in
+    /// lexicographic order. Subtrees whose keys all sort below `start` or at or
+    /// above `end` are skipped without being visited.
+    pub fn range(&self, start: &str, end: &str) -> Vec<(String, &T)> {
+        let mut entries = Vec::new();
+        let mut key = String::new();
+        let mut stack = vec![(&self.inner.root, 0)];
+        while let Some((node, parent_len)) = stack.pop() {
+            key.truncate(parent_len);
+            if !std::ptr::eq(node, &self.inner.root) {
+                key.push(node.key_char());
+            }
+
+            // Every key in this subtree extends `key`, so none can reach
+            // `start` unless `key` is a prefix of it, and all are >= `key`
+            if (key.as_str() < start && !start.starts_with(key.as_str())) || key.as_str() >= end {
+                continue;
+            }
+            if let Some(value) = node.value() {
+                if key.as_str() >= start {
+                    entries.push((key.clone(), value));
+                }
+            }
+            for child in node.sorted_children().into_iter().rev() {
+                stack.push((child, key.len()));
+            }
+        }
+        entries
+    }
+
+    /// Rough heap footprint in bytes: a fixed cost per node (root included)
+    /// for the node and the key char stored next to it in the parent's
+    /// child container. Spare capacity, HashMap-backed wide nodes, and heap
+    /// data owned by values are not measured; [`Trie::memory_usage`]
+    /// measures the actual containers instead, so it is the one to use when
+    /// those matter.
+    pub fn memory_estimate(&self) -> usize {
+        self.inner.root.count_nodes()
+            * (std::mem::size_of::<TrieNode<T, S>>() + CHILD_SLOT_OVERHEAD)
+    }
+
+    /// Releases spare capacity in every node's child container, e.g. after
+    /// many removals; see [`Trie::memory_usage`].
+    pub fn shrink_to_fit(&mut self) {
+        let mut stack = vec![&mut self.inner.root];
+        while let Some(node) = stack.pop() {
+            node.shrink_children();
+            stack.extend(node.split_mut().1);
+        }
+    }
+
+    /// Number of stored keys lexicographically smaller than `key`, or `None` if
+    /// `key` is not stored. Every node keeps count of the values below it,
+    /// so this is O(depth · fanout): only the children before each char of
+    /// `key` are looked at, not their subtrees.
+    pub fn rank(&self, key: &str) -> Option<usize> {
+        let key = &self.normalize(key);
+        let mut rank = 0;
+        let mut current_node = &self.inner.root;
+        for c in key.chars() {
+            // A valued proper prefix sorts before the key itself
+            if current_node.value().is_some() {
+                rank += 1;
+            }
+            for (child_char, child) in current_node.children() {
+                if child_char < c {
+                    rank += child.count_values();
+                }
+            }
+            current_node = current_node.child(c)?;
+        }
+
+        current_node.value().map(|_| rank)
+    }
+
+    /// Key at 0-based position `rank` in the sorted order of stored keys, or
+    /// `None` if there are no more than `rank` keys. Like [`Trie::rank`],
+    /// O(depth · fanout): the kept counts say which child to descend into.
+    pub fn select(&self, rank: usize) -> Option<String> {
+        if rank >= self.count {
+            return None;
+        }
+        let mut remaining = rank;
+        let mut key = String::new();
+        let mut current_node = &self.inner.root;
+        loop {
+            if current_node.value().is_some() {
+                if remaining == 0 {
+                    return Some(key);
+                }
+                remaining -= 1;
+            }
+
+            let mut next_node = None;
+            for child in current_node.sorted_children() {
+                let count = child.count_values();
+                if remaining < count {
+                    next_node = Some(child);
+                    break;
+                }
+                remaining -= count;
+            }
+
+            current_node = next_node?;
+            key.push(current_node.key_char());
+        }
+    }
+}
+
+// Number of entries the Trie's Debug output lists before eliding the rest
+const DEBUG_ENTRIES: usize = 8;
+
+impl<T: fmt::Debug, S: BuildHasher + Default> fmt::Debug for Trie<T, S> {
+    // A summary plus the first few entries. Deriving Debug would dump every
+    // node recursively, which is unreadable for big tries and overflows the
+    // stack for deep ones.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let stats = self.stats();
+        write!(
+            f,
+            "Trie {{ keys: {}, nodes: {}, max_depth: {}, entries: [",
+            stats.num_keys, stats.num_nodes, stats.max_depth
+        )?;
+        for (i, (key, value)) in self.iter().take(DEBUG_ENTRIES).enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{:?} => {:?}", key, value)?;
+        }
+        if stats.num_keys > DEBUG_ENTRIES {
+            write!(f, ", ..")?;
+        }
+        write!(f, "] }}")
+    }
+}
+
+// Key chars as they come from Trie::normalize_chars: the caller's own, or
+// those of the normalized string, with the byte offset of the next one
+enum KeyChars<I> {
+    Raw(I),
+    Normalized(String, usize),
+}
+
+impl<I: Iterator<Item = char>> Iterator for KeyChars<I> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        match self {
+            KeyChars::Raw(chars) => chars.next(),
+            KeyChars::Normalized(key, offset) => {
+                let c = key[*offset..].chars().next()?;
+                *offset += c.len_utf8();
+                Some(c)
+            }
+        }
+    }
+}
+
+/// One page of entries, from [`Trie::keys_with_prefix_page`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Page<'a, T> {
+    /// The page's entries, in lexicographic key order.
+    pub entries: Vec<(String, &'a T)>,
+    /// The last key of the page if more entries follow: pass it as `after`
+    /// for the next page.
+    pub next_after: Option<String>,
+}
+
+/// How a string relates to a trie's keys, from [`Trie::prefix_status`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrefixStatus {
+    /// No stored key starts with the string.
+    NoMatch,
+    /// The string isn't stored, but is a proper prefix of stored keys.
+    InternalOnly,
+    /// The string is stored, and may be a prefix of other keys too.
+    Valued,
+}
+
+/// `Debug` wrapper returned by [`Trie::debug_full`].
+pub struct DebugFull<'a, T, S = RandomState> {
+    trie: &'a Trie<T, S>,
+}
+
+impl<T: fmt::Debug, S: BuildHasher + Default> fmt::Debug for DebugFull<'_, T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[")?;
+        for (i, (key, value)) in self.trie.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{:?} => {:?}", key, value)?;
+        }
+        write!(f, "]")
+    }
+}
+
+impl<T, S> Default for Trie<T, S> {
+    fn default() -> Trie<T, S> {
+        Trie {
+            inner: GenericTrie::default(),
+            normalizer: None,
+            count: 0,
+        }
+    }
+}
+
+impl<K: AsRef<str>, T, S: BuildHasher + Default> FromIterator<(K, T)> for Trie<T, S> {
+    /// Builds a trie from key-value pairs. As with [`Trie::insert`], the
+    /// first value of a repeated key is kept and later ones are dropped.
+    fn from_iter<I: IntoIterator<Item = (K, T)>>(iter: I) -> Trie<T, S> {
+        let mut trie = Trie::default();
+        trie.extend(iter);
+        trie
+    }
+}
+
+impl<K: AsRef<str>, T, S: BuildHasher + Default> Extend<(K, T)> for Trie<T, S> {
+    /// Inserts the pairs in order. Keys already stored keep their values,
+    /// as with [`Trie::insert`].
+    fn extend<I: IntoIterator<Item = (K, T)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key.as_ref(), value);
+        }
+    }
+}
+
+impl<T: PartialEq, S: BuildHasher + Default> PartialEq for Trie<T, S> {
+    // Tries are equal when they store the same entries; normalizers are
+    // function pointers and can't be meaningfully compared
+    fn eq(&self, other: &Trie<T, S>) -> bool {
+        // Equal nodes mean equal entries, but nodes left by remove_lazy
+        // can make equal entries sit in different nodes
+        self.inner == other.inner || self.iter().eq(other.iter())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::Reverse;
+
+    use super::*;
+    use crate::test_util::{check_max_priorities, XorShift64};
+
+    #[test]
+    fn insert_and_get() {
+        let mut trie = Trie::<&str>::new();
+
+        // Random order
+        trie.insert("a", "one");
+        trie.insert("aaa", "three");
+        trie.insert("aaaa", "four");
+        trie.insert("aa", "two");
+
+        assert_eq!(trie.get("a"), Some(&"one"));
+        assert_eq!(trie.get("aaa"), Some(&"three"));
+        assert_eq!(trie.get("aaaa"), Some(&"four"));
+        assert_eq!(trie.get("aa"), Some(&"two"));
+
+        // Duplicate key
+        assert!(!trie.insert("a", "ten"));
+        assert_eq!(trie.get("a"), Some(&"one"));
+    }
+
+    #[test]
+    fn empty_key() {
+        let mut trie = Trie::<u32>::new();
+        assert!(trie.is_empty());
+        assert_eq!(trie.get(""), None);
+        assert_eq!(trie.longest_prefix_match("abc"), None);
+
+        assert!(trie.insert("", 0));
+        assert!(!trie.insert("", 1));
+        assert!(trie.insert("a", 1));
+        assert!(trie.insert("ab", 2));
+        assert!(!trie.is_empty());
+        assert_eq!(trie.len(), 3);
+        assert_eq!(trie.get(""), Some(&0));
+        assert!(trie.contains_key(""));
+        *trie.get_mut("").unwrap() += 10;
+        assert_eq!(trie.get(""), Some(&10));
+
+        assert_eq!(
+            trie.iter_prefix("").collect::<Vec<_>>(),
+            vec![
+                (String::new(), &10),
+                (String::from("a"), &1),
+                (String::from("ab"), &2)
+            ]
+        );
+        assert_eq!(trie.first_key(), Some(String::new()));
+        assert_eq!(trie.rank(""), Some(0));
+        assert_eq!(trie.rank("a"), Some(1));
+        assert_eq!(trie.select(0), Some(String::new()));
+        assert_eq!(trie.range("", "a"), vec![(String::new(), &10)]);
+        assert_eq!(trie.values_along_path("abc"), vec![&10, &1, &2]);
+        assert_eq!(
+            trie.longest_prefix_match("abc"),
+            Some((String::from("ab"), &2))
+        );
+        assert_eq!(trie.longest_prefix_match("b"), Some((String::new(), &10)));
+
+        assert!(trie.remove(""));
+        assert!(!trie.remove(""));
+        assert_eq!(trie.get(""), None);
+        assert_eq!(trie.len(), 2);
+        assert_eq!(trie.get("ab"), Some(&2));
+        assert_eq!(trie.longest_prefix_match("b"), None);
+
+        assert!(trie.insert("", 3));
+        let all = trie.split_off("");
+        assert!(trie.is_empty());
+        assert_eq!(all.get(""), Some(&3));
+        assert_eq!(all.len(), 3);
+
+        // A key the normalizer empties is the empty key
+        let mut trie = Trie::<u32>::new_with_normalizer(|key| key.trim().to_string());
+        assert!(trie.insert("  ", 1));
+        assert_eq!(trie.get(""), Some(&1));
+        assert_eq!(trie.try_insert("\t", 2), Err(TrieError::DuplicateKey));
+    }
+
+    #[test]
+    fn longest_prefix_match() {
+        let mut trie = Trie::<u32>::new();
+        trie.insert("ab", 1);
+        trie.insert("abcd", 2);
+        trie.insert("x", 3);
+        assert_eq!(
+            trie.longest_prefix_match("abcde"),
+            Some((String::from("abcd"), &2))
+        );
+        assert_eq!(
+            trie.longest_prefix_match("abc"),
+            Some((String::from("ab"), &1))
+        );
+        assert_eq!(trie.longest_prefix_match("a"), None);
+        assert_eq!(
+            trie.longest_prefix_match("xyz"),
+            Some((String::from("x"), &3))
+        );
+        assert_eq!(trie.longest_prefix_match(""), None);
+    }
+
+    #[test]
+    fn diverge_point() {
+        let mut trie = Trie::<u32>::new();
+        trie.insert("tea", 0);
+        trie.insert("teapot", 1);
+        trie.insert("ténor", 2);
+
+        // Both follow stored nodes until the strings themselves differ
+        assert_eq!(trie.diverge_point("teapot", "teacup"), 3);
+        assert_eq!(trie.diverge_point("tea", "teapot"), 3);
+        assert_eq!(trie.diverge_point("teapot", "teapot"), 6);
+        // "ten" leaves the trie after "te" even though it matches "tent"
+        assert_eq!(trie.diverge_point("ten", "tent"), 2);
+        assert_eq!(trie.diverge_point("zebra", "zebra"), 0);
+        assert_eq!(trie.diverge_point("ténor", "téno"), 4);
+        assert_eq!(trie.diverge_point("", "tea"), 0);
+    }
+
+    #[test]
+    fn try_insert() {
+        let mut trie = Trie::<u32>::new();
+        assert_eq!(trie.try_insert("ab", 1), Ok(()));
+        assert_eq!(trie.try_insert("a", 2), Ok(()));
+        assert_eq!(trie.try_insert("ab", 4), Err(TrieError::DuplicateKey));
+        assert_eq!(trie.get("ab"), Some(&1));
+
+        let mut trie = Trie::<u32>::new_with_normalizer(|key| key.trim().to_string());
+        assert_eq!(trie.try_insert(" x", 1), Ok(()));
+        assert_eq!(trie.try_insert("x ", 2), Err(TrieError::DuplicateKey));
+
+        assert_eq!(
+            TrieError::DuplicateKey.to_string(),
+            "key already has a value"
+        );
+    }
+
+    #[test]
+    fn remove() {
+        let mut trie = Trie::<&str>::new();
+        trie.insert("a", "one");
+        trie.insert("aaa", "three");
+        trie.insert("aaaa", "four");
+        trie.insert("aa", "two");
+
+        assert!(!trie.remove("aaaaa"));
+        assert!(trie.remove("aaaa"));
+        assert_eq!(trie.get("aaaa"), None);
+        assert!(trie.remove("aaa"));
+        assert_eq!(trie.get("aaa"), None);
+
+        // Reinsert and remove
+        assert!(!trie.remove("a"));
+        trie.insert("aaaa", "four");
+        trie.insert("aaa", "three");
+        assert_eq!(trie.get("aaa"), Some(&"three"));
+        assert_eq!(trie.get("aaaa"), Some(&"four"));
+        assert!(trie.remove("aaaa"));
+        assert_eq!(trie.get("aaaa"), None);
+        assert!(trie.remove("aaa"));
+        assert_eq!(trie.get("aaa"), None);
+
+        // Emptied paths are pruned, and multibyte chars are handled
+        assert!(trie.remove("aa"));
+        assert_eq!(trie.get("a"), Some(&"one"));
+        assert!(trie.remove("a"));
+        assert!(!trie.inner.root.has_children());
+        trie.insert("héé", "accents");
+        assert!(!trie.remove("hé"));
+        assert!(trie.remove("héé"));
+        assert!(!trie.inner.root.has_children());
+    }
+
+    #[test]
+    fn remove_where() {
+        let mut trie = Trie::<u32>::new();
+        for (i, key) in ["a", "ab", "abc", "abd", "b", "bc"].iter().enumerate() {
+            trie.insert(key, i as u32);
+        }
+
+        let mut seen = Vec::new();
+        let removed = trie.remove_where(|key, value| {
+            seen.push(String::from(key));
+            key.starts_with("ab") || *value == 5
+        });
+        assert_eq!(removed, 4);
+        assert_eq!(seen, vec!["a", "ab", "abc", "abd", "b", "bc"]);
+        assert_eq!(
+            trie.iter().collect::<Vec<_>>(),
+            vec![(String::from("a"), &0), (String::from("b"), &4)]
+        );
+        assert_eq!(trie.inner.root.count_nodes(), 3);
+
+        assert_eq!(trie.remove_where(|_, _| false), 0);
+        assert_eq!(trie.remove_where(|_, _| true), 2);
+        assert!(!trie.inner.root.has_children());
+    }
+
+    #[test]
+    fn most_common_prefix_among() {
+        let mut trie = Trie::new();
+        for route in ["/", "/api", "/api/users", "/api/orders", "/static"] {
+            trie.insert(route, ());
+        }
+        let requests = [
+            "/api/users/1",
+            "/api/users/2",
+            "/api/orders/7",
+            "/static/app.js",
+            "/api/users",
+        ];
+        // "/" matches all five requests
+        assert_eq!(
+            trie.most_common_prefix_among(&requests).as_deref(),
+            Some("/")
+        );
+        trie.remove_if("/", |_| true);
+        assert_eq!(
+            trie.most_common_prefix_among(&requests).as_deref(),
+            Some("/api")
+        );
+        // Two each for "/api/users" and "/api", so the longer one wins
+        assert_eq!(
+            trie.most_common_prefix_among(&requests[..2]).as_deref(),
+            Some("/api/users")
+        );
+        // One each: the longest, "/api/orders" over "/static" and "/api"
+        assert_eq!(
+            trie.most_common_prefix_among(&["/static/x", "/api/orders/1"])
+                .as_deref(),
+            Some("/api/orders")
+        );
+        assert_eq!(trie.most_common_prefix_among(&["/ap", "x"]), None);
+        assert_eq!(trie.most_common_prefix_among(&[]), None);
+
+        // Equal count and length: the lexicographically smallest
+        let mut trie = Trie::new();
+        trie.insert("ab", ());
+        trie.insert("ac", ());
+        trie.insert("", ());
+        assert_eq!(
+            trie.most_common_prefix_among(&["acx", "abx"]).as_deref(),
+            Some("")
+        );
+        trie.remove("");
+        assert_eq!(
+            trie.most_common_prefix_among(&["acx", "abx"]).as_deref(),
+            Some("ab")
+        );
+
+        // Against counting each key's matches one by one
+        let mut rng = XorShift64(0x7007_0000_0000_0075);
+        let mut random_key = |max_len| -> String {
+            (0..rng.below(max_len))
+                .map(|_| ['a', 'b', 'é'][rng.below(3)])
+                .collect()
+        };
+        for _ in 0..50 {
+            let mut trie = Trie::new();
+            for _ in 0..10 {
+                trie.insert(&random_key(5), ());
+            }
+            let queries: Vec<String> = (0..8).map(|_| random_key(7)).collect();
+            let queries: Vec<&str> = queries.iter().map(String::as_str).collect();
+            let expected = trie
+                .iter()
+                .map(|(key, _)| {
+                    let count = queries.iter().filter(|q| q.starts_with(&key)).count();
+                    (count, key.chars().count(), Reverse(key))
+                })
+                .filter(|(count, _, _)| *count > 0)
+                .max()
+                .map(|(_, _, Reverse(key))| key);
+            assert_eq!(trie.most_common_prefix_among(&queries), expected);
+        }
+    }
+
+    #[test]
+    fn insert_with() {
+        let mut counts = Trie::new();
+        for word in "the cat and the hat and the bat".split(' ') {
+            counts.insert_with(word, 1, |a, b| a + b);
+        }
+        assert_eq!(counts.get("the"), Some(&3));
+        assert_eq!(counts.get("and"), Some(&2));
+        assert_eq!(counts.get("cat"), Some(&1));
+
+        let mut lists = Trie::new();
+        assert!(!lists.insert_with("k", vec![1], |mut a, b| {
+            a.extend(b);
+            a
+        }));
+        // The merge sees the existing value first
+        assert!(lists.insert_with("k", vec![2, 3], |mut a, b| {
+            a.extend(b);
+            a
+        }));
+        assert!(lists.insert_with("k", vec![0], |a, b| [b, a].concat()));
+        assert_eq!(lists.get("k"), Some(&vec![0, 1, 2, 3]));
+        assert!(!lists.insert_with("", Vec::new(), |_, _| unreachable!()));
+        assert_eq!(lists.len(), 2);
+    }
+
+    #[test]
+    fn rebuild_balanced() {
+        let mut rng = XorShift64(0x2eb0_11d0_0000_0078);
+        let mut trie = Trie::new_with_normalizer(|key: &str| key.to_lowercase());
+        for i in 0..3000 {
+            let key: String = (0..1 + rng.below(4))
+                .map(|_| char::from(b'a' + rng.below(26) as u8))
+                .collect();
+            if rng.below(3) == 0 {
+                trie.remove(&key);
+            } else {
+                trie.insert(&key, i);
+            }
+        }
+        trie.insert("zzz", 0);
+        trie.remove_lazy("zzz");
+
+        let rebuilt = trie.rebuild_balanced();
+        assert!(rebuilt == trie);
+        assert!(rebuilt.iter().eq(trie.iter()));
+        for (key, value) in trie.iter() {
+            assert_eq!(rebuilt.get(&key.to_uppercase()), Some(value));
+        }
+        assert_eq!(rebuilt.tombstone_ratio(), 0.0);
+        assert!(rebuilt.memory_usage().total() < trie.memory_usage().total());
+    }
+
+    #[test]
+    fn len_is_maintained() {
+        let mut rng = XorShift64(0x1e70_c0de_0000_0083);
+        let mut trie = Trie::new();
+        let random_key = |rng: &mut XorShift64| -> String {
+            (0..rng.below(4))
+                .map(|_| char::from(b'a' + rng.below(3) as u8))
+                .collect()
+        };
+        for i in 0..2000 {
+            let key = random_key(&mut rng);
+            match rng.below(11) {
+                0..=2 => {
+                    trie.insert(&key, i);
+                }
+                3 => {
+                    trie.insert_with(&key, i, |a, b| a + b);
+                }
+                4 => {
+                    trie.remove(&key);
+                }
+                5 => {
+                    trie.remove_if(&key, |value| value % 2 == 0);
+                }
+                6 => {
+                    trie.remove_lazy(&key);
+                }
+                7 => {
+                    trie.remove_where(|_, value| value % 7 == 0);
+                }
+                8 => trie.retain_prefixes(&[&key, &random_key(&mut rng)]),
+                9 => {
+                    let mut split = trie.split_off(&key);
+                    assert_eq!(split.len(), split.iter().count());
+                    if rng.below(2) == 0 {
+                        trie.extend(split.iter().map(|(key, value)| (key, *value)));
+                    } else {
+                        split.insert(&key, i);
+                        trie = split;
+                    }
+                }
+                _ => {
+                    let mut inserter = trie.inserter();
+                    key.chars().for_each(|c| inserter.push(c));
+                    inserter.finish(i);
+                }
+            }
+            // len itself checks the count against the nodes in debug builds
+            assert_eq!(trie.len(), trie.iter().count());
+        }
+        trie.compact();
+        assert_eq!(trie.len(), trie.iter().count());
+        assert_eq!(trie.clone_with_map(|value| *value).len(), trie.len());
+        let rebuilt = trie.rebuild_balanced();
+        assert_eq!(rebuilt.len(), trie.len());
+    }
+
+    #[test]
+    fn sorted_vec_round_trip() {
+        let mut trie = Trie::new();
+        for (i, key) in ["a", "aaa", "aaaa", "aa", "", "ab", "b", "été"]
+            .iter()
+            .enumerate()
+        {
+            trie.insert(key, i);
+        }
+        let entries = trie.to_sorted_vec();
+        assert_eq!(entries.len(), 8);
+        assert!(entries.windows(2).all(|pair| pair[0].0 < pair[1].0));
+
+        let rebuilt = Trie::from_sorted_vec(entries);
+        assert!(rebuilt == trie);
+        assert!(rebuilt.inner == trie.inner);
+        assert_eq!(rebuilt.len(), 8);
+        assert_eq!(rebuilt.get("aaa"), Some(&1));
+        assert!(Trie::<u32>::from_sorted_vec(Vec::new()).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "from_sorted_vec called with \"ab\" after \"b\"")]
+    fn from_sorted_vec_unsorted() {
+        Trie::<u32>::from_sorted_vec(vec![(String::from("b"), 0), (String::from("ab"), 1)]);
+    }
+
+    #[test]
+    fn prefix_status() {
+        let mut trie = Trie::new_with_normalizer(|key| key.to_lowercase());
+        for (i, key) in ["a", "aa", "aaa", "aaaa"].iter().enumerate() {
+            trie.insert(key, i);
+        }
+        assert_eq!(trie.prefix_status("ab"), PrefixStatus::NoMatch);
+        assert_eq!(trie.prefix_status("aa"), PrefixStatus::Valued);
+        assert_eq!(trie.prefix_status("AAAA"), PrefixStatus::Valued);
+        assert_eq!(trie.prefix_status("aaaaa"), PrefixStatus::NoMatch);
+        assert_eq!(trie.prefix_status(""), PrefixStatus::InternalOnly);
+
+        trie.insert("abc", 4);
+        assert_eq!(trie.prefix_status("ab"), PrefixStatus::InternalOnly);
+        trie.remove_lazy("abc");
+        assert_eq!(trie.prefix_status("ab"), PrefixStatus::NoMatch);
+        trie.insert("", 5);
+        assert_eq!(trie.prefix_status(""), PrefixStatus::Valued);
+    }
+
+    #[test]
+    fn shrink_to_fit() {
+        let letters = || 'a'..='z';
+        let keys: Vec<String> = letters()
+            .flat_map(|x| letters().flat_map(move |y| letters().map(move |z| [x, y, z])))
+            .map(|chars| chars.iter().collect())
+            .collect();
+        let mut trie: Trie<usize> = keys.iter().enumerate().map(|(i, key)| (key, i)).collect();
+        for (i, key) in keys.iter().enumerate() {
+            if i % 10 != 0 {
+                trie.remove(key);
+            }
+        }
+
+        let before: Vec<(String, usize)> = trie.iter().map(|(key, value)| (key, *value)).collect();
+        let pruned = trie.memory_usage();
+        trie.shrink_to_fit();
+        let shrunk = trie.memory_usage();
+        assert!(trie
+            .iter()
+            .map(|(key, value)| (key, *value))
+            .eq(before.into_iter()));
+        assert_eq!(trie.len(), keys.len().div_ceil(10));
+        assert_eq!(shrunk.node_count, pruned.node_count);
+        // Removals left the surviving parents with their peak capacity
+        assert!(shrunk.children_bytes * 2 < pruned.children_bytes);
+    }
+
+    #[test]
+    fn keys_with_prefix_limited() {
+        let mut trie = Trie::new();
+        let letters = ('a'..='z').map(String::from);
+        for (i, key) in letters
+            .clone()
+            .flat_map(|x| letters.clone().map(move |y| x.clone() + &y))
+            .enumerate()
+        {
+            trie.insert(&key, i);
+        }
+        assert_eq!(trie.keys_with_prefix_limited("b", 3), ["ba", "bb", "bc"]);
+        assert_eq!(trie.keys_with_prefix_limited("", 2), ["aa", "ab"]);
+        assert_eq!(trie.keys_with_prefix_limited("zy", 10), ["zy"]);
+        assert!(trie.keys_with_prefix_limited("b", 0).is_empty());
+        assert!(trie.keys_with_prefix_limited("-", 5).is_empty());
+    }
+
+    #[test]
+    fn top_k_by_prefix() {
+        fn keys<T>(top: Vec<(String, &T)>) -> Vec<String> {
+            top.into_iter().map(|(key, _)| key).collect()
+        }
+
+        let mut trie = Trie::new();
+        for (key, priority) in [
+            ("rust", 90.0),
+            ("rust book", 40.0),
+            ("rust analyzer", 70.0),
+            ("rustup", 70.0),
+            ("ruby", 80.0),
+            ("rugby", 10.0),
+            ("python", 100.0),
+        ] {
+            assert!(trie.insert_with_priority(key, key.len(), priority));
+        }
+        assert!(!trie.insert_with_priority("rust", 0, 1000.0));
+        // Never ranked
+        assert!(trie.insert("rustc", 5));
+        assert_eq!(trie.priority("rust"), Some(90.0));
+        assert_eq!(trie.priority("rustc"), None);
+        assert_eq!(trie.root().max_priority(), Some(&100.0));
+        check_max_priorities(trie.root());
+
+        assert_eq!(
+            keys(trie.top_k_by_prefix("ru", 4)),
+            ["rust", "ruby", "rust analyzer", "rustup"]
+        );
+        assert_eq!(
+            trie.top_k_by_prefix("rust ", 10),
+            [
+                (String::from("rust analyzer"), &13),
+                (String::from("rust book"), &9),
+            ]
+        );
+        assert_eq!(keys(trie.top_k_by_prefix("", 1)), ["python"]);
+        assert!(trie.top_k_by_prefix("ru", 0).is_empty());
+        assert!(trie.top_k_by_prefix("rustc", 3).is_empty());
+        assert!(trie.top_k_by_prefix("java", 3).is_empty());
+
+        // Removal lowers the maxima along the path
+        assert_eq!(trie.remove_if("python", |_| true), Some(6));
+        assert_eq!(trie.root().max_priority(), Some(&90.0));
+        assert!(trie.root().child('p').is_none());
+        assert_eq!(trie.remove_if("rust", |_| true), Some(4));
+        let rust = trie.path_to_node("rust").unwrap();
+        assert_eq!(rust.last().unwrap().max_priority(), Some(&70.0));
+        assert_eq!(trie.len(), 6);
+        assert_eq!(
+            keys(trie.top_k_by_prefix("", 3)),
+            ["ruby", "rust analyzer", "rustup"]
+        );
+        check_max_priorities(trie.root());
+
+        let mut trie = Trie::new_with_normalizer(|key| key.to_lowercase());
+        trie.insert_with_priority("Tea", 1, 1.0);
+        assert_eq!(trie.priority("TEA"), Some(1.0));
+        assert_eq!(keys(trie.top_k_by_prefix("T", 1)), ["tea"]);
+    }
+
+    #[test]
+    fn priorities_kept() {
+        let mut trie = Trie::new();
+        trie.insert_with_priority("a", 1, 1.0);
+        trie.insert_with_priority("ab", 2, 5.0);
+        trie.insert_with_priority("abc", 3, 3.0);
+        trie.insert_with_priority("b", 4, 2.0);
+        trie.insert("bc", 5);
+
+        // Values move, priorities stay with their keys
+        assert!(trie.insert_with("ab", 10, |a, b| a + b));
+        assert_eq!(trie.priority("ab"), Some(5.0));
+        assert!(trie.swap("a", "b"));
+        assert_eq!(trie.get("a"), Some(&4));
+        assert_eq!(trie.priority("a"), Some(1.0));
+        assert_eq!(
+            trie.clone_with_map(|value| *value).priority("ab"),
+            Some(5.0)
+        );
+        let mut trie = trie.map_values(|_, value| value + 1);
+        assert_eq!(trie.priority("ab"), Some(5.0));
+        assert_eq!(trie.rebuild_balanced().priority("abc"), Some(3.0));
+        check_max_priorities(trie.root());
+
+        assert_eq!(trie.remove_where(|key, _| key == "ab"), 1);
+        assert_eq!(trie.root().max_priority(), Some(&3.0));
+        check_max_priorities(trie.root());
+        let split = trie.split_off("a");
+        assert_eq!(split.root().max_priority(), Some(&3.0));
+        assert_eq!(trie.root().max_priority(), Some(&2.0));
+        check_max_priorities(split.root());
+        check_max_priorities(trie.root());
+
+        assert!(trie.remove_lazy("b"));
+        assert_eq!(trie.root().max_priority(), None);
+        trie.insert_with_priority("bcd", 6, 4.0);
+        trie.retain_prefixes(&["bc"]);
+        assert_eq!(trie.root().max_priority(), Some(&4.0));
+        trie.retain_prefixes(&["bc", "x"]);
+        assert!(trie.remove_if("bcd", |_| true).is_some());
+        assert_eq!(trie.root().max_priority(), None);
+        check_max_priorities(trie.root());
+    }
+
+    #[test]
+    fn top_k_matches_sorting() {
+        let mut rng = XorShift64(0x7091_0000_0000_0071);
+        let mut trie = Trie::new();
+        let mut entries = Vec::new();
+        for i in 0..2000 {
+            let key: String = (0..rng.below(6))
+                .map(|_| char::from(b'a' + rng.below(4) as u8))
+                .collect();
+            // Few distinct priorities, to exercise ties
+            let priority = rng.below(8) as f64 - 2.0;
+            match rng.below(8) {
+                0 | 1 => {
+                    if trie.remove_if(&key, |_| true).is_some() {
+                        entries.retain(|(k, _, _)| *k != key);
+                    }
+                }
+                2 => {
+                    if trie.insert(&key, i) {
+                        entries.push((key, i, None));
+                    }
+                }
+                3 if i % 50 == 0 => {
+                    let removed = trie.remove_where(|key, value| key.len() == 2 && value % 2 == 0);
+                    let before = entries.len();
+                    entries.retain(|(key, value, _)| key.len() != 2 || value % 2 != 0);
+                    assert_eq!(removed, before - entries.len());
+                }
+                _ => {
+                    if trie.insert_with_priority(&key, i, priority) {
+                        entries.push((key, i, Some(priority)));
+                    }
+                }
+            }
+            check_max_priorities(trie.root());
+        }
+
+        for prefix in ["", "a", "b", "ab", "dd", "abc"] {
+            let mut expected: Vec<(&String, &usize, f64)> = entries
+                .iter()
+                .filter(|(key, _, _)| key.starts_with(prefix))
+                .filter_map(|(key, value, priority)| Some((key, value, (*priority)?)))
+                .collect();
+            expected.sort_by(|a, b| b.2.total_cmp(&a.2).then_with(|| a.0.cmp(b.0)));
+            for k in [1, 5, 40, 1000] {
+                let want: Vec<(String, &usize)> = expected
+                    .iter()
+                    .take(k)
+                    .map(|(key, value, _)| ((*key).clone(), *value))
+                    .collect();
+                assert_eq!(trie.top_k_by_prefix(prefix, k), want, "{:?} {}", prefix, k);
+            }
+        }
+        assert_eq!(trie.len(), entries.len());
+    }
+
+    #[test]
+    fn keys_starting_with_any() {
+        let mut trie = Trie::new();
+        for key in ["", "a", "app", "apple", "apt", "bat", "bath", "cat", "dog"] {
+            trie.insert(key, ());
+        }
+        let keys = |prefixes: &[&str]| trie.keys_starting_with_any(prefixes).collect::<Vec<_>>();
+        // Nested and repeated prefixes yield each key once, in order
+        assert_eq!(
+            keys(&["app", "ba", "a", "app", "bath"]),
+            ["a", "app", "apple", "apt", "bat", "bath"]
+        );
+        assert_eq!(keys(&["dog", "cat", "x", "do"]), ["cat", "dog"]);
+        assert_eq!(keys(&["", "cat"]).len(), trie.len());
+        assert!(keys(&[]).is_empty());
+        assert!(keys(&["bb", "apples"]).is_empty());
+
+        let mut trie = Trie::new_with_normalizer(|key: &str| key.to_lowercase());
+        trie.insert("Apple", ());
+        trie.insert("apricot", ());
+        assert_eq!(
+            trie.keys_starting_with_any(&["APP", "Ap"])
+                .collect::<Vec<_>>(),
+            ["apple", "apricot"]
+        );
+    }
+
+    #[test]
+    fn remove_if() {
+        let mut trie = Trie::new();
+        trie.insert("doc", 1);
+        trie.insert("document", 3);
+
+        // Stale version: nothing changes
+        assert_eq!(trie.remove_if("document", |&version| version == 2), None);
+        assert_eq!(trie.get("document"), Some(&3));
+        assert_eq!(trie.inner.root.count_nodes(), 9);
+
+        assert_eq!(trie.remove_if("document", |&version| version == 3), Some(3));
+        assert!(!trie.contains_key("document"));
+        // "ument" is pruned, "doc" stays
+        assert_eq!(trie.inner.root.count_nodes(), 4);
+        assert_eq!(trie.get("doc"), Some(&1));
+
+        assert_eq!(trie.remove_if("do", |_| true), None);
+        assert_eq!(trie.remove_if("docs", |_| true), None);
+        assert_eq!(trie.inner.root.count_nodes(), 4);
+        assert_eq!(trie.remove_if("doc", |_| true), Some(1));
+        assert!(trie.is_empty());
+        assert!(!trie.inner.root.has_children());
+    }
+
+    #[test]
+    fn lazy_removal() {
+        let mut trie = Trie::new();
+        for (i, key) in ["car", "card", "care", "cat", "dog"].iter().enumerate() {
+            trie.insert(key, i);
+        }
+        assert_eq!(trie.tombstone_ratio(), 0.0);
+        let nodes = trie.inner.root.count_nodes();
+
+        assert!(trie.remove_lazy("card"));
+        assert!(trie.remove_lazy("care"));
+        assert!(trie.remove_lazy("dog"));
+        assert!(!trie.remove_lazy("dog"));
+        assert!(!trie.remove_lazy("ca"));
+        assert!(!trie.remove_lazy("cards"));
+        // Values are gone, nodes aren't
+        assert_eq!(trie.len(), 2);
+        assert_eq!(trie.get("card"), None);
+        assert_eq!(trie.inner.root.count_nodes(), nodes);
+        assert_eq!(
+            trie.iter().map(|(key, _)| key).collect::<Vec<_>>(),
+            ["car", "cat"]
+        );
+        assert!(!trie.peek_prefix("d"));
+        assert!(!trie.peek_prefix("card"));
+        assert!(trie.peek_prefix("car"));
+        // "d", "e", "d", "o", "g" of 10 nodes
+        assert_eq!(trie.tombstone_ratio(), 0.5);
+        assert_eq!(trie.to_regexp(), "ca(?:r|t)");
+        assert_eq!(trie.match_prefix("dog"), (0, None));
+        assert_eq!(trie.match_prefix("cards").0, 3);
+        // Diagnostics show the tombstones
+        assert_eq!(
+            trie.trace("dog"),
+            [('d', false), ('o', false), ('g', false)]
+        );
+        assert_eq!(trie.debug_structure(), "{c{a{r*{de}t*}}d{o{g}}}");
+
+        // Pruned in place: the surviving nodes aren't rebuilt
+        let ca: *const TrieNode<usize> = trie.root().child('c').unwrap().child('a').unwrap();
+        trie.compact();
+        assert!(std::ptr::eq(
+            ca,
+            trie.root().child('c').unwrap().child('a').unwrap()
+        ));
+        assert_eq!(trie.tombstone_ratio(), 0.0);
+        assert_eq!(trie.inner.root.count_nodes(), 5);
+        assert!(!trie.inner.root.has_child('d'));
+        assert_eq!(trie.get("car"), Some(&0));
+
+        assert!(trie.remove_lazy("car"));
+        assert!(trie.remove_lazy("cat"));
+        assert!(trie.is_empty());
+        assert_eq!(trie.tombstone_ratio(), 0.8);
+        trie.compact();
+        assert!(!trie.inner.root.has_children());
+    }
+
+    #[test]
+    fn from_iter() {
+        let trie: Trie<i32> = [("b", 1), ("a", 2), ("b", 3), ("", 4)]
+            .into_iter()
+            .collect();
+        assert_eq!(
+            trie.iter().collect::<Vec<_>>(),
+            [
+                (String::new(), &4),
+                (String::from("a"), &2),
+                (String::from("b"), &1),
+            ]
+        );
+
+        let mut trie = trie;
+        trie.extend(vec![(String::from("a"), 5), (String::from("ab"), 6)]);
+        assert_eq!(trie.get("a"), Some(&2));
+        assert_eq!(trie.get("ab"), Some(&6));
+    }
+
+    #[test]
+    fn retain_prefixes() {
+        let mut trie = Trie::<u32>::new();
+        for (i, key) in ["a", "aa", "aaa", "aaaa"].iter().enumerate() {
+            trie.insert(key, i as u32);
+        }
+        trie.retain_prefixes(&["aa"]);
+        assert!(!trie.contains_key("a"));
+        assert_eq!(
+            trie.iter().map(|(key, _)| key).collect::<Vec<_>>(),
+            ["aa", "aaa", "aaaa"]
+        );
+
+        let mut trie = Trie::<u32>::new();
+        for (i, key) in ["", "app", "apple", "apt", "bat", "bath", "cat"]
+            .iter()
+            .enumerate()
+        {
+            trie.insert(key, i as u32);
+        }
+        trie.retain_prefixes(&["ap", "bath", "dog", "app"]);
+        assert_eq!(
+            trie.iter().map(|(key, _)| key).collect::<Vec<_>>(),
+            ["app", "apple", "apt", "bath"]
+        );
+        // "cat" is pruned; "bat" loses its value but stays on the way to "bath"
+        assert!(!trie.inner.root.has_child('c'));
+        assert_eq!(trie.inner.root.count_nodes(), 11);
+
+        trie.retain_prefixes(&[""]);
+        assert_eq!(trie.len(), 4);
+        trie.retain_prefixes(&[]);
+        assert!(trie.is_empty());
+        assert!(!trie.inner.root.has_children());
+    }
+
+    #[test]
+    fn keys_matching_and_collect_where() {
+        let mut trie = Trie::<u32>::new();
+        for (i, key) in ["a", "ab", "abc", "b", "bcd", "bcde"].iter().enumerate() {
+            trie.insert(key, i as u32);
+        }
+
+        assert_eq!(
+            trie.keys_matching(|key| key.chars().count() % 2 == 1),
+            vec!["a", "abc", "b", "bcd"]
+        );
+        assert_eq!(
+            trie.keys_matching(|key| key.len() > 4),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            trie.collect_where(|key, value| key.starts_with('b').then(|| value * 10)),
+            vec![30, 40, 50]
+        );
+        assert_eq!(
+            trie.collect_where(|key, value| (value % 2 == 0).then(|| format!("{}={}", key, value))),
+            vec!["a=0", "abc=2", "bcd=4"]
+        );
+    }
+
+    #[test]
+    fn find_all_with() {
+        let mut trie = Trie::<i32>::new();
+        for (key, value) in [("", -1), ("debt", -20), ("gain", 5), ("loss", -3)] {
+            trie.insert(key, value);
+        }
+        assert_eq!(trie.find_all_with(|value| *value < 0), ["", "debt", "loss"]);
+        assert_eq!(trie.find_all_with(|value| *value > 0), ["gain"]);
+        assert!(trie.find_all_with(|value| *value > 100).is_empty());
+
+        let mut notes = Trie::<String>::new();
+        notes.insert("todo", String::from("buy milk"));
+        notes.insert("done", String::from("walk dog"));
+        assert_eq!(notes.find_all_with(|note| note.contains("milk")), ["todo"]);
+        assert!(Trie::<String>::new().find_all_with(|_| true).is_empty());
+    }
+
+    #[test]
+    fn values_along_path() {
+        let mut trie = Trie::<&str>::new();
+        trie.insert("a", "one");
+        trie.insert("aa", "two");
+        trie.insert("aaa", "three");
+        trie.insert("aaaa", "four");
+        trie.insert("ab", "other");
+
+        assert_eq!(
+            trie.values_along_path("aaab"),
+            vec![&"one", &"two", &"three"]
+        );
+        assert_eq!(
+            trie.values_along_path("aaaa"),
+            vec![&"one", &"two", &"three", &"four"]
+        );
+        assert_eq!(trie.values_along_path("ab"), vec![&"one", &"other"]);
+        assert_eq!(trie.values_along_path("b"), Vec::<&&str>::new());
+        assert_eq!(trie.values_along_path(""), Vec::<&&str>::new());
+    }
+
+    #[test]
+    fn path_to_node() {
+        let mut trie = Trie::<&str>::new_with_normalizer(|key| key.to_lowercase());
+        trie.insert("a", "one");
+        trie.insert("aab", "three");
+        trie.insert("ac", "other");
+
+        let path = trie.path_to_node("AAB").unwrap();
+        assert_eq!(
+            path.iter().map(|node| node.key_char()).collect::<String>(),
+            "aab"
+        );
+        assert_eq!(
+            path.iter().map(|node| node.value()).collect::<Vec<_>>(),
+            [Some(&"one"), None, Some(&"three")]
+        );
+        assert_eq!(
+            path.iter()
+                .map(|node| node.child_count())
+                .collect::<Vec<_>>(),
+            [2, 1, 0]
+        );
+        // A path without a value at its end
+        assert_eq!(trie.path_to_node("aa").unwrap().len(), 2);
+        assert_eq!(trie.path_to_node("").unwrap().len(), 0);
+        assert!(trie.path_to_node("aabc").is_none());
+        assert!(trie.path_to_node("b").is_none());
+    }
+
+    #[test]
+    fn to_regexp() {
+        let mut trie = Trie::<()>::new();
+        for key in ["foo", "foobar", "baz"] {
+            trie.insert(key, ());
+        }
+        assert_eq!(trie.to_regexp(), "(?:baz|foo(?:bar)?)");
+
+        let keys = [
+            "a.b", "a", "a+", "(x)", "é|ü", "tea", "ten", "to", "[]", "\\",
+        ];
+        let mut trie = Trie::<()>::new();
+        for key in keys {
+            trie.insert(key, ());
+        }
+        let regexp = regex::Regex::new(&format!("^(?:{})$", trie.to_regexp())).unwrap();
+        for key in keys {
+            assert!(regexp.is_match(key), "{:?} should match", key);
+        }
+        for key in ["", "aXb", "aa", "a++", "x", "é", "te", "tean", "[", "\\\\"] {
+            assert!(!regexp.is_match(key), "{:?} should not match", key);
+        }
+
+        trie.insert("", ());
+        let regexp = regex::Regex::new(&format!("^(?:{})$", trie.to_regexp())).unwrap();
+        assert!(regexp.is_match(""));
+        assert!(regexp.is_match("ten"));
+
+        let regexp =
+            regex::Regex::new(&format!("^(?:{})$", Trie::<()>::new().to_regexp())).unwrap();
+        assert!(!regexp.is_match(""));
+        assert!(!regexp.is_match("a"));
+
+        // One char per level, without recursing
+        std::thread::spawn(|| {
+            let mut trie = Trie::<()>::new();
+            trie.insert(&"a".repeat(100_000), ());
+            trie.insert("ab", ());
+            let regexp = trie.to_regexp();
+            assert!(regexp.starts_with("a(?:"));
+            assert_eq!(regexp.len(), 100_000 + 6);
+        })
+        .join()
+        .unwrap();
+    }
+
+    #[test]
+    fn rank_and_select() {
+        let mut trie = Trie::<u32>::new();
+        trie.insert("b", 3);
+        trie.insert("abc", 2);
+        trie.insert("a", 0);
+        trie.insert("ba", 4);
+        trie.insert("ab", 1);
+
+        assert_eq!(trie.rank("a"), Some(0));
+        assert_eq!(trie.rank("abc"), Some(2));
+        assert_eq!(trie.rank("ba"), Some(4));
+        assert_eq!(trie.rank("bb"), None);
+        assert_eq!(trie.rank(""), None);
+
+        assert_eq!(trie.select(0), Some(String::from("a")));
+        assert_eq!(trie.select(2), Some(String::from("abc")));
+        assert_eq!(trie.select(3), Some(String::from("b")));
+        assert_eq!(trie.select(5), None);
+        for i in 0..5 {
+            assert_eq!(trie.rank(&trie.select(i).unwrap()), Some(i));
+        }
+    }
+
+    #[test]
+    fn rank_and_select_after_mutations() {
+        // Every way of adding or removing keys has to keep the nodes' value
+        // counts right, which len checks in debug builds
+        let mut rng = XorShift64(0x0a7c_0000_0000_0067);
+        let mut trie = Trie::<usize>::new();
+        let mut model = BTreeMap::new();
+        for round in 0..3000 {
+            let key: String = (0..rng.below(5))
+                .map(|_| char::from(b'a' + rng.below(3) as u8))
+                .collect();
+            match rng.below(9) {
+                0..=2 => {
+                    if trie.insert(&key, round) {
+                        model.insert(key, round);
+                    }
+                }
+                3 => {
+                    trie.insert_with(&key, round, |a, b| a + b);
+                    *model.entry(key).or_insert(0) += round;
+                }
+                4 => {
+                    let mut inserter = trie.inserter();
+                    key.chars().for_each(|c| inserter.push(c));
+                    if inserter.finish(round) {
+                        model.insert(key, round);
+                    }
+                }
+                5 => {
+                    assert_eq!(trie.remove_if(&key, |_| true), model.remove(&key));
+                }
+                6 => {
+                    assert_eq!(trie.remove_lazy(&key), model.remove(&key).is_some());
+                }
+                7 if round % 10 == 0 => {
+                    let split = trie.split_off(&key);
+                    let split_model = model.split_off(key.as_str());
+                    let (moved, kept): (BTreeMap<_, _>, BTreeMap<_, _>) = split_model
+                        .into_iter()
+                        .partition(|(k, _)| k.starts_with(key.as_str()));
+                    model.extend(kept);
+                    assert_eq!(split.len(), moved.len());
+                    assert_eq!(split.rank(&key), moved.get(&key).map(|_| 0));
+                }
+                8 if round % 10 == 0 => {
+                    trie.retain_prefixes(&[&key, "b"]);
+                    model.retain(|k, _| k.starts_with(key.as_str()) || k.starts_with('b'));
+                }
+                _ => {
+                    trie.remove_where(|_, value| value % 7 == 0);
+                    model.retain(|_, value| *value % 7 != 0);
+                }
+            }
+
+            assert_eq!(trie.len(), model.len());
+            assert_eq!(trie.select(model.len()), None);
+            if let Some((i, (key, _))) = model.iter().enumerate().nth(rng.below(model.len() + 1)) {
+                assert_eq!(trie.rank(key), Some(i));
+                assert_eq!(trie.select(i).as_ref(), Some(key));
+            }
+        }
+    }
+
+    #[test]
+    fn for_each_entry() {
+        let mut trie = Trie::new_with_normalizer(|key| key.to_lowercase());
+        for (i, key) in ["", "a", "aa", "ab", "b", "été"].iter().enumerate() {
+            trie.insert(key, i);
+        }
+        // Wide enough to keep its children in a HashMap
+        for c in 'a'..='z' {
+            trie.insert(&format!("w{}", c), 100);
+        }
+
+        let mut entries = Vec::new();
+        trie.for_each_entry(|key, value| entries.push((String::from(key), value)));
+        assert_eq!(entries, trie.iter().collect::<Vec<_>>());
+
+        let mut entries = Vec::new();
+        trie.for_each_entry_with_prefix("A", |key, value| entries.push((String::from(key), value)));
+        assert_eq!(entries, trie.iter_prefix("a").collect::<Vec<_>>());
+        trie.for_each_entry_with_prefix("x", |key, _| panic!("{:?}", key));
+        assert_eq!(trie.keys_matching(|key| key.len() > 2), ["été"]);
+    }
+
+    #[test]
+    fn char_iterator_keys() {
+        let mut trie = Trie::new();
+        assert!(trie.insert_iter(std::iter::repeat_n('a', 5), 5));
+        assert!(!trie.insert_iter("aaaaa".chars(), 6));
+        assert_eq!(trie.get("aaaaa"), Some(&5));
+        assert!(trie.insert("abc", 3));
+        // Punctuation filtered out on the fly
+        let filtered = "a-b.c".chars().filter(char::is_ascii_alphanumeric);
+        assert_eq!(trie.get_iter(filtered), Some(&3));
+        assert!(trie.contains_iter(['a'; 5]));
+        assert!(!trie.contains_iter("aaaa".chars()));
+        assert!(trie.insert_iter("".chars(), 0));
+        assert_eq!(trie.get(""), Some(&0));
+        assert_eq!(trie.len(), 3);
+
+        assert!(!trie.remove_iter("ab".chars()));
+        assert!(trie.remove_iter("x-y-abc".chars().skip(4)));
+        assert!(!trie.contains_key("abc"));
+        assert_eq!(trie.len(), 2);
+
+        // A normalizer sees the collected key
+        let mut trie = Trie::new_with_normalizer(|key| key.to_lowercase());
+        assert!(trie.insert_iter("ÉTÉ".chars(), 1));
+        assert_eq!(trie.get("été"), Some(&1));
+        assert_eq!(trie.get_iter("Été".chars()), Some(&1));
+        assert!(trie.remove_iter("éTé".chars()));
+        assert!(trie.is_empty());
+    }
+
+    #[test]
+    fn trace() {
+        let mut trie = Trie::new_with_normalizer(|key| key.to_lowercase());
+        for (i, key) in ["a", "aaa", "ab"].iter().enumerate() {
+            trie.insert(key, i);
+        }
+        assert_eq!(trie.trace("AAA"), [('a', true), ('a', false), ('a', true)]);
+        // Breaks off after "ab"
+        assert_eq!(trie.trace("abc"), [('a', true), ('b', true)]);
+        assert_eq!(trie.trace("aab"), [('a', true), ('a', false)]);
+        assert!(trie.trace("b").is_empty());
+        assert!(trie.trace("").is_empty());
+    }
+
+    #[test]
+    fn from_reader() {
+        let input = "tea\nten\r\n\nto\ntea\n\r\nété";
+        let trie = Trie::from_reader(input.as_bytes(), str::len).unwrap();
+        assert_eq!(
+            trie.iter().collect::<Vec<_>>(),
+            [
+                (String::from("tea"), &3),
+                (String::from("ten"), &3),
+                (String::from("to"), &2),
+                (String::from("été"), &5),
+            ]
+        );
+
+        // Reads through a small buffer, line by line
+        let reader = io::BufReader::with_capacity(4, "a\nbb\nccc\n".as_bytes());
+        let trie = Trie::from_reader(reader, |_| ()).unwrap();
+        assert_eq!(trie.len(), 3);
+
+        let error = Trie::from_reader(&b"ok\n\xff\n"[..], |_| ()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn apply_prefix() {
+        let mut trie = Trie::new_with_normalizer(|key| key.to_lowercase());
+        for key in ["/tmp/a", "/tmp/b/c", "/tmp", "/tm", "/var/tmp/x"] {
+            trie.insert(key, Vec::new());
+        }
+        let mut visited = 0;
+        trie.apply_prefix("/TMP/", |flags: &mut Vec<&str>| {
+            visited += 1;
+            flags.push("expiring");
+        });
+        assert_eq!(visited, 2);
+        assert_eq!(
+            trie.find_all_with(|flags| !flags.is_empty()),
+            ["/tmp/a", "/tmp/b/c"]
+        );
+
+        // The prefix's own value is included, and order is lexicographic
+        let mut order = Vec::new();
+        trie.apply_prefix("/tm", |flags| {
+            order.push(flags.len());
+            flags.clear();
+        });
+        assert_eq!(order, [0, 0, 1, 1]);
+        assert!(trie.iter().all(|(_, flags)| flags.is_empty()));
+        trie.apply_prefix("/nothing", |_| panic!());
+    }
+
+    #[test]
+    fn keys_with_prefix_page() {
+        let mut trie = Trie::new_with_normalizer(|key| key.to_lowercase());
+        let letters = || ['a', 'b', 'é'].into_iter();
+        for x in letters() {
+            for y in letters() {
+                for key in [format!("t{}", x), format!("t{}{}", x, y)] {
+                    trie.insert(&key, key.len());
+                }
+            }
+        }
+        trie.insert("t", 0);
+        trie.insert("u", 0);
+
+        for limit in 1..6 {
+            let mut stitched = Vec::new();
+            let mut after = None;
+            loop {
+                let page = trie.keys_with_prefix_page("T", after.as_deref(), limit);
+                assert!(page.entries.len() <= limit);
+                stitched.extend(page.entries);
+                match page.next_after {
+                    Some(next) => after = Some(next),
+                    None => break,
+                }
+            }
+            assert_eq!(stitched, trie.iter_prefix("t").collect::<Vec<_>>());
+        }
+
+        let keys = |page: Page<'_, usize>| -> Vec<String> {
+            page.entries.into_iter().map(|(key, _)| key).collect()
+        };
+        // After a key that isn't stored, or sorts before or after them all
+        assert_eq!(
+            keys(trie.keys_with_prefix_page("t", Some("tac"), 3)),
+            ["taé", "tb", "tba"]
+        );
+        assert_eq!(
+            keys(trie.keys_with_prefix_page("t", Some("tbéz"), 2)),
+            ["té", "téa"]
+        );
+        assert_eq!(
+            keys(trie.keys_with_prefix_page("tb", Some("a"), 2)),
+            ["tb", "tba"]
+        );
+        assert_eq!(
+            keys(trie.keys_with_prefix_page("ta", Some("tb"), 2)),
+            Vec::<String>::new()
+        );
+        // After the last key: an empty page without a token
+        let page = trie.keys_with_prefix_page("t", Some("téé"), 5);
+        assert!(page.entries.is_empty() && page.next_after.is_none());
+        // An exactly full last page has no token either
+        let page = trie.keys_with_prefix_page("t", Some("téa"), 2);
+        assert_eq!(page.entries.len(), 2);
+        assert_eq!(page.next_after, None);
+
+        let page = trie.keys_with_prefix_page("t", None, 0);
+        assert!(page.entries.is_empty() && page.next_after.is_none());
+        assert!(trie.keys_with_prefix_page("x", None, 5).entries.is_empty());
+    }
+
+    #[test]
+    fn to_maps() {
+        let mut trie = Trie::new();
+        for (i, key) in ["a", "aa", "aaa", "aaaa", ""].iter().enumerate() {
+            trie.insert(key, i);
+        }
+        let entries = [("", 4), ("a", 0), ("aa", 1), ("aaa", 2), ("aaaa", 3)]
+            .map(|(key, value)| (String::from(key), value));
+        assert_eq!(trie.to_btreemap(), BTreeMap::from(entries.clone()));
+        assert_eq!(trie.to_hashmap(), HashMap::from(entries));
+        assert!(Trie::<u32>::new().to_hashmap().is_empty());
+    }
+
+    #[test]
+    fn difference() {
+        let mut trie = Trie::new();
+        for (i, key) in ["", "a", "aa", "aaa", "ab", "b", "ba"].iter().enumerate() {
+            trie.insert(key, i);
+        }
+        let mut other = Trie::new();
+        for key in ["a", "aaa", "abc", "c", "ba"] {
+            other.insert(key, String::from(key));
+        }
+        assert_eq!(
+            trie.difference(&other).collect::<Vec<_>>(),
+            [
+                (String::new(), &0),
+                (String::from("aa"), &2),
+                (String::from("ab"), &4),
+                (String::from("b"), &5),
+            ]
+        );
+        assert_eq!(
+            other
+                .difference(&trie)
+                .map(|(key, _)| key)
+                .collect::<Vec<_>>(),
+            ["abc", "c"]
+        );
+        assert!(trie.difference(&trie).next().is_none());
+        assert!(trie.difference(&Trie::<()>::new()).eq(trie.iter()));
+
+        // Lazily removed keys leave nodes but no value behind
+        other.remove_lazy("ba");
+        assert_eq!(trie.difference(&other).last().unwrap().0, "ba");
+    }
+
+    #[test]
+    fn map_values() {
+        let mut trie = Trie::new_with_normalizer(|key| key.to_lowercase());
+        for (key, value) in [
+            ("a", "1"),
+            ("aaa", "3"),
+            ("ab", "12"),
+            ("", "0"),
+            ("été", "5"),
+        ] {
+            trie.insert(key, String::from(value));
+        }
+        // Wide enough to keep its children in a HashMap
+        for c in 'a'..='z' {
+            trie.insert(&format!("w{}", c), c.to_string().len().to_string());
+        }
+        trie.insert(&"x".repeat(100_000), String::from("7"));
+        let keys: Vec<String> = trie.iter().map(|(key, _)| key).collect();
+        let nodes = trie.root().count_nodes();
+
+        let mut seen = Vec::new();
+        let parsed = trie.map_values(|key, value| {
+            seen.push(String::from(key));
+            value.parse::<u32>().unwrap()
+        });
+        assert_eq!(seen, keys);
+        assert_eq!(parsed.iter().map(|(key, _)| key).collect::<Vec<_>>(), keys);
+        assert_eq!(parsed.root().count_nodes(), nodes);
+        assert_eq!(parsed.get("AB"), Some(&12));
+        assert_eq!(parsed.len(), keys.len());
+
+        let mut trie = Trie::new();
+        for (key, value) in [("a", "1"), ("ab", "x"), ("b", "y")] {
+            trie.insert(key, String::from(value));
+        }
+        let error = trie
+            .try_map_values(|_, value| value.parse::<u32>())
+            .unwrap_err();
+        assert_eq!(error.0, "ab");
+    }
+
+    #[test]
+    fn get_many() {
+        let mut trie = Trie::new_with_normalizer(|key| key.to_lowercase());
+        for (i, key) in ["a", "aa", "aaa", "aaaa", "ab", "été", "b"]
+            .iter()
+            .enumerate()
+        {
+            trie.insert(key, i);
+        }
+        let keys = [
+            "aaaa", "AA", "abc", "", "a", "été", "ét", "aaaa", "b", "aab", "zz", "Ab", "aaaaa",
+        ];
+        let values = trie.get_many(&keys);
+        assert_eq!(values.len(), keys.len());
+        for (key, value) in keys.iter().zip(values) {
+            assert_eq!(value, trie.get(key), "{:?}", key);
+        }
+        assert_eq!(trie.get_many(&["ab", "x", "a"]), [Some(&4), None, Some(&0)]);
+        assert!(trie.get_many(&[]).is_empty());
+    }
+
+    #[test]
+    fn get_many_mut() {
+        let mut trie = Trie::<u32>::new();
+        trie.insert("a", 1);
+        trie.insert("ab", 2);
+        trie.insert("b", 3);
+
+        let [a, ab, missing, b] = trie.get_many_mut(["a", "ab", "abc", "b"]);
+        assert_eq!(missing, None);
+        let (a, ab, b) = (a.unwrap(), ab.unwrap(), b.unwrap());
+        std::mem::swap(a, b);
+        *ab += 10;
+        assert_eq!(trie.get("a"), Some(&3));
+        assert_eq!(trie.get("ab"), Some(&12));
+        assert_eq!(trie.get("b"), Some(&1));
+
+        // The root value, a key and its extension, and siblings, all held
+        // at once
+        trie.insert("", 0);
+        let [ab, root, b, a] = trie.get_many_mut(["ab", "", "b", "a"]).map(Option::unwrap);
+        *root += 100;
+        *a += 100;
+        *ab += 100;
+        *b += 100;
+        assert_eq!(
+            trie.values_with_prefix("").copied().collect::<Vec<_>>(),
+            [100, 103, 112, 101]
+        );
+
+        let duplicate = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            trie.get_many_mut(["a", "b", "a"]);
+        }));
+        assert!(duplicate.is_err());
+    }
+
+    #[test]
+    fn and_modify() {
+        let mut trie = Trie::<u32>::new();
+        trie.and_modify("foo", |v| *v += 1).or_insert("foo", 0);
+        assert_eq!(trie.get("foo"), Some(&0));
+        trie.and_modify("foo", |v| *v += 1).or_insert("foo", 0);
+        trie.and_modify("foo", |v| *v += 1)
+            .and_modify("fo", |v| *v += 1);
+        assert_eq!(trie.get("foo"), Some(&2));
+        assert_eq!(trie.get("fo"), None);
+        assert_eq!(*trie.or_insert("", 5), 5);
+        assert_eq!(*trie.or_insert("", 6), 5);
+    }
+
+    #[test]
+    fn peek_prefix() {
+        let mut trie = Trie::new();
+        assert!(!trie.peek_prefix(""));
+        trie.insert("app/config", 1);
+        trie.insert("app/data", 2);
+        assert!(trie.peek_prefix(""));
+        assert!(trie.peek_prefix("app/"));
+        assert!(trie.peek_prefix("app/config"));
+        assert!(!trie.peek_prefix("app/config/"));
+        assert!(!trie.peek_prefix("lib"));
+
+        trie.remove("app/config");
+        trie.remove("app/data");
+        assert!(!trie.peek_prefix("app"));
+        assert!(!trie.peek_prefix(""));
+        trie.insert("", 3);
+        assert!(trie.peek_prefix(""));
+
+        let mut trie = Trie::new_with_normalizer(|key: &str| key.to_lowercase());
+        trie.insert("Users/Alice", ());
+        assert!(trie.peek_prefix("USERS/"));
+    }
+
+    #[test]
+    fn normalizer() {
+        fn trim_lowercase(key: &str) -> String {
+            key.trim().to_lowercase()
+        }
+
+        let mut trie = Trie::<u32>::new_with_normalizer(trim_lowercase);
+        assert!(trie.insert("  Hello ", 1));
+        assert!(!trie.insert("HELLO", 2));
+        assert_eq!(trie.get("hello"), Some(&1));
+        assert_eq!(trie.get(" hElLo"), Some(&1));
+        assert!(trie.contains_key("Hello\t"));
+        assert!(trie.insert("   ", 3));
+        assert_eq!(trie.get(""), Some(&3));
+        assert!(trie.insert("World", 4));
+        assert_eq!(trie.rank(" WORLD "), Some(2));
+
+        let duplicate = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            trie.get_many_mut(["hello", "HELLO"]);
+        }));
+        assert!(duplicate.is_err());
+
+        assert!(trie.remove("HeLLo  "));
+        assert!(!trie.contains_key("hello"));
+        assert!(trie.contains_key("world"));
+    }
+
+    #[test]
+    fn split_off() {
+        let keys = ["a", "aa", "aaa", "aaaa", "ab", "b"];
+        let mut trie = Trie::<usize>::new();
+        for (i, key) in keys.iter().enumerate() {
+            trie.insert(key, i);
+        }
+
+        let split = trie.split_off("aa");
+        for (i, key) in keys.iter().enumerate() {
+            let in_split = key.starts_with("aa");
+            assert_eq!(split.get(key), if in_split { Some(&i) } else { None });
+            assert_eq!(trie.get(key), if in_split { None } else { Some(&i) });
+        }
+        assert!(!trie.inner.root.child('a').unwrap().has_child('a'));
+        assert_eq!(split.inner.root.count_values(), 3);
+        assert_eq!(trie.inner.root.count_values(), 3);
+
+        let empty = trie.split_off("zz");
+        assert!(!empty.inner.root.has_children());
+        assert_eq!(trie.inner.root.count_values(), 3);
+
+        // Ancestors left without values or children are pruned
+        let mut trie = Trie::<u32>::new();
+        trie.insert("xyz", 1);
+        let mut split = trie.split_off("xy");
+        assert!(!trie.inner.root.has_children());
+        assert_eq!(split.get("xyz"), Some(&1));
+
+        let all = split.split_off("");
+        assert!(!split.inner.root.has_children());
+        assert_eq!(all.get("xyz"), Some(&1));
+    }
+
+    #[test]
+    fn swap() {
+        let mut trie = Trie::<&str>::new();
+        trie.insert("a", "one");
+        trie.insert("aa", "two");
+        trie.insert("b", "three");
+        assert!(trie.swap("a", "b"));
+        assert_eq!(trie.get("a"), Some(&"three"));
+        assert_eq!(trie.get("aa"), Some(&"two"));
+        assert_eq!(trie.get("b"), Some(&"one"));
+        assert!(!trie.swap("a", "ab"));
+        assert!(!trie.swap("ab", "aa"));
+        assert_eq!(trie.get("a"), Some(&"three"));
+        assert_eq!(trie.get("aa"), Some(&"two"));
+        assert!(trie.swap("aa", "aa"));
+        assert!(!trie.swap("c", "c"));
+        trie.insert("", "four");
+        assert!(trie.swap("", "aa"));
+        assert_eq!(trie.get(""), Some(&"two"));
+        assert_eq!(trie.get("aa"), Some(&"four"));
+        assert_eq!(trie.len(), 4);
+        assert_eq!(trie.rank("b"), Some(3));
+    }
+
+    #[test]
+    fn clone_with_map() {
+        // Not Clone
+        struct Account {
+            balance: u32,
+        }
+
+        let mut trie = Trie::new();
+        for (i, key) in ["", "a", "ab", "b"].iter().enumerate() {
+            trie.insert(
+                key,
+                Account {
+                    balance: i as u32 * 10,
+                },
+            );
+        }
+        // Wide enough to use the map-backed children
+        for c in 'c'..='z' {
+            trie.insert(&format!("x{}", c), Account { balance: 1 });
+        }
+
+        let mut calls = 0;
+        let labels = trie.clone_with_map(|account| {
+            calls += 1;
+            format!("${}", account.balance)
+        });
+        assert_eq!(calls, trie.len());
+        assert_eq!(labels.get(""), Some(&String::from("$0")));
+        assert_eq!(labels.get("ab"), Some(&String::from("$20")));
+        assert_eq!(labels.get("xq"), Some(&String::from("$1")));
+        assert_eq!(labels.root().count_nodes(), trie.root().count_nodes());
+        assert_eq!(
+            labels.iter().map(|(key, _)| key).collect::<Vec<_>>(),
+            trie.iter().map(|(key, _)| key).collect::<Vec<_>>()
+        );
+        assert_eq!(trie.get("b").unwrap().balance, 30);
+    }
+
+    #[test]
+    fn depth_first_order() {
+        let mut trie = Trie::new();
+        // Inserted out of order, with a node wide enough for map-backed
+        // children
+        for c in ('a'..='z').rev() {
+            trie.insert(&format!("m{}", c), c as u32);
+        }
+        trie.insert("z", 0);
+        trie.insert("m", 1);
+        trie.insert("", 2);
+        trie.insert("a", 3);
+
+        let order = trie.depth_first_order();
+        let mut expected = vec![(String::new(), &2), (String::from("a"), &3)];
+        expected.push((String::from("m"), &1));
+        let values: Vec<u32> = ('a'..='z').map(|c| c as u32).collect();
+        for (c, value) in ('a'..='z').zip(&values) {
+            expected.push((format!("m{}", c), value));
+        }
+        expected.push((String::from("z"), &0));
+        assert_eq!(order, expected);
+        assert!(Trie::<u32>::new().depth_first_order().is_empty());
+    }
+
+    #[test]
+    fn memory_estimate() {
+        // The key slot is what an entry adds to the node it holds
+        assert_eq!(
+            std::mem::size_of::<(char, TrieNode<u64>)>(),
+            std::mem::size_of::<TrieNode<u64>>() + CHILD_SLOT_OVERHEAD
+        );
+        let node_size = std::mem::size_of::<TrieNode<u64>>() + CHILD_SLOT_OVERHEAD;
+        let mut trie = Trie::<u64>::new();
+        assert_eq!(trie.memory_estimate(), node_size);
+        trie.insert("abc", 1);
+        assert_eq!(trie.memory_estimate(), 4 * node_size);
+        trie.insert("ab", 2);
+        assert_eq!(trie.memory_estimate(), 4 * node_size);
+        trie.insert("abd", 3);
+        assert_eq!(trie.memory_estimate(), 5 * node_size);
+    }
+
+    #[test]
+    fn first_and_last_key() {
+        let mut trie = Trie::<u32>::new();
+        assert_eq!(trie.first_key(), None);
+        assert_eq!(trie.last_key(), None);
+        trie.insert("ab", 1);
+        trie.insert("a", 2);
+        trie.insert("z", 3);
+        trie.insert("mno", 4);
+        assert_eq!(trie.first_key(), Some(String::from("a")));
+        assert_eq!(trie.last_key(), Some(String::from("z")));
+        trie.insert("zz", 5);
+        assert_eq!(trie.last_key(), Some(String::from("zz")));
+        trie.split_off("z");
+        trie.split_off("a");
+        assert_eq!(trie.first_key(), Some(String::from("mno")));
+        assert_eq!(trie.last_key(), Some(String::from("mno")));
+    }
+
+    #[test]
+    fn longest_and_shortest_key() {
+        let mut trie = Trie::<u32>::new();
+        assert_eq!(trie.longest_key(), None);
+        assert_eq!(trie.shortest_key(), None);
+
+        for key in ["tea", "to", "inn", "é", "teapot", "zebras"] {
+            trie.insert(key, 0);
+        }
+        assert_eq!(trie.longest_key(), Some(String::from("teapot")));
+        // Length is in chars, so "é" (two bytes) is the shortest
+        assert_eq!(trie.shortest_key(), Some(String::from("é")));
+
+        trie.insert("", 0);
+        assert_eq!(trie.shortest_key(), Some(String::new()));
+        trie.remove("");
+        trie.remove("é");
+        assert_eq!(trie.shortest_key(), Some(String::from("to")));
+    }
+
+    #[test]
+    fn match_prefix() {
+        let mut trie = Trie::<u32>::new();
+        for key in ["help", "hello", "héros"] {
+            trie.insert(key, 0);
+        }
+        // "hel" matches, then 'x' leaves the trie
+        let (matched, node) = trie.match_prefix("helxo");
+        assert_eq!(matched, 3);
+        let node = node.unwrap();
+        assert_eq!(node.key_char(), 'l');
+        assert!(std::ptr::eq(
+            node,
+            trie.root()
+                .child('h')
+                .unwrap()
+                .child('e')
+                .unwrap()
+                .child('l')
+                .unwrap()
+        ));
+        let mut completions: Vec<char> = node.child_chars().collect();
+        completions.sort();
+        assert_eq!(completions, ['l', 'p']);
+
+        // Counted in chars
+        let (matched, node) = trie.match_prefix("hér");
+        assert_eq!((matched, node.unwrap().key_char()), (3, 'r'));
+        assert_eq!(trie.match_prefix("hello world").0, 5);
+        let (matched, node) = trie.match_prefix("xyz");
+        assert_eq!(matched, 0);
+        assert!(node.is_none());
+        assert!(trie.match_prefix("").1.is_none());
+    }
+
+    #[test]
+    fn invert() {
+        let mut trie = Trie::<u32>::new();
+        for (key, category) in [("pear", 7), ("apple", 7), ("kiwi", 3), ("plum", 7)] {
+            trie.insert(key, category);
+        }
+        let index = trie.invert();
+        assert_eq!(index.len(), 2);
+        assert_eq!(index[&7], ["apple", "pear", "plum"]);
+        assert_eq!(index[&3], ["kiwi"]);
+        assert!(!index.contains_key(&1));
+
+        assert_eq!(trie.keys_for_value(&7), ["apple", "pear", "plum"]);
+        assert_eq!(trie.keys_for_value(&3), ["kiwi"]);
+        assert!(trie.keys_for_value(&1).is_empty());
+        assert!(Trie::<u32>::new().invert().is_empty());
+    }
+
+    #[test]
+    fn into_inverted() {
+        let mut trie = Trie::<&str>::new();
+        for (key, value) in [
+            ("en", "english"),
+            ("fr", "french"),
+            ("eng", "english"),
+            ("", "none"),
+        ] {
+            trie.insert(key, value);
+        }
+        let inverted = trie.into_inverted();
+        assert_eq!(
+            inverted.iter().collect::<Vec<_>>(),
+            vec![
+                (String::from("english"), &String::from("en")),
+                (String::from("french"), &String::from("fr")),
+                (String::from("none"), &String::new()),
+            ]
+        );
+
+        // Old keys come out as stored, i.e. normalized
+        let mut trie = Trie::<String>::new_with_normalizer(|key: &str| key.to_lowercase());
+        trie.insert("Key", String::from("Value"));
+        let inverted = trie.into_inverted();
+        assert_eq!(inverted.get("Value"), Some(&String::from("key")));
+        assert_eq!(inverted.get("value"), None);
+        assert!(Trie::<String>::new().into_inverted().is_empty());
+    }
+
+    #[test]
+    fn topological_order() {
+        let mut trie = Trie::<u32>::new();
+        assert_eq!(trie.topological_order().next(), None);
+        for key in [
+            "src/main.rs",
+            "src",
+            "b",
+            "src/lib.rs",
+            "é",
+            "",
+            "ab",
+            "src/bin",
+        ] {
+            trie.insert(key, 0);
+        }
+        assert_eq!(
+            trie.topological_order().collect::<Vec<_>>(),
+            [
+                "",
+                "b",
+                "é",
+                "ab",
+                "src",
+                "src/bin",
+                "src/lib.rs",
+                "src/main.rs"
+            ]
+        );
+    }
+
+    #[test]
+    fn deep_drop() {
+        std::thread::spawn(|| {
+            let key = "a".repeat(1_000_000);
+            let mut trie = Trie::<u32>::new();
+            assert!(trie.insert(&key, 1));
+            assert_eq!(trie.get(&key), Some(&1));
+            drop(trie);
+        })
+        .join()
+        .unwrap();
+    }
+
+    #[test]
+    fn deep_remove() {
+        std::thread::spawn(|| {
+            let key = "a".repeat(1_000_000);
+            let mut trie = Trie::<u32>::new();
+            trie.insert(&key, 1);
+            trie.insert(&key[..10], 2);
+            assert!(!trie.remove(&key[..10]));
+            assert!(trie.remove(&key));
+            assert_eq!(trie.root().count_nodes(), 11);
+            trie.insert(&key, 1);
+            assert_eq!(trie.remove_if(&key[..10], |_| true), Some(2));
+            assert_eq!(trie.remove_if(&key, |_| true), Some(1));
+            assert!(trie.is_empty());
+            assert_eq!(trie.root().count_nodes(), 1);
+        })
+        .join()
+        .unwrap();
+    }
+
+    #[test]
+    fn deep_priority() {
+        std::thread::spawn(|| {
+            let key = "a".repeat(1_000_000);
+            let mut trie = Trie::new();
+            trie.insert_with_priority(&key, 1, 1.0);
+            trie.insert_with_priority(&key[..10], 2, 0.5);
+            assert_eq!(trie.root().max_priority(), Some(&1.0));
+            assert_eq!(trie.remove_if(&key, |_| true), Some(1));
+            assert_eq!(trie.root().max_priority(), Some(&0.5));
+            assert_eq!(trie.top_k_by_prefix("", 1), [(key[..10].to_string(), &2)]);
+            trie.insert_with_priority(&key, 3, 1.0);
+        })
+        .join()
+        .unwrap();
+    }
+
+    #[test]
+    fn deep_compact() {
+        std::thread::spawn(|| {
+            let key = "a".repeat(1_000_000);
+            let mut trie = Trie::<u32>::new();
+            trie.insert(&key, 1);
+            trie.insert(&key[..10], 2);
+            assert!(trie.remove_lazy(&key));
+            assert!(trie.tombstone_ratio() > 0.99);
+            trie.compact();
+            assert_eq!(trie.tombstone_ratio(), 0.0);
+            assert_eq!(trie.root().count_nodes(), 11);
+
+            trie.insert(&key, 1);
+            assert_eq!(trie.remove_where(|key, _| key.len() > 10), 1);
+            assert_eq!(trie.root().count_nodes(), 11);
+        })
+        .join()
+        .unwrap();
+    }
+
+    #[test]
+    fn drop_count() {
+        struct DropCounter(std::rc::Rc<std::cell::Cell<usize>>);
+
+        impl Drop for DropCounter {
+            fn drop(&mut self) {
+                self.0.set(self.0.get() + 1);
+            }
+        }
+
+        let drops = std::rc::Rc::new(std::cell::Cell::new(0));
+        let mut trie = Trie::new();
+        let keys: Vec<String> = (0..100).map(|i| format!("{:b}", i)).collect();
+        for key in &keys {
+            assert!(trie.insert(key, DropCounter(drops.clone())));
+        }
+        assert!(!trie.insert("1", DropCounter(drops.clone())));
+        assert_eq!(drops.get(), 1);
+        assert!(trie.remove("1100011"));
+        assert_eq!(drops.get(), 2);
+        drop(trie);
+        assert_eq!(drops.get(), keys.len() + 1);
+    }
+
+    #[test]
+    fn range() {
+        let words = [
+            "apple", "apricot", "banana", "band", "bandana", "can", "cane", "dog",
+        ];
+        let mut trie = Trie::<usize>::new();
+        for (i, word) in words.iter().enumerate() {
+            trie.insert(word, i);
+        }
+        let range_keys = |start, end| {
+            trie.range(start, end)
+                .into_iter()
+                .map(|(key, _)| key)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(range_keys("b", "c"), vec!["banana", "band", "bandana"]);
+        assert_eq!(range_keys("ban", "band"), vec!["banana"]);
+        assert_eq!(range_keys("band", "cane"), vec!["band", "bandana", "can"]);
+        assert_eq!(range_keys("apricots", "bana"), Vec::<String>::new());
+        assert_eq!(range_keys("c", "c"), Vec::<String>::new());
+        assert_eq!(range_keys("d", "a"), Vec::<String>::new());
+        assert_eq!(range_keys("", "z"), words);
+        assert_eq!(trie.range("dog", "dogs"), vec![(String::from("dog"), &7)]);
+    }
+
+    #[test]
+    fn debug() {
+        let mut trie = Trie::<&str>::new();
+        assert_eq!(
+            format!("{:?}", trie),
+            "Trie { keys: 0, nodes: 1, max_depth: 0, entries: [] }"
+        );
+        trie.insert("a", "one");
+        trie.insert("aaa", "three");
+        trie.insert("b", "two");
+        assert_eq!(
+            format!("{:?}", trie),
+            r#"Trie { keys: 3, nodes: 5, max_depth: 3, entries: ["a" => "one", "aaa" => "three", "b" => "two"] }"#
+        );
+        assert_eq!(
+            format!("{:?}", trie.debug_full()),
+            r#"["a" => "one", "aaa" => "three", "b" => "two"]"#
+        );
+
+        let mut trie = Trie::<usize>::new();
+        for i in 0..10 {
+            trie.insert(&i.to_string(), i);
+        }
+        assert_eq!(
+            format!("{:?}", trie),
+            r#"Trie { keys: 10, nodes: 11, max_depth: 1, entries: ["0" => 0, "1" => 1, "2" => 2, "3" => 3, "4" => 4, "5" => 5, "6" => 6, "7" => 7, ..] }"#
+        );
+        assert_eq!(
+            format!("{:?}", trie.debug_full()),
+            r#"["0" => 0, "1" => 1, "2" => 2, "3" => 3, "4" => 4, "5" => 5, "6" => 6, "7" => 7, "8" => 8, "9" => 9]"#
+        );
+    }
+
+    #[test]
+    fn debug_structure() {
+        let mut trie = Trie::<u32>::new();
+        assert_eq!(trie.debug_structure(), "{}");
+
+        for key in ["ac", "a", "ab", "b"] {
+            trie.insert(key, 0);
+        }
+        assert_eq!(trie.debug_structure(), "{a*{b*c*}b*}");
+
+        // A node without a value and the empty key
+        trie.insert("xyz", 0);
+        trie.insert("", 0);
+        assert_eq!(trie.debug_structure(), "*{a*{b*c*}b*x{y{z*}}}");
+
+        // Syntax chars are escaped
+        let mut special = Trie::<u32>::new();
+        for key in ["{", "*}", r"\"] {
+            special.insert(key, 0);
+        }
+        assert_eq!(special.debug_structure(), r"{\*{\}*}\\*\{*}");
+
+        // Same dump regardless of insertion order or child representation
+        let keys: Vec<String> = ('a'..='z').map(|c| format!("k{}", c)).collect();
+        let mut forward = Trie::<u32>::new();
+        let mut backward = Trie::<u32>::new();
+        for key in &keys {
+            forward.insert(key, 0);
+        }
+        for key in keys.iter().rev() {
+            backward.insert(key, 0);
+        }
+        let dump = forward.debug_structure();
+        assert_eq!(dump, backward.debug_structure());
+        assert_eq!(dump, forward.debug_structure());
+        assert!(dump.starts_with("{k{a*b*c*"));
+    }
+
+    #[test]
+    fn deep_debug() {
+        std::thread::spawn(|| {
+            let mut trie = Trie::<u32>::new();
+            trie.insert(&"x".repeat(100_000), 1);
+            let summary = format!("{:?}", trie);
+            assert!(summary.starts_with("Trie { keys: 1, nodes: 100001, max_depth: 100000, "));
+            assert_eq!(format!("{:?}", trie.debug_full()).len(), 100_000 + 9);
+        })
+        .join()
+        .unwrap();
+    }
+}
@@ -0,0 +1,366 @@
+use std::cmp::Ordering;
+
+// Index of a node in TstTrie::nodes. Slot 0 is a placeholder that no link
+// points to, so 0 can stand for "no node".
+type NodeId = u32;
+
+const NIL: NodeId = 0;
+
+// Node of a ternary search tree: one char of a key, compared against to
+// branch left (lo, smaller char at this position), right (hi) or down to the
+// next position (eq)
+#[derive(Debug, PartialEq)]
+struct TstNode<T> {
+    split: char,
+    lo: NodeId,
+    eq: NodeId,
+    hi: NodeId,
+    value: Option<T>,
+}
+
+impl<T> TstNode<T> {
+    fn new(split: char) -> TstNode<T> {
+        TstNode {
+            split,
+            lo: NIL,
+            eq: NIL,
+            hi: NIL,
+            value: None,
+        }
+    }
+}
+
+/// Ternary search tree, a compact alternative to [`Trie`](crate::Trie):
+/// each node holds one char and three child links instead of a child
+/// container, at the cost of a few more comparisons per char. Nodes live in
+/// one `Vec`, like [`ArenaTrie`](crate::ArenaTrie)'s.
+///
+/// Queries answer exactly as the same calls on a [`Trie`](crate::Trie)
+/// without a normalizer, including for the empty key.
+#[derive(Debug)]
+pub struct TstTrie<T> {
+    nodes: Vec<TstNode<T>>,
+    root: NodeId,
+    // The empty key has no char to hang a node on
+    empty_value: Option<T>,
+}
+
+impl<T> Default for TstTrie<T> {
+    fn default() -> TstTrie<T> {
+        TstTrie::new()
+    }
+}
+
+impl<T> TstTrie<T> {
+    /// Creates an empty trie.
+    pub fn new() -> TstTrie<T> {
+        TstTrie {
+            nodes: vec![TstNode::new('\0')],
+            root: NIL,
+            empty_value: None,
+        }
+    }
+
+    fn node(&self, id: NodeId) -> &TstNode<T> {
+        &self.nodes[id as usize]
+    }
+
+    // The lo, eq or hi link of a node, for a char comparing less, equal or
+    // greater than its split char
+    fn link_mut(&mut self, id: NodeId, branch: Ordering) -> &mut NodeId {
+        let node = &mut self.nodes[id as usize];
+        match branch {
+            Ordering::Less => &mut node.lo,
+            Ordering::Equal => &mut node.eq,
+            Ordering::Greater => &mut node.hi,
+        }
+    }
+
+    fn alloc(&mut self, split: char) -> NodeId {
+        self.nodes.push(TstNode::new(split));
+        (self.nodes.len() - 1) as NodeId
+    }
+
+    /// Inserts `value` under `key`. Returns false, leaving the trie
+    /// untouched, if `key` is already stored.
+    pub fn insert(&mut self, key: &str, value: T) -> bool {
+        let mut chars = key.chars();
+        let mut c = match chars.next() {
+            Some(c) => c,
+            None if self.empty_value.is_some() => return false,
+            None => {
+                self.empty_value = Some(value);
+                return true;
+            }
+        };
+
+        if self.root == NIL {
+            self.root = self.alloc(c);
+        }
+        let mut id = self.root;
+        loop {
+            let branch = c.cmp(&self.node(id).split);
+            if branch == Ordering::Equal {
+                match chars.next() {
+                    Some(next_c) => c = next_c,
+                    None => break,
+                }
+            }
+            let next = *self.link_mut(id, branch);
+            id = if next == NIL {
+                let child = self.alloc(c);
+                *self.link_mut(id, branch) = child;
+                child
+            } else {
+                next
+            };
+        }
+
+        let value_slot = &mut self.nodes[id as usize].value;
+        if value_slot.is_some() {
+            return false;
+        }
+        *value_slot = Some(value);
+        true
+    }
+
+    // Node for the last char of the non-empty `key`
+    fn find_node(&self, key: &str) -> Option<NodeId> {
+        let mut chars = key.chars().peekable();
+        let mut id = self.root;
+        while let Some(&c) = chars.peek() {
+            if id == NIL {
+                return None;
+            }
+            let node = self.node(id);
+            if c < node.split {
+                id = node.lo;
+            } else if c > node.split {
+                id = node.hi;
+            } else {
+                chars.next();
+                if chars.peek().is_none() {
+                    return Some(id);
+                }
+                id = node.eq;
+            }
+        }
+        None
+    }
+
+    /// The value stored under `key`, if any.
+    pub fn get(&self, key: &str) -> Option<&T> {
+        if key.is_empty() {
+            return self.empty_value.as_ref();
+        }
+        self.node(self.find_node(key)?).value.as_ref()
+    }
+
+    /// Returns true if `key` is stored in the trie.
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Number of stored keys. Counted on the fly, so this is O(n).
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    /// Returns true if no key is stored.
+    pub fn is_empty(&self) -> bool {
+        // Nodes are never removed, and every one was created for a key
+        self.root == NIL && self.empty_value.is_none()
+    }
+
+    /// The longest stored key that is a prefix of `key`, with its value. The
+    /// empty key, if stored, matches as a last resort.
+    pub fn longest_prefix_match(&self, key: &str) -> Option<(String, &T)> {
+        let mut best = self.empty_value.as_ref().map(|value| (0, value));
+        let mut id = self.root;
+        for (i, c) in key.char_indices() {
+            while id != NIL && c != self.node(id).split {
+                let node = self.node(id);
+                id = if c < node.split { node.lo } else { node.hi };
+            }
+            if id == NIL {
+                break;
+            }
+            if let Some(value) = &self.node(id).value {
+                best = Some((i + c.len_utf8(), value));
+            }
+            id = self.node(id).eq;
+        }
+        best.map(|(len, value)| (String::from(&key[..len]), value))
+    }
+
+    /// Iterates over all entries in lexicographic key order.
+    pub fn iter(&self) -> TstIter<'_, T> {
+        TstIter {
+            trie: self,
+            stack: vec![(Step::Visit(self.root), 0)],
+            key: String::new(),
+            first: self.empty_value.as_ref(),
+        }
+    }
+
+    /// Iterates over the keys starting with `prefix`, in lexicographic order.
+    pub fn keys_with_prefix(&self, prefix: &str) -> impl Iterator<Item = String> + '_ {
+        let iter = if prefix.is_empty() {
+            self.iter()
+        } else {
+            match self.find_node(prefix) {
+                Some(id) => {
+                    let node = self.node(id);
+                    TstIter {
+                        trie: self,
+                        stack: vec![(Step::Visit(node.eq), prefix.len())],
+                        key: String::from(prefix),
+                        first: node.value.as_ref(),
+                    }
+                }
+                None => TstIter {
+                    trie: self,
+                    stack: Vec::new(),
+                    key: String::new(),
+                    first: None,
+                },
+            }
+        };
+        iter.map(|(key, _)| key)
+    }
+
+    /// Rough heap footprint in bytes: the node arena, counted by capacity.
+    /// Heap data owned by values is not measured.
+    pub fn memory_estimate(&self) -> usize {
+        self.nodes.capacity() * std::mem::size_of::<TstNode<T>>()
+    }
+}
+
+// Work item of a TstIter walk
+enum Step {
+    // Walk the subtree under a node, all of whose keys extend the key
+    // buffer's first `len` bytes
+    Visit(NodeId),
+    // Append the node's char, report its value and go down its eq link
+    Descend(NodeId),
+}
+
+/// Iterator over a [`TstTrie`]'s entries in lexicographic key order.
+pub struct TstIter<'a, T> {
+    trie: &'a TstTrie<T>,
+    // In-order walk: a node's lo subtree, its own key, its eq subtree, then
+    // its hi subtree. Each step carries the length of the key buffer it
+    // starts from.
+    stack: Vec<(Step, usize)>,
+    key: String,
+    // Value of the key the walk starts from, reported before the rest
+    first: Option<&'a T>,
+}
+
+impl<'a, T> Iterator for TstIter<'a, T> {
+    type Item = (String, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(value) = self.first.take() {
+            return Some((self.key.clone(), value));
+        }
+
+        while let Some((step, len)) = self.stack.pop() {
+            self.key.truncate(len);
+            match step {
+                Step::Visit(NIL) => {}
+                Step::Visit(id) => {
+                    let node = self.trie.node(id);
+                    self.stack.push((Step::Visit(node.hi), len));
+                    self.stack.push((Step::Descend(id), len));
+                    self.stack.push((Step::Visit(node.lo), len));
+                }
+                Step::Descend(id) => {
+                    let node = self.trie.node(id);
+                    self.key.push(node.split);
+                    self.stack.push((Step::Visit(node.eq), self.key.len()));
+                    if let Some(value) = &node.value {
+                        return Some((self.key.clone(), value));
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::XorShift64;
+    use crate::Trie;
+
+    #[test]
+    fn insert_and_query() {
+        let mut trie = TstTrie::new();
+        assert!(trie.is_empty());
+        assert_eq!(trie.iter().next(), None);
+        for (i, key) in ["cat", "cap", "car", "ca", "dog", "été", "c"]
+            .iter()
+            .enumerate()
+        {
+            assert!(trie.insert(key, i));
+        }
+        assert!(!trie.insert("ca", 9));
+        assert_eq!(trie.get("ca"), Some(&3));
+        assert_eq!(trie.get("cab"), None);
+        assert_eq!(trie.get("été"), Some(&5));
+        assert_eq!(trie.get(""), None);
+        assert_eq!(trie.len(), 7);
+
+        assert_eq!(
+            trie.iter().map(|(key, _)| key).collect::<Vec<_>>(),
+            ["c", "ca", "cap", "car", "cat", "dog", "été"]
+        );
+        assert_eq!(
+            trie.keys_with_prefix("ca").collect::<Vec<_>>(),
+            ["ca", "cap", "car", "cat"]
+        );
+        assert_eq!(trie.keys_with_prefix("cb").next(), None);
+        assert_eq!(
+            trie.longest_prefix_match("cartoon"),
+            Some((String::from("car"), &2))
+        );
+        assert_eq!(trie.longest_prefix_match("xyz"), None);
+
+        assert!(trie.insert("", 10));
+        assert!(!trie.insert("", 11));
+        assert_eq!(trie.longest_prefix_match("xyz"), Some((String::new(), &10)));
+        assert_eq!(trie.keys_with_prefix("").next(), Some(String::new()));
+    }
+
+    #[test]
+    fn matches_trie() {
+        let alphabet = ['a', 'b', 'c', 'ß', '中'];
+        let mut rng = XorShift64(0x7e57_0000_0000_0061);
+        let random_key = |rng: &mut XorShift64| -> String {
+            (0..rng.below(6)).map(|_| alphabet[rng.below(5)]).collect()
+        };
+        for _ in 0..20 {
+            let mut tst = TstTrie::new();
+            let mut trie = Trie::new();
+            for i in 0..200 {
+                let key = random_key(&mut rng);
+                match rng.below(4) {
+                    0 | 1 => assert_eq!(tst.insert(&key, i), trie.insert(&key, i)),
+                    2 => {
+                        assert_eq!(tst.get(&key), trie.get(&key));
+                        assert_eq!(
+                            tst.longest_prefix_match(&key),
+                            trie.longest_prefix_match(&key)
+                        );
+                    }
+                    _ => assert!(tst.keys_with_prefix(&key).eq(trie.keys_with_prefix(&key))),
+                }
+            }
+            assert!(tst.iter().eq(trie.iter()));
+            assert_eq!(tst.len(), trie.len());
+            assert_eq!(tst.is_empty(), trie.is_empty());
+        }
+    }
+}
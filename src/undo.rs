@@ -0,0 +1,295 @@
+use crate::trie::Trie;
+
+/// Handle to a checkpoint of an [`UndoTrie`], from
+/// [`UndoTrie::checkpoint`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CheckpointId(u64);
+
+// Inverse of one change, holding the key as it was passed in: the
+// normalizer maps it to the same node again on undo
+#[derive(Debug)]
+enum UndoEntry<T> {
+    // The key was newly inserted
+    Inserted(String),
+    // The key's value was replaced; this is the old one
+    Replaced(String, T),
+    // The key was removed, with this value
+    Removed(String, T),
+}
+
+/// A [`Trie`] that can roll back the changes made since a checkpoint, e.g.
+/// to apply a speculative batch and abandon it if validation fails,
+/// without cloning the trie up front.
+///
+/// While a checkpoint is open, every change is recorded in an undo log as
+/// its inverse: an inserted key is removed again, and a replaced or
+/// removed value is moved into the log to be restored. `T` needn't be
+/// `Clone`. With no checkpoint open, nothing is logged.
+///
+/// Checkpoints nest and are closed in LIFO order: committing or rolling
+/// back one closes the checkpoints opened after it too, so rolling back an
+/// outer checkpoint also undoes changes an inner one committed. Changes are
+/// only permanent once the outermost checkpoint is committed.
+///
+/// Changes go through the methods here; reads go through
+/// [`UndoTrie::trie`]. Rolling back restores the keys, values and key
+/// count, and prunes the nodes that inserts created.
+#[derive(Debug, Default)]
+pub struct UndoTrie<T> {
+    trie: Trie<T>,
+    log: Vec<UndoEntry<T>>,
+    // Open checkpoints, innermost last, with the log length when each was
+    // taken
+    checkpoints: Vec<(CheckpointId, usize)>,
+    next_id: u64,
+}
+
+impl<T> UndoTrie<T> {
+    /// Creates an empty trie.
+    pub fn new() -> UndoTrie<T> {
+        UndoTrie::from_trie(Trie::new())
+    }
+
+    /// Wraps an existing trie, with no checkpoint open.
+    pub fn from_trie(trie: Trie<T>) -> UndoTrie<T> {
+        UndoTrie {
+            trie,
+            log: Vec::new(),
+            checkpoints: Vec::new(),
+            next_id: 0,
+        }
+    }
+
+    /// The trie in its current state, changes since open checkpoints
+    /// included.
+    pub fn trie(&self) -> &Trie<T> {
+        &self.trie
+    }
+
+    /// Unwraps the trie in its current state, keeping all changes as if
+    /// every open checkpoint were committed.
+    pub fn into_trie(self) -> Trie<T> {
+        self.trie
+    }
+
+    /// Number of changes recorded for the open checkpoints.
+    pub fn log_len(&self) -> usize {
+        self.log.len()
+    }
+
+    fn record(&mut self, entry: UndoEntry<T>) {
+        if !self.checkpoints.is_empty() {
+            self.log.push(entry);
+        }
+    }
+
+    /// Inserts `value` under `key`, as [`Trie::insert`] does. Returns
+    /// false, leaving the trie untouched, if `key` is already stored.
+    pub fn insert(&mut self, key: &str, value: T) -> bool {
+        let inserted = self.trie.insert(key, value);
+        if inserted {
+            self.record(UndoEntry::Inserted(String::from(key)));
+        }
+        inserted
+    }
+
+    /// Stores `value` under `key`, replacing any value already there.
+    /// Returns true if a value was replaced.
+    pub fn replace(&mut self, key: &str, value: T) -> bool {
+        match self.trie.get_mut(key) {
+            Some(existing) => {
+                let old = std::mem::replace(existing, value);
+                self.record(UndoEntry::Replaced(String::from(key), old));
+                true
+            }
+            None => {
+                self.insert(key, value);
+                false
+            }
+        }
+    }
+
+    /// Removes `key`, returning whether it was removed.
+    pub fn remove(&mut self, key: &str) -> bool {
+        match self.trie.remove_if(key, |_| true) {
+            Some(value) => {
+                self.record(UndoEntry::Removed(String::from(key), value));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Opens a checkpoint, nested in those already open.
+    pub fn checkpoint(&mut self) -> CheckpointId {
+        let id = CheckpointId(self.next_id);
+        self.next_id += 1;
+        self.checkpoints.push((id, self.log.len()));
+        id
+    }
+
+    // Position of `id` among the open checkpoints
+    fn position(&self, id: CheckpointId) -> usize {
+        self.checkpoints
+            .iter()
+            .rposition(|(open, _)| *open == id)
+            .unwrap_or_else(|| panic!("checkpoint {:?} isn't open", id))
+    }
+
+    /// Undoes every change made since `id` was taken and closes it, along
+    /// with the checkpoints opened after it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` was already committed or rolled back.
+    pub fn rollback(&mut self, id: CheckpointId) {
+        let position = self.position(id);
+        let (_, log_len) = self.checkpoints[position];
+        self.checkpoints.truncate(position);
+        // Newest first, so each entry sees the state right after its change
+        for entry in self.log.drain(log_len..).rev() {
+            match entry {
+                UndoEntry::Inserted(key) => {
+                    self.trie.remove_if(&key, |_| true);
+                }
+                UndoEntry::Replaced(key, value) => *self.trie.get_mut(&key).unwrap() = value,
+                UndoEntry::Removed(key, value) => {
+                    self.trie.insert(&key, value);
+                }
+            }
+        }
+    }
+
+    /// Keeps the changes made since `id` was taken and closes it, along
+    /// with the checkpoints opened after it. Until the outermost checkpoint
+    /// is committed, an outer rollback can still undo them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` was already committed or rolled back.
+    pub fn commit(&mut self, id: CheckpointId) {
+        let position = self.position(id);
+        self.checkpoints.truncate(position);
+        if self.checkpoints.is_empty() {
+            self.log.clear();
+        }
+    }
+}
+
+impl<T> From<Trie<T>> for UndoTrie<T> {
+    fn from(trie: Trie<T>) -> UndoTrie<T> {
+        UndoTrie::from_trie(trie)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::XorShift64;
+
+    fn entries(trie: &Trie<u32>) -> Vec<(String, u32)> {
+        trie.iter().map(|(key, value)| (key, *value)).collect()
+    }
+
+    #[test]
+    fn rollback_and_commit() {
+        let mut undo = UndoTrie::new();
+        undo.insert("tea", 1);
+        undo.insert("ten", 2);
+        assert_eq!(undo.log_len(), 0);
+
+        let outer = undo.checkpoint();
+        let at_outer = entries(undo.trie());
+        assert!(undo.insert("team", 3));
+        assert!(!undo.insert("tea", 10));
+        assert!(undo.replace("tea", 4));
+        assert!(undo.remove("ten"));
+
+        let inner = undo.checkpoint();
+        let at_inner = entries(undo.trie());
+        assert!(undo.insert("to", 5));
+        assert!(!undo.replace("inn", 6));
+        assert!(undo.remove("team"));
+        assert!(!undo.remove("nothing"));
+        undo.rollback(inner);
+        assert_eq!(entries(undo.trie()), at_inner);
+
+        let inner = undo.checkpoint();
+        undo.insert("to", 7);
+        undo.commit(inner);
+        assert_eq!(undo.trie().get("to"), Some(&7));
+        assert_eq!(undo.log_len(), 4);
+
+        // The outer rollback undoes the committed inner changes too, and
+        // the nodes they created
+        undo.rollback(outer);
+        assert_eq!(entries(undo.trie()), at_outer);
+        assert_eq!(undo.trie().len(), 2);
+        assert_eq!(undo.log_len(), 0);
+        assert!(!undo.trie().root().child('t').unwrap().has_child('o'));
+
+        let outer = undo.checkpoint();
+        undo.checkpoint();
+        undo.remove("tea");
+        // Committing the outer checkpoint closes the inner one too
+        undo.commit(outer);
+        assert_eq!(undo.log_len(), 0);
+        let trie = undo.into_trie();
+        assert_eq!(entries(&trie), [(String::from("ten"), 2)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "isn't open")]
+    fn closed_checkpoint() {
+        let mut undo = UndoTrie::<u32>::new();
+        let outer = undo.checkpoint();
+        let inner = undo.checkpoint();
+        undo.rollback(outer);
+        undo.commit(inner);
+    }
+
+    #[test]
+    fn matches_clones() {
+        let mut rng = XorShift64(0x0dd0_0000_0000_0085);
+        let mut undo = UndoTrie::from(Trie::new_with_normalizer(|key| key.to_lowercase()));
+        // Open checkpoints with the entries at the time each was taken
+        let mut open: Vec<(CheckpointId, Vec<(String, u32)>)> = Vec::new();
+        for i in 0..3000 {
+            let key: String = (0..rng.below(4))
+                .map(|_| char::from(b'a' + rng.below(3) as u8))
+                .collect();
+            let key = if rng.below(2) == 0 {
+                key.to_uppercase()
+            } else {
+                key
+            };
+            match rng.below(10) {
+                0..=2 => {
+                    undo.insert(&key, i);
+                }
+                3 | 4 => {
+                    undo.replace(&key, i);
+                }
+                5 | 6 => {
+                    undo.remove(&key);
+                }
+                7 => open.push((undo.checkpoint(), entries(undo.trie()))),
+                _ if open.is_empty() => {}
+                8 => {
+                    // Closes the checkpoints opened after it too
+                    let (id, at) = open.drain(rng.below(open.len())..).next().unwrap();
+                    undo.rollback(id);
+                    assert_eq!(entries(undo.trie()), at);
+                    assert_eq!(undo.trie().len(), at.len());
+                }
+                _ => {
+                    let (id, _) = open.drain(rng.below(open.len())..).next().unwrap();
+                    undo.commit(id);
+                }
+            }
+            if open.is_empty() {
+                assert_eq!(undo.log_len(), 0);
+            }
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use std::collections::hash_map::RandomState;
+
+use crate::GenericTrie;
+
+/// Trie for ranked autocomplete where the values are their own weights,
+/// e.g. hit counts, or `(score, timestamp)` pairs to break ties.
+/// [`WeightedTrie::top_k_completions`] finds the `k` greatest values under
+/// a prefix without visiting the rest.
+///
+/// A thin wrapper over a [`GenericTrie`] whose priorities are the weights,
+/// stored with unit values: every node tracks the greatest weight in its
+/// subtree, and the best-first search of
+/// [`GenericTrie::top_k_by_prefix`] leaves alone any subtree that can't
+/// beat the results it already has. Unlike the priorities of
+/// [`Trie::insert_with_priority`](crate::Trie::insert_with_priority), which
+/// are `f64`, any `Ord` type works as a weight, such as `u64` beyond 2^53
+/// or strings.
+///
+/// Inserting raises the tracked maxima along the key's path, cloning the
+/// value into those it exceeds. Removing the current maximum of a subtree
+/// has to find the next greatest, so removal recomputes each maximum on the
+/// path from the node's children, bottom-up: O(depth · fanout).
+#[derive(Debug)]
+pub struct WeightedTrie<T> {
+    inner: GenericTrie<char, (), RandomState, T>,
+}
+
+impl<T> Default for WeightedTrie<T> {
+    fn default() -> WeightedTrie<T> {
+        WeightedTrie::new()
+    }
+}
+
+impl<T> WeightedTrie<T> {
+    /// Creates an empty trie.
+    pub fn new() -> WeightedTrie<T> {
+        WeightedTrie {
+            inner: GenericTrie::default(),
+        }
+    }
+}
+
+impl<T: Ord + Clone> WeightedTrie<T> {
+    /// The value stored under `key`, if any.
+    pub fn get(&self, key: &str) -> Option<&T> {
+        self.inner.priority(key.chars())
+    }
+
+    /// Returns true if `key` is stored in the trie.
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// The greatest value stored under a key starting with `prefix`, read
+    /// off the prefix's node in one walk down.
+    pub fn max_with_prefix(&self, prefix: &str) -> Option<&T> {
+        self.inner.max_priority_with_prefix(prefix.chars())
+    }
+
+    /// Number of stored keys.
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns true if no key is stored.
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Inserts `value` under `key`. Returns false, leaving the trie
+    /// untouched, if `key` is already stored.
+    pub fn insert(&mut self, key: &str, value: T) -> bool {
+        self.inner.insert_with_priority(key.chars(), (), value)
+    }
+
+    /// Removes `key`, returning its value. Nodes left without values or
+    /// children are pruned, and the maxima along the path are recomputed.
+    pub fn remove(&mut self, key: &str) -> Option<T> {
+        self.inner
+            .remove_with_priority(key.chars())
+            .and_then(|(_, value)| value)
+    }
+
+    /// The (at most) `k` keys starting with `prefix` that have the greatest
+    /// values, greatest first, with their values. Equal values are in
+    /// lexicographic key order.
+    pub fn top_k_completions(&self, prefix: &str, k: usize) -> Vec<(String, &T)> {
+        self.inner
+            .top_k_nodes(prefix.chars(), k)
+            .into_iter()
+            .map(|(key, node)| (key.into_iter().collect(), node.priority().unwrap()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{check_max_priorities, XorShift64};
+
+    #[test]
+    fn weighted_completions() {
+        let mut trie = WeightedTrie::new();
+        // Beyond what an f64 holds exactly
+        let big = 1u64 << 60;
+        for (key, weight) in [
+            ("rust", big + 3),
+            ("rust book", big + 1),
+            ("rust analyzer", big + 2),
+            ("rustup", big + 2),
+            ("ruby", 80),
+            ("python", big + 4),
+        ] {
+            assert!(trie.insert(key, weight));
+            check_max_priorities(&trie.inner.root);
+        }
+        assert!(!trie.insert("rust", 0));
+        assert_eq!(trie.get("rust"), Some(&(big + 3)));
+        assert_eq!(trie.max_with_prefix(""), Some(&(big + 4)));
+        assert_eq!(trie.max_with_prefix("rust "), Some(&(big + 2)));
+        assert_eq!(trie.max_with_prefix("java"), None);
+
+        assert_eq!(
+            trie.top_k_completions("ru", 3),
+            [
+                (String::from("rust"), &(big + 3)),
+                (String::from("rust analyzer"), &(big + 2)),
+                (String::from("rustup"), &(big + 2)),
+            ]
+        );
+        assert!(trie.top_k_completions("ru", 0).is_empty());
+        assert!(trie.top_k_completions("java", 3).is_empty());
+
+        // Removing a subtree's maximum finds the next greatest
+        assert_eq!(trie.remove("python"), Some(big + 4));
+        assert_eq!(trie.remove("python"), None);
+        assert_eq!(trie.max_with_prefix(""), Some(&(big + 3)));
+        assert!(trie.inner.root.child('p').is_none());
+        assert_eq!(trie.remove("rust"), Some(big + 3));
+        assert_eq!(trie.max_with_prefix("rust"), Some(&(big + 2)));
+        check_max_priorities(&trie.inner.root);
+        assert_eq!(trie.len(), 4);
+
+        // Tuples order by score, then by their second field
+        let mut trie = WeightedTrie::new();
+        trie.insert("tea", (2, "b"));
+        trie.insert("ten", (2, "a"));
+        trie.insert("to", (1, "z"));
+        assert_eq!(
+            trie.top_k_completions("t", 1),
+            [(String::from("tea"), &(2, "b"))]
+        );
+    }
+
+    #[test]
+    fn matches_sorting() {
+        let mut rng = XorShift64(0x7091_0000_0000_0099);
+        let mut trie = WeightedTrie::new();
+        let mut entries: Vec<(String, u64)> = Vec::new();
+        for _ in 0..600 {
+            let key: String = (0..rng.below(6))
+                .map(|_| char::from(b'a' + rng.below(4) as u8))
+                .collect();
+            // Few distinct weights, to exercise ties
+            let weight = rng.below(8) as u64;
+            if rng.below(4) == 0 {
+                if trie.remove(&key).is_some() {
+                    entries.retain(|(k, _)| *k != key);
+                }
+            } else if trie.insert(&key, weight) {
+                entries.push((key, weight));
+            }
+            check_max_priorities(&trie.inner.root);
+        }
+
+        for prefix in ["", "a", "b", "ab", "dd", "abc"] {
+            let mut expected: Vec<&(String, u64)> = entries
+                .iter()
+                .filter(|(key, _)| key.starts_with(prefix))
+                .collect();
+            expected.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            for k in [1, 5, 40, 1000] {
+                let want: Vec<(String, &u64)> = expected
+                    .iter()
+                    .take(k)
+                    .map(|(key, weight)| (key.clone(), weight))
+                    .collect();
+                assert_eq!(
+                    trie.top_k_completions(prefix, k),
+                    want,
+                    "{:?} {}",
+                    prefix,
+                    k
+                );
+            }
+        }
+        assert_eq!(trie.len(), entries.len());
+    }
+
+    #[test]
+    fn deep_key() {
+        std::thread::spawn(|| {
+            let key = "a".repeat(1_000_000);
+            let mut trie = WeightedTrie::new();
+            trie.insert(&key, 1);
+            trie.insert(&key[..10], 2);
+            assert_eq!(trie.max_with_prefix(&key[..100]), Some(&1));
+            assert_eq!(trie.remove(&key), Some(1));
+            assert_eq!(trie.max_with_prefix(""), Some(&2));
+            trie.insert(&key, 3);
+        })
+        .join()
+        .unwrap();
+    }
+}
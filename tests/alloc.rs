@@ -0,0 +1,82 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use bustub::Trie;
+
+// Counts the allocations made by the current thread, so that tests running
+// in parallel don't disturb each other's counts
+struct CountingAlloc;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+fn allocations_during<F: FnOnce()>(f: F) -> usize {
+    let before = ALLOCATIONS.with(Cell::get);
+    f();
+    ALLOCATIONS.with(Cell::get) - before
+}
+
+#[test]
+fn for_each_entry_doesnt_allocate_per_key() {
+    let mut trie = Trie::new();
+    let letters = || 'a'..='j';
+    for x in letters() {
+        for y in letters() {
+            for z in letters() {
+                trie.insert(&format!("{}{}{}", x, y, z), 1);
+            }
+        }
+    }
+
+    let mut sum = 0;
+    let scan = allocations_during(|| trie.for_each_entry(|key, value| sum += key.len() * value));
+    assert_eq!(sum, 3000);
+    // The stack and key buffer, grown a few times; the nodes are all small,
+    // so none has to be sorted
+    assert!(scan < 20, "{} allocations", scan);
+
+    let mut total = 0;
+    let iter = allocations_during(|| total = trie.iter().map(|(key, _)| key.len()).sum());
+    assert_eq!(total, 3000);
+    assert!(iter >= 1000, "{} allocations", iter);
+}
+
+#[test]
+fn keys_with_prefix_limited_stops_at_the_limit() {
+    let mut trie = Trie::new();
+    let letters = || 'a'..='z';
+    for x in letters() {
+        for y in letters() {
+            for z in letters() {
+                trie.insert(&format!("{}{}{}", x, y, z), ());
+            }
+        }
+    }
+
+    // The walk allocates a String for every key it reaches, so one that
+    // went on past the limit would allocate at least once per key
+    let mut keys = Vec::new();
+    let limited = allocations_during(|| keys = trie.keys_with_prefix_limited("", 3));
+    assert_eq!(keys, ["aaa", "aab", "aac"]);
+    assert!(limited < 200, "{} allocations", limited);
+
+    let mut count = 0;
+    let all = allocations_during(|| count = trie.keys_with_prefix("").count());
+    assert_eq!(count, 17576);
+    assert!(all > 17576, "{} allocations", all);
+}
@@ -0,0 +1,203 @@
+use std::hash::{BuildHasherDefault, Hasher};
+
+use bustub::{ArenaTrie, AsciiKeyError, AsciiTrie, GenericTrie, Trie, TrieError, TrieNode};
+
+fn sample() -> Trie<u32> {
+    let mut trie = Trie::new();
+    for (i, key) in ["to", "tea", "ted", "ten", "i", "in", "inn", "a"]
+        .iter()
+        .enumerate()
+    {
+        assert!(trie.insert(key, i as u32));
+    }
+    trie
+}
+
+#[test]
+fn insert_get_remove() {
+    let mut trie = sample();
+    assert_eq!(trie.get("ted"), Some(&2));
+    assert_eq!(trie.get("te"), None);
+    assert!(trie.contains_key("inn"));
+    assert!(!trie.insert("tea", 10));
+
+    *trie.get_mut("tea").unwrap() += 10;
+    assert_eq!(trie.get("tea"), Some(&11));
+
+    assert!(trie.remove("inn"));
+    assert!(!trie.contains_key("inn"));
+    assert!(trie.contains_key("in"));
+    assert!(!trie.remove("x"));
+}
+
+#[test]
+fn try_insert_errors() {
+    let mut trie = sample();
+    let err = trie.try_insert("tea", 10).unwrap_err();
+    assert_eq!(err, TrieError::DuplicateKey);
+    let err: Box<dyn std::error::Error> = Box::new(err);
+    assert_eq!(err.to_string(), "key already has a value");
+    // The empty key is valid, so it fails only as a duplicate
+    assert_eq!(trie.try_insert("", 10), Ok(()));
+    assert_eq!(trie.try_insert("", 11), Err(TrieError::DuplicateKey));
+    assert_eq!(trie.try_insert("tee", 10), Ok(()));
+}
+
+#[test]
+fn ordered_queries() {
+    let trie = sample();
+    let keys: Vec<String> = trie.iter().map(|(key, _)| key).collect();
+    assert_eq!(keys, ["a", "i", "in", "inn", "tea", "ted", "ten", "to"]);
+    assert_eq!(
+        trie.keys_with_prefix("te").collect::<Vec<_>>(),
+        ["tea", "ted", "ten"]
+    );
+    assert_eq!(
+        trie.range("in", "te")
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect::<Vec<_>>(),
+        ["in", "inn"]
+    );
+    assert_eq!(trie.first_key(), Some(String::from("a")));
+    assert_eq!(trie.last_key(), Some(String::from("to")));
+    assert_eq!(trie.rank("tea"), Some(4));
+    assert_eq!(trie.select(4), Some(String::from("tea")));
+}
+
+#[test]
+fn read_only_nodes() {
+    let trie = sample();
+    let root: &TrieNode<u32> = trie.root();
+    assert_eq!(root.value(), None);
+    assert_eq!(root.count_values(), 8);
+
+    let t = root.child('t').unwrap();
+    assert_eq!(t.key_char(), 't');
+    let children: Vec<char> = t
+        .sorted_children()
+        .iter()
+        .map(|child| child.key_char())
+        .collect();
+    assert_eq!(children, ['e', 'o']);
+    assert_eq!(t.child('o').unwrap().value(), Some(&0));
+}
+
+#[test]
+#[allow(deprecated)]
+fn deprecated_aliases() {
+    let mut trie = sample();
+    assert_eq!(trie.get_value("ten"), trie.get("ten"));
+    *trie.get_value_mut("ten").unwrap() = 7;
+    assert_eq!(trie.get("ten"), Some(&7));
+
+    let i = trie.root().get_child('i').unwrap();
+    assert_eq!(i.get_key_char(), 'i');
+}
+
+#[test]
+fn generic_keys() {
+    let mut tokens = GenericTrie::new();
+    assert!(tokens.insert([101u32, 2023, 102], "cls this sep"));
+    assert!(tokens.insert([101u32, 2045], "cls there"));
+    assert_eq!(tokens.get([101, 2045]), Some(&"cls there"));
+    assert_eq!(tokens.iter_prefix([101]).count(), 2);
+    assert!(tokens.remove([101, 2023, 102]));
+    assert_eq!(
+        tokens.iter().collect::<Vec<_>>(),
+        vec![(vec![101, 2045], &"cls there")]
+    );
+
+    let mut paths = GenericTrie::new();
+    let path = |p: &str| p.split('/').map(String::from).collect::<Vec<_>>();
+    assert!(paths.insert(path("usr/local/bin"), 1));
+    assert!(paths.insert(path("usr/lib"), 2));
+    assert_eq!(paths.get(path("usr/lib")), Some(&2));
+    assert!(!paths.contains_key(path("usr/local")));
+    assert_eq!(
+        paths.iter_prefix(path("usr/local")).collect::<Vec<_>>(),
+        vec![(path("usr/local/bin"), &1)]
+    );
+    assert!(paths.remove(path("usr/local/bin")));
+    assert_eq!(paths.len(), 1);
+}
+
+#[test]
+fn other_tries() {
+    let mut ascii = AsciiTrie::new();
+    assert_eq!(ascii.insert("cat", 1), Ok(true));
+    assert_eq!(ascii.get("cat"), Ok(Some(&1)));
+    assert_eq!(
+        ascii.insert("Cat", 2),
+        Err(AsciiKeyError::InvalidChar { ch: 'C', index: 0 })
+    );
+
+    let mut arena = ArenaTrie::new();
+    assert!(arena.insert("cat", 1));
+    assert!(arena.remove("cat"));
+    assert_eq!(arena.iter().count(), 0);
+
+    let mut set = Trie::new();
+    for word in ["tapping", "tapped", "topping", "topped"] {
+        set.insert(word, ());
+    }
+    let dawg = set.minimize();
+    assert!(dawg.contains("topped"));
+    assert!(!dawg.contains("top"));
+}
+
+// Multiply-rotate hasher in the style of FxHash: fast, and plenty for chars
+#[derive(Default)]
+struct CharHasher(u64);
+
+impl Hasher for CharHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.write_u64(u64::from(byte));
+        }
+    }
+
+    fn write_u32(&mut self, n: u32) {
+        self.write_u64(u64::from(n));
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.0 = (self.0.rotate_left(5) ^ n).wrapping_mul(0x51_7c_c1_b7_27_22_0a_95);
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+#[test]
+fn custom_hasher() {
+    let mut fast: Trie<usize, BuildHasherDefault<CharHasher>> = Trie::default();
+    let mut default = Trie::new();
+    // Wide nodes, so children end up in hash maps
+    let keys: Vec<String> = ('a'..='z')
+        .chain('À'..='Ö')
+        .flat_map(|c| {
+            ('0'..='9')
+                .chain('a'..='z')
+                .map(move |d| format!("{}{}", c, d))
+        })
+        .collect();
+    for (i, key) in keys.iter().enumerate() {
+        assert_eq!(fast.insert(key, i), default.insert(key, i));
+    }
+    for key in keys.iter().step_by(3) {
+        assert_eq!(fast.remove(key), default.remove(key));
+    }
+
+    assert_eq!(fast.len(), default.len());
+    assert!(fast.iter().eq(default.iter()));
+    for key in ["a0", "a1", "Öz", "zz", "", "b"] {
+        assert_eq!(fast.get(key), default.get(key));
+    }
+    assert_eq!(
+        fast.keys_with_prefix("Ö").collect::<Vec<_>>(),
+        default.keys_with_prefix("Ö").collect::<Vec<_>>()
+    );
+    assert_eq!(fast.stats(), default.stats());
+}